@@ -1,24 +1,64 @@
 #import indirect_instancing::instance_struct
 #import indirect_instancing::indirect_struct
 #import indirect_instancing::color_instance_struct
+#import bevy_pbr::mesh_view_types
 
-struct UniformData {
-    time: f32,
+struct BoidState {
+    position: vec3<f32>,
+    velocity: vec3<f32>,
 };
 
-@group(0)
+#ifdef SOA_INSTANCES
+@group(1)
 @binding(0)
-var<uniform> in_uniform: UniformData;
+var<storage, read_write> out_transforms: array<mat4x4<f32>>;
+
+@group(1)
+@binding(1)
+var<storage, read_write> out_inverse_transpose_models: array<mat4x4<f32>>;
 
+@group(1)
+@binding(2)
+var<storage, read_write> out_colors: array<vec4<f32>>;
+#else
 @group(1)
 @binding(0)
 var<storage, read_write> out_instances: ColorInstances;
+#endif
+
+@group(2)
+@binding(0)
+var<storage> in_state: array<BoidState>;
+
+@group(2)
+@binding(1)
+var<storage, read_write> out_state: array<BoidState>;
+
+@group(3)
+@binding(0)
+var<uniform> globals: Globals;
+
+let NEIGHBOUR_RADIUS: f32 = 8.0;
+let SEPARATION_RADIUS: f32 = 2.0;
+let BOUNDS: f32 = 30.0;
+let MAX_SPEED: f32 = 10.0;
+
+fn hash(n: u32) -> f32 {
+    var x = n * 747796405u + 2891336453u;
+    x = ((x >> ((x >> 28u) + 4u)) ^ x) * 277803737u;
+    x = (x >> 22u) ^ x;
+    return f32(x) / 4294967295.0;
+}
 
 @compute
 @workgroup_size(64)
 fn instances(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
     // Calculate maximum indices
+#ifdef SOA_INSTANCES
+    let max_instance = arrayLength(&out_transforms);
+#else
     let max_instance = arrayLength(&out_instances.instances);
+#endif
 
     // Destructure invocation index
     let instance_idx = invocation_id.x;
@@ -28,22 +68,77 @@ fn instances(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
         return;
     }
 
-    let f = f32(instance_idx) / f32(max_instance);
+    var boid = in_state[instance_idx];
 
-    let frequency = f * 3.141 * 0.1;
-    let amplitude = f * 3.141 * 0.5;
-    let scale = f * 25.0;
+    // State buffers start zeroed, so scatter boids on the first dispatch
+    if (all(boid.velocity == vec3<f32>(0.0))) {
+        let seed = instance_idx * 6u;
+        boid.position = (vec3<f32>(hash(seed), hash(seed + 1u), hash(seed + 2u)) * 2.0 - 1.0) * BOUNDS;
+        boid.velocity = vec3<f32>(hash(seed + 3u), hash(seed + 4u), hash(seed + 5u)) * 2.0 - 1.0;
+    }
 
-    let fac = sin(in_uniform.time * frequency) * amplitude;
+    // Accumulate neighbour influence from the previous dispatch's state
+    var center = vec3<f32>(0.0);
+    var heading = vec3<f32>(0.0);
+    var separation = vec3<f32>(0.0);
+    var neighbours = 0u;
 
-    let pos = ((vec3<f32>(0.0, 1.0, 0.0) * sin(fac)) + (vec3<f32>(0.0, 0.0, 1.0) * cos(fac))) * scale;
+    for (var i = 0u; i < max_instance; i = i + 1u) {
+        if (i == instance_idx) {
+            continue;
+        }
+
+        let other = in_state[i];
+        let offset = other.position - boid.position;
+        let dist = length(offset);
+
+        if (dist < NEIGHBOUR_RADIUS) {
+            center += other.position;
+            heading += other.velocity;
+            neighbours = neighbours + 1u;
+
+            if (dist < SEPARATION_RADIUS && dist > 0.0) {
+                separation -= offset / dist;
+            }
+        }
+    }
+
+    var acceleration = separation * 1.5;
+    if (neighbours > 0u) {
+        let n = f32(neighbours);
+        acceleration += (center / n - boid.position) * 0.05;
+        acceleration += (heading / n - boid.velocity) * 0.1;
+    }
+
+    // Steer back toward the origin when leaving the bounds
+    acceleration -= boid.position * step(BOUNDS, length(boid.position)) * 0.1;
+
+    boid.velocity += acceleration * globals.delta_time;
+    let speed = length(boid.velocity);
+    if (speed > MAX_SPEED) {
+        boid.velocity *= MAX_SPEED / speed;
+    }
+    boid.position += boid.velocity * globals.delta_time;
+
+    out_state[instance_idx] = boid;
 
     // Write instance transform
-    out_instances.instances[instance_idx].base.transform = mat4x4<f32>(
+    let transform = mat4x4<f32>(
         vec4<f32>(1.0, 0.0, 0.0, 0.0),
         vec4<f32>(0.0, 1.0, 0.0, 0.0),
         vec4<f32>(0.0, 0.0, 1.0, 0.0),
-        vec4<f32>(pos, 1.0),
+        vec4<f32>(boid.position, 1.0),
+    );
+    let color = vec4<f32>(
+        normalize(abs(boid.velocity) + vec3<f32>(0.001)),
+        0.5,
     );
-    out_instances.instances[instance_idx].color = vec4<f32>(vec3<f32>(1.0), abs(f));
+
+#ifdef SOA_INSTANCES
+    out_transforms[instance_idx] = transform;
+    out_colors[instance_idx] = color;
+#else
+    out_instances.instances[instance_idx].base.transform = transform;
+    out_instances.instances[instance_idx].color = color;
+#endif
 }
@@ -0,0 +1,126 @@
+#import bevy_pbr::mesh_view_bindings
+
+#ifdef AFFINE_INSTANCE
+#import indirect_instancing::affine_instance_struct
+#endif
+#ifdef TRS_INSTANCE
+#import indirect_instancing::trs_instance_struct
+#endif
+#ifdef QUANTIZED_INSTANCE
+#import indirect_instancing::quantized_instance_struct
+#endif
+
+// InstanceOrigin shared by the material's quantized instances
+@group(1)
+@binding(0)
+var<uniform> origin: vec3<f32>;
+
+#ifndef VERTEX_INSTANCES
+#ifndef SOA_INSTANCES
+#ifdef AFFINE_INSTANCE
+#ifdef NO_STORAGE_BUFFERS_SUPPORT
+@group(2)
+@binding(0)
+var<uniform> instances: AffineInstances;
+#else
+@group(2)
+@binding(0)
+var<storage> instances: AffineInstances;
+#endif
+#endif
+#ifdef TRS_INSTANCE
+#ifdef NO_STORAGE_BUFFERS_SUPPORT
+@group(2)
+@binding(0)
+var<uniform> instances: TrsInstances;
+#else
+@group(2)
+@binding(0)
+var<storage> instances: TrsInstances;
+#endif
+#endif
+#ifdef QUANTIZED_INSTANCE
+#ifdef NO_STORAGE_BUFFERS_SUPPORT
+@group(2)
+@binding(0)
+var<uniform> instances: QuantizedInstances;
+#else
+@group(2)
+@binding(0)
+var<storage> instances: QuantizedInstances;
+#endif
+#endif
+#endif
+#endif
+
+struct VertexInput {
+    @builtin(instance_index) instance: u32,
+    @location(0) vertex: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+#ifdef VERTEX_INSTANCES
+#ifdef AFFINE_INSTANCE
+fn vertex(in: VertexInput, instance_in: AffineInstanceVertex) -> VertexOutput {
+    let instance = affine_instance_from_vertex(instance_in);
+#endif
+#ifdef TRS_INSTANCE
+fn vertex(in: VertexInput, instance_in: TrsInstanceVertex) -> VertexOutput {
+    let instance = trs_instance_from_vertex(instance_in);
+#endif
+#ifdef QUANTIZED_INSTANCE
+fn vertex(in: VertexInput, instance_in: QuantizedInstanceVertex) -> VertexOutput {
+    let instance = quantized_instance_from_vertex(instance_in);
+#endif
+#else
+fn vertex(in: VertexInput) -> VertexOutput {
+#ifdef SOA_INSTANCES
+#ifdef AFFINE_INSTANCE
+    let instance = load_affine_instance(in.instance);
+#endif
+#ifdef TRS_INSTANCE
+    let instance = load_trs_instance(in.instance);
+#endif
+#ifdef QUANTIZED_INSTANCE
+    let instance = load_quantized_instance(in.instance);
+#endif
+#else
+    let instance = instances.instances[in.instance];
+#endif
+#endif
+
+#ifdef AFFINE_INSTANCE
+    let transform = affine_instance_transform(instance);
+    let normal_matrix = affine_instance_normal_matrix(instance);
+    let color = vec4<f32>(0.9, 0.4, 0.2, 1.0);
+#endif
+#ifdef TRS_INSTANCE
+    let transform = trs_instance_transform(instance);
+    let normal_matrix = trs_instance_normal_matrix(instance);
+    let color = vec4<f32>(0.2, 0.8, 0.4, 1.0);
+#endif
+#ifdef QUANTIZED_INSTANCE
+    let transform = quantized_instance_transform(instance, origin);
+    let normal_matrix = quantized_instance_normal_matrix(instance);
+    let color = quantized_instance_color(instance);
+#endif
+
+    var out: VertexOutput;
+    out.clip_position = view.view_proj * transform * vec4<f32>(in.vertex, 1.0);
+    out.normal = normalize(normal_matrix * in.normal);
+    out.color = color;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let light = 0.4 + 0.6 * max(dot(in.normal, normalize(vec3<f32>(0.5, 1.0, 0.25))), 0.0);
+    return vec4<f32>(in.color.rgb * light, 1.0);
+}
@@ -0,0 +1,65 @@
+#import bevy_pbr::mesh_view_bindings
+#import derive_instance::pulse_instance
+
+@group(1)
+@binding(0)
+var<uniform> glow: vec4<f32>;
+
+#ifndef VERTEX_INSTANCES
+#ifndef SOA_INSTANCES
+#ifdef NO_STORAGE_BUFFERS_SUPPORT
+@group(2)
+@binding(0)
+var<uniform> instances: PulseInstances;
+#else
+@group(2)
+@binding(0)
+var<storage> instances: PulseInstances;
+#endif
+#endif
+#endif
+
+struct VertexInput {
+    @builtin(instance_index) instance: u32,
+    @location(0) vertex: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+#ifdef VERTEX_INSTANCES
+fn vertex(
+    in: VertexInput,
+    base_in: InstanceVertex,
+    instance_in: PulseInstanceVertex,
+) -> VertexOutput {
+    let instance = pulse_instance_from_vertex(base_in, instance_in);
+#else
+fn vertex(in: VertexInput) -> VertexOutput {
+#ifdef SOA_INSTANCES
+    let instance = load_pulse_instance(in.instance);
+#else
+    let instance = instances.instances[in.instance];
+#endif
+#endif
+
+    let pulse = 0.5 + 0.5 * sin(globals.time * 3.0 + instance.phase);
+
+    var out: VertexOutput;
+    let world_position = instance.base.transform * vec4<f32>(in.vertex * mix(0.6, 1.0, pulse), 1.0);
+    out.clip_position = view.view_proj * world_position;
+    out.normal = normalize((instance.base.inverse_transpose_model * vec4<f32>(in.normal, 0.0)).xyz);
+    out.color = mix(instance.color, glow, pulse * glow.a);
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let light = 0.4 + 0.6 * max(dot(in.normal, normalize(vec3<f32>(0.5, 1.0, 0.25))), 0.0);
+    return vec4<f32>(in.color.rgb * light, 1.0);
+}
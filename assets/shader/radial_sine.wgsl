@@ -17,15 +17,33 @@ struct UniformData {
 @binding(0)
 var<uniform> in_uniform: UniformData;
 
+#ifdef SOA_INSTANCES
+@group(1)
+@binding(0)
+var<storage, read_write> out_transforms: array<mat4x4<f32>>;
+
+@group(1)
+@binding(1)
+var<storage, read_write> out_inverse_transpose_models: array<mat4x4<f32>>;
+
+@group(1)
+@binding(2)
+var<storage, read_write> out_colors: array<vec4<f32>>;
+#else
 @group(1)
 @binding(0)
 var<storage, read_write> out_instances: ColorInstances;
+#endif
 
 @compute
 @workgroup_size(64)
 fn instances(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
     // Calculate maximum indices
+#ifdef SOA_INSTANCES
+    let max_instance = arrayLength(&out_transforms);
+#else
     let max_instance = arrayLength(&out_instances.instances);
+#endif
 
     // Destructure invocation index
     let instance_idx = invocation_id.x;
@@ -46,11 +64,19 @@ fn instances(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
     let pos = ((in_uniform.normal * sin(fac)) + (in_uniform.tangent * cos(fac))) * scale;
 
     // Write instance transform
-    out_instances.instances[instance_idx].base.transform = mat4x4<f32>(
+    let transform = mat4x4<f32>(
         vec4<f32>(1.0, 0.0, 0.0, 0.0),
         vec4<f32>(0.0, 1.0, 0.0, 0.0),
         vec4<f32>(0.0, 0.0, 1.0, 0.0),
         vec4<f32>(pos, 1.0),
     );
-    out_instances.instances[instance_idx].color = vec4<f32>(in_uniform.tint, abs(f));
+    let color = vec4<f32>(in_uniform.tint, abs(f));
+
+#ifdef SOA_INSTANCES
+    out_transforms[instance_idx] = transform;
+    out_colors[instance_idx] = color;
+#else
+    out_instances.instances[instance_idx].base.transform = transform;
+    out_instances.instances[instance_idx].color = color;
+#endif
 }
@@ -0,0 +1,424 @@
+//! Derive macros for `bevy-instancing`
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{
+    parse::ParseStream, parse_macro_input, punctuated::Punctuated, Data, DeriveInput, Error,
+    Fields, Ident, LitStr, Token, Type,
+};
+
+/// Implements `Instance` and `InstanceWgsl` for a struct of per-instance data,
+/// alongside a matching `Gpu`-prefixed `ShaderType` struct and WGSL import.
+///
+/// ```ignore
+/// #[derive(Debug, Default, Clone, Component, Instance)]
+/// #[instance(import_path = "my_game::tinted_instance")]
+/// pub struct TintedInstance {
+///     #[instance(base)]
+///     pub base: MeshInstance,
+///     #[instance(component = InstanceColor)]
+///     pub color: Vec4,
+/// }
+/// ```
+///
+/// Exactly one field must be marked `#[instance(base)]` and come first; it supplies the mesh
+/// index and transform, and its type must be its own `Instance::ExtractedInstance`.
+/// Every other field is read from the named component via `From`, and must implement
+/// `InstanceFieldWgsl` so that its WGSL type is known.
+///
+/// Per-instance vertex attributes are generated in field order, one location per field
+/// and one per matrix column, starting after those of the base.
+/// Under `VERTEX_INSTANCES` the vertex accessor takes the base's vertex structs,
+/// followed by a struct holding the remaining fields.
+///
+/// Struct-level options:
+/// - `import_path` (required): the `#define_import_path` of the generated WGSL
+/// - `struct_name`: WGSL name of the per-instance struct, defaults to `{Name}Data`
+/// - `array_name`: WGSL name of the array wrapper, defaults to `{Name}s`
+/// - `soa_accessor`: WGSL function loading an instance from its streams,
+///   defaults to `load_{name}`
+/// - `vertex_struct`: WGSL name of the per-instance vertex input struct, defaults to `{Name}Vertex`
+/// - `vertex_accessor`: WGSL function building an instance from its vertex inputs,
+///   defaults to `{name}_from_vertex`
+#[proc_macro_derive(Instance, attributes(instance))]
+pub fn derive_instance(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match instance(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+enum FieldKind {
+    Base,
+    Component(Box<Type>),
+}
+
+struct InstanceField {
+    ident: Ident,
+    ty: Type,
+    kind: FieldKind,
+}
+
+#[derive(Default)]
+struct InstanceAttributes {
+    import_path: Option<LitStr>,
+    struct_name: Option<LitStr>,
+    array_name: Option<LitStr>,
+    soa_accessor: Option<LitStr>,
+    vertex_struct: Option<LitStr>,
+    vertex_accessor: Option<LitStr>,
+}
+
+fn parse_struct_attributes(input: &DeriveInput) -> syn::Result<InstanceAttributes> {
+    let mut attributes = InstanceAttributes::default();
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("instance"))
+    {
+        let args = attr.parse_args_with(|input: ParseStream| {
+            Punctuated::<(Ident, LitStr), Token![,]>::parse_terminated_with(input, |input| {
+                let ident: Ident = input.parse()?;
+                input.parse::<Token![=]>()?;
+                let value: LitStr = input.parse()?;
+                Ok((ident, value))
+            })
+        })?;
+
+        for (ident, value) in args {
+            match ident.to_string().as_str() {
+                "import_path" => attributes.import_path = Some(value),
+                "struct_name" => attributes.struct_name = Some(value),
+                "array_name" => attributes.array_name = Some(value),
+                "soa_accessor" => attributes.soa_accessor = Some(value),
+                "vertex_struct" => attributes.vertex_struct = Some(value),
+                "vertex_accessor" => attributes.vertex_accessor = Some(value),
+                _ => {
+                    return Err(Error::new(
+                        ident.span(),
+                        "expected `import_path`, `struct_name`, `array_name`, `soa_accessor`, \
+                         `vertex_struct` or `vertex_accessor`",
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(attributes)
+}
+
+fn parse_field(field: &syn::Field) -> syn::Result<InstanceField> {
+    let ident = field.ident.clone().unwrap();
+
+    let mut kind = None;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("instance"))
+    {
+        kind = Some(attr.parse_args_with(|input: ParseStream| {
+            let arg: Ident = input.parse()?;
+            match arg.to_string().as_str() {
+                "base" => Ok(FieldKind::Base),
+                "component" => {
+                    input.parse::<Token![=]>()?;
+                    Ok(FieldKind::Component(Box::new(input.parse()?)))
+                }
+                _ => Err(Error::new(
+                    arg.span(),
+                    "expected `base` or `component = Type`",
+                )),
+            }
+        })?);
+    }
+
+    let kind = kind.ok_or_else(|| {
+        Error::new_spanned(
+            &ident,
+            "fields must be marked `#[instance(base)]` or `#[instance(component = Type)]`",
+        )
+    })?;
+
+    Ok(InstanceField {
+        ident,
+        ty: field.ty.clone(),
+        kind,
+    })
+}
+
+/// `TintedInstance` -> `tinted_instance`
+fn snake_case(value: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in value.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+/// FNV-1a, used to derive a stable shader handle from the import path
+fn fnv1a(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn instance(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let ident = &input.ident;
+    let vis = &input.vis;
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "#[derive(Instance)] does not support generic structs",
+        ));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .map(parse_field)
+                .collect::<syn::Result<Vec<_>>>()?,
+            _ => {
+                return Err(Error::new_spanned(
+                    ident,
+                    "#[derive(Instance)] requires named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                ident,
+                "#[derive(Instance)] can only be used on structs",
+            ))
+        }
+    };
+
+    let attributes = parse_struct_attributes(&input)?;
+    let import_path = attributes.import_path.ok_or_else(|| {
+        Error::new_spanned(
+            ident,
+            "missing `#[instance(import_path = \"...\")]` attribute",
+        )
+    })?;
+    let struct_name = attributes
+        .struct_name
+        .unwrap_or_else(|| LitStr::new(&format!("{ident}Data"), Span::call_site()));
+    let array_name = attributes
+        .array_name
+        .unwrap_or_else(|| LitStr::new(&format!("{ident}s"), Span::call_site()));
+    let stream_prefix = snake_case(&ident.to_string());
+    let soa_accessor = attributes
+        .soa_accessor
+        .unwrap_or_else(|| LitStr::new(&format!("load_{stream_prefix}"), Span::call_site()));
+    let vertex_struct = attributes
+        .vertex_struct
+        .unwrap_or_else(|| LitStr::new(&format!("{ident}Vertex"), Span::call_site()));
+    let vertex_accessor = attributes
+        .vertex_accessor
+        .unwrap_or_else(|| LitStr::new(&format!("{stream_prefix}_from_vertex"), Span::call_site()));
+
+    let mut bases = fields
+        .iter()
+        .filter(|field| matches!(field.kind, FieldKind::Base));
+    let base = match (bases.next(), bases.next()) {
+        (Some(base), None) => base,
+        _ => {
+            return Err(Error::new_spanned(
+                ident,
+                "exactly one field must be marked `#[instance(base)]`",
+            ))
+        }
+    };
+
+    // The base's WGSL hardcodes its bindings and locations from the start of the instance
+    if !matches!(fields[0].kind, FieldKind::Base) {
+        return Err(Error::new_spanned(
+            &base.ident,
+            "the `#[instance(base)]` field must come first",
+        ));
+    }
+
+    let base_ident = &base.ident;
+    let base_ty = &base.ty;
+
+    let components = fields
+        .iter()
+        .filter_map(|field| match &field.kind {
+            FieldKind::Component(component) => Some((field, component.as_ref())),
+            FieldKind::Base => None,
+        })
+        .collect::<Vec<_>>();
+
+    let instance_path = quote!(::bevy_instancing::prelude::Instance);
+    let wgsl_path = quote!(::bevy_instancing::prelude::InstanceWgsl);
+    let field_wgsl_path = quote!(::bevy_instancing::prelude::InstanceFieldWgsl);
+
+    let gpu_ident = format_ident!("Gpu{}", ident);
+
+    let gpu_fields = fields.iter().map(|field| {
+        let field_ident = &field.ident;
+        let ty = &field.ty;
+        match field.kind {
+            FieldKind::Base => quote!(pub #field_ident: <#ty as #instance_path>::PreparedInstance),
+            FieldKind::Component(_) => quote!(pub #field_ident: #ty),
+        }
+    });
+
+    let component_idents = components
+        .iter()
+        .map(|(field, _)| &field.ident)
+        .collect::<Vec<_>>();
+    let component_tys = components
+        .iter()
+        .map(|(_, component)| *component)
+        .collect::<Vec<_>>();
+
+    let wgsl_fields = fields.iter().map(|field| {
+        let name = field.ident.to_string();
+        let ty = &field.ty;
+        match field.kind {
+            FieldKind::Base => quote!((
+                #name,
+                <#ty as #wgsl_path>::STRUCT_NAME,
+                &[] as &[::bevy::render::render_resource::VertexFormat],
+            )),
+            FieldKind::Component(_) => quote!((
+                #name,
+                <#ty as #field_wgsl_path>::WGSL_TYPE,
+                <#ty as #field_wgsl_path>::VERTEX_FORMATS,
+            )),
+        }
+    });
+
+    let layout_fields = fields
+        .iter()
+        .map(|field| {
+            let ty = &field.ty;
+            match field.kind {
+                FieldKind::Base => quote!(.base::<#ty>()),
+                FieldKind::Component(_) => quote!(.field::<#ty>()),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let base_name = base_ident.to_string();
+
+    // Only declared in WGSL if there are fields past the base
+    let vertex_structs = if components.is_empty() {
+        quote!(<#base_ty as #wgsl_path>::vertex_structs())
+    } else {
+        quote! {
+            let mut vertex_structs = <#base_ty as #wgsl_path>::vertex_structs();
+            vertex_structs.push(#vertex_struct);
+            vertex_structs
+        }
+    };
+
+    let shader_id = fnv1a(&import_path.value());
+
+    Ok(quote! {
+        #[derive(Debug, Default, Clone, ::bevy::render::render_resource::ShaderType)]
+        #vis struct #gpu_ident {
+            #(#gpu_fields,)*
+        }
+
+        impl #instance_path for #ident {
+            type ExtractedInstance = Self;
+            type PreparedInstance = #gpu_ident;
+
+            type Query = (
+                <#base_ty as #instance_path>::Query,
+                #(::bevy::ecs::system::lifetimeless::Read<#component_tys>,)*
+            );
+
+            fn extract_instance(
+                (#base_ident, #(#component_idents,)*): ::bevy::ecs::query::ROQueryItem<Self::Query>,
+            ) -> Self::ExtractedInstance {
+                #ident {
+                    #base_ident: <#base_ty as #instance_path>::extract_instance(#base_ident),
+                    #(
+                        #component_idents: ::core::convert::From::from(
+                            ::core::clone::Clone::clone(#component_idents),
+                        ),
+                    )*
+                }
+            }
+
+            fn prepare_instance(
+                instance: &Self::ExtractedInstance,
+                mesh: u32,
+            ) -> Self::PreparedInstance {
+                #gpu_ident {
+                    #base_ident: <#base_ty as #instance_path>::prepare_instance(
+                        &instance.#base_ident,
+                        mesh,
+                    ),
+                    #(
+                        #component_idents: ::core::clone::Clone::clone(&instance.#component_idents),
+                    )*
+                }
+            }
+
+            fn transform(instance: &Self::ExtractedInstance) -> ::bevy::math::Mat4 {
+                <#base_ty as #instance_path>::transform(&instance.#base_ident)
+            }
+
+            fn shader() -> Option<(
+                ::bevy::prelude::HandleUntyped,
+                ::bevy::prelude::Shader,
+            )> {
+                Some((
+                    ::bevy::prelude::HandleUntyped::weak_from_u64(
+                        <::bevy::prelude::Shader as ::bevy::reflect::TypeUuid>::TYPE_UUID,
+                        #shader_id,
+                    ),
+                    ::bevy::prelude::Shader::from_wgsl(
+                        ::bevy_instancing::prelude::instance_struct_wgsl::<Self, #base_ty>(
+                            #base_name,
+                            &[#(#wgsl_fields,)*],
+                            #stream_prefix,
+                        ),
+                    ),
+                ))
+            }
+
+            fn vertex_attributes(
+                shader_location: u32,
+            ) -> Vec<::bevy::render::render_resource::VertexAttribute> {
+                ::bevy_instancing::prelude::InstanceVertexAttributes::new(shader_location)
+                    #(#layout_fields)*
+                    .build()
+            }
+
+            fn streams() -> Vec<::bevy_instancing::prelude::InstanceStream> {
+                ::bevy_instancing::prelude::InstanceStreams::new()
+                    #(#layout_fields)*
+                    .build()
+            }
+        }
+
+        impl #wgsl_path for #ident {
+            const IMPORT_PATH: &'static str = #import_path;
+            const STRUCT_NAME: &'static str = #struct_name;
+            const ARRAY_NAME: &'static str = #array_name;
+            const SOA_ACCESSOR: &'static str = #soa_accessor;
+            const VERTEX_ACCESSOR: &'static str = #vertex_accessor;
+
+            fn vertex_structs() -> Vec<&'static str> {
+                #vertex_structs
+            }
+        }
+    })
+}
@@ -0,0 +1,12 @@
+use bevy_instancing_derive::Instance;
+
+#[derive(Instance)]
+#[instance(import_path = "ui::duplicate_base")]
+struct DuplicateBase {
+    #[instance(base)]
+    base: MeshInstance,
+    #[instance(base)]
+    other: MeshInstance,
+}
+
+fn main() {}
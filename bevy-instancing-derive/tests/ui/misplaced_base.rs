@@ -0,0 +1,12 @@
+use bevy_instancing_derive::Instance;
+
+#[derive(Instance)]
+#[instance(import_path = "ui::misplaced_base")]
+struct MisplacedBase {
+    #[instance(component = Color)]
+    color: Vec4,
+    #[instance(base)]
+    base: MeshInstance,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use bevy_instancing_derive::Instance;
+
+#[derive(Instance)]
+#[instance(import_path = "ui::missing_base")]
+struct MissingBase {
+    #[instance(component = Color)]
+    color: Vec4,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use bevy_instancing_derive::Instance;
+
+#[derive(Instance)]
+struct MissingImportPath {
+    #[instance(base)]
+    base: MeshInstance,
+}
+
+fn main() {}
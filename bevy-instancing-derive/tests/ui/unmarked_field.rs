@@ -0,0 +1,11 @@
+use bevy_instancing_derive::Instance;
+
+#[derive(Instance)]
+#[instance(import_path = "ui::unmarked_field")]
+struct UnmarkedField {
+    #[instance(base)]
+    base: MeshInstance,
+    color: Vec4,
+}
+
+fn main() {}
@@ -0,0 +1,215 @@
+//! Renders the compact instance layouts side by side, spinning:
+//! affine on the left, translation-rotation-scale in the middle,
+//! and quantized on the right, stored relative to an `InstanceOrigin`.
+//!
+//! Pass `vertex` or `soa` as an argument to bind instances as a vertex buffer
+//! or as struct-of-arrays streams instead of a single buffer.
+//!
+
+use std::marker::PhantomData;
+
+use bevy::{
+    math::{Quat, Vec3},
+    prelude::{
+        default, shape::Cube, App, AssetServer, Assets, Camera3dBundle, Color, Commands, Component,
+        Mesh, Query, Res, ResMut, SpatialBundle, Transform,
+    },
+    reflect::{TypeUuid, Uuid},
+    render::{
+        mesh::MeshVertexBufferLayout,
+        render_resource::{
+            AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+        },
+    },
+    time::Time,
+    DefaultPlugins,
+};
+
+use bevy_instancing::prelude::{
+    AffineMeshInstance, AsBatch, IndirectRenderingPlugin, Instance, InstanceBufferMode,
+    InstanceColor, InstanceOrigin, InstancedMaterialPipeline, InstancedMaterialPlugin,
+    MaterialInstanced, MeshInstanceBundle, QuantizedMeshInstance, TrsMeshInstance,
+};
+
+fn main() {
+    let instance_buffer_mode = match std::env::args().nth(1).as_deref() {
+        Some("vertex") => InstanceBufferMode::Vertex,
+        Some("soa") => InstanceBufferMode::StructOfArrays,
+        _ => InstanceBufferMode::Binding,
+    };
+
+    let mut app = App::default();
+
+    app.insert_resource(instance_buffer_mode)
+        .add_plugins(DefaultPlugins)
+        .add_plugin(IndirectRenderingPlugin)
+        .add_plugin(InstancedMaterialPlugin::<CompactMaterial<AffineMeshInstance>>::default())
+        .add_plugin(InstancedMaterialPlugin::<CompactMaterial<TrsMeshInstance>>::default())
+        .add_plugin(InstancedMaterialPlugin::<
+            CompactMaterial<QuantizedMeshInstance>,
+        >::default());
+
+    app.add_startup_system(setup_instancing);
+
+    app.add_system(spin);
+
+    app.run()
+}
+
+/// Compact instance types drawn by `compact_instances.wgsl`
+trait CompactInstance: Instance + Clone + Send + Sync + 'static {
+    /// Selects this type's branch of the shader
+    const SHADER_DEF: &'static str;
+    const MATERIAL_UUID: Uuid;
+}
+
+impl CompactInstance for AffineMeshInstance {
+    const SHADER_DEF: &'static str = "AFFINE_INSTANCE";
+    const MATERIAL_UUID: Uuid = Uuid::from_u128(0x3f0b1d6e_52a4_4c1e_9d7b_6a2e8c41f001);
+}
+
+impl CompactInstance for TrsMeshInstance {
+    const SHADER_DEF: &'static str = "TRS_INSTANCE";
+    const MATERIAL_UUID: Uuid = Uuid::from_u128(0x3f0b1d6e_52a4_4c1e_9d7b_6a2e8c41f002);
+}
+
+impl CompactInstance for QuantizedMeshInstance {
+    const SHADER_DEF: &'static str = "QUANTIZED_INSTANCE";
+    const MATERIAL_UUID: Uuid = Uuid::from_u128(0x3f0b1d6e_52a4_4c1e_9d7b_6a2e8c41f003);
+}
+
+#[derive(Clone, AsBindGroup)]
+struct CompactMaterial<I: CompactInstance> {
+    /// Must match the [`InstanceOrigin`] of quantized instances
+    #[uniform(0)]
+    origin: Vec3,
+    marker: PhantomData<I>,
+}
+
+impl<I: CompactInstance> CompactMaterial<I> {
+    fn new(origin: Vec3) -> Self {
+        Self {
+            origin,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<I: CompactInstance> TypeUuid for CompactMaterial<I> {
+    const TYPE_UUID: Uuid = I::MATERIAL_UUID;
+}
+
+impl<I: CompactInstance> From<&CompactMaterial<I>> for () {
+    fn from(_: &CompactMaterial<I>) -> Self {}
+}
+
+impl<I: CompactInstance> AsBatch for CompactMaterial<I> {
+    type BatchKey = ();
+}
+
+impl<I: CompactInstance> MaterialInstanced for CompactMaterial<I> {
+    type Instance = I;
+
+    fn vertex_shader(asset_server: &AssetServer) -> ShaderRef {
+        asset_server.load("shader/compact_instances.wgsl").into()
+    }
+
+    fn fragment_shader(asset_server: &AssetServer) -> ShaderRef {
+        asset_server.load("shader/compact_instances.wgsl").into()
+    }
+
+    fn specialize(
+        _: &InstancedMaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _: Self::Data,
+        _: &MeshVertexBufferLayout,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        // Both stages are built from the same module, so both need the instance type
+        descriptor.vertex.shader_defs.push(I::SHADER_DEF.into());
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader_defs.push(I::SHADER_DEF.into());
+        }
+        Ok(())
+    }
+}
+
+/// Spins instances about their own Y axis
+#[derive(Debug, Default, Copy, Clone, Component)]
+struct Spin(f32);
+
+fn setup_instancing(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut affine_materials: ResMut<Assets<CompactMaterial<AffineMeshInstance>>>,
+    mut trs_materials: ResMut<Assets<CompactMaterial<TrsMeshInstance>>>,
+    mut quantized_materials: ResMut<Assets<CompactMaterial<QuantizedMeshInstance>>>,
+    mut commands: Commands,
+) {
+    // Perspective camera
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(0.0, 20.0, 30.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+
+    // Populate scene
+    let mesh = meshes.add(Cube { size: 0.6 }.into());
+
+    // Quantized translations are relative to the origin, keeping them within half precision
+    let quantized_origin = Vec3::new(12.0, 0.0, 0.0);
+
+    let affine_material = affine_materials.add(CompactMaterial::new(Vec3::ZERO));
+    let trs_material = trs_materials.add(CompactMaterial::new(Vec3::ZERO));
+    let quantized_material = quantized_materials.add(CompactMaterial::new(quantized_origin));
+
+    const SIZE: i32 = 8;
+    for x in 0..SIZE {
+        for z in 0..SIZE {
+            let offset = Vec3::new(x as f32, 0.0, z as f32) - Vec3::splat((SIZE - 1) as f32 * 0.5);
+            let transform = |center: Vec3| {
+                Transform::from_translation(center + offset)
+                    .with_rotation(Quat::from_rotation_x(0.3))
+                    // Non-uniform scale, which quantized instances reduce to the largest axis
+                    .with_scale(Vec3::new(1.0, 0.5 + (x + z) as f32 / SIZE as f32, 1.0))
+            };
+            let spin = Spin(0.5 + (x * SIZE + z) as f32 / (SIZE * SIZE) as f32);
+
+            commands.spawn((
+                MeshInstanceBundle {
+                    mesh: mesh.clone(),
+                    material: affine_material.clone(),
+                    spatial_bundle: SpatialBundle::from_transform(transform(-quantized_origin)),
+                },
+                spin,
+            ));
+
+            commands.spawn((
+                MeshInstanceBundle {
+                    mesh: mesh.clone(),
+                    material: trs_material.clone(),
+                    spatial_bundle: SpatialBundle::from_transform(transform(Vec3::ZERO)),
+                },
+                spin,
+            ));
+
+            commands.spawn((
+                MeshInstanceBundle {
+                    mesh: mesh.clone(),
+                    material: quantized_material.clone(),
+                    spatial_bundle: SpatialBundle::from_transform(transform(quantized_origin)),
+                },
+                InstanceOrigin(quantized_origin),
+                InstanceColor(Color::hsl(
+                    (x * SIZE + z) as f32 / (SIZE * SIZE) as f32 * 360.0,
+                    0.8,
+                    0.5,
+                )),
+                spin,
+            ));
+        }
+    }
+}
+
+fn spin(time: Res<Time>, mut query_spin: Query<(&Spin, &mut Transform)>) {
+    for (spin, mut transform) in query_spin.iter_mut() {
+        transform.rotate_local_y(spin.0 * time.delta_seconds());
+    }
+}
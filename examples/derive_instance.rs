@@ -0,0 +1,137 @@
+//! Demonstration of a custom instance type built with `#[derive(Instance)]`,
+//! combining a color and a per-instance pulse phase.
+//!
+//! Pass `vertex` or `soa` as an argument to bind instances as a vertex buffer
+//! or as struct-of-arrays streams instead of a single buffer.
+//!
+
+use bevy::{
+    math::{Vec3, Vec4},
+    prelude::{
+        default, shape::Icosphere, App, AssetServer, Assets, Camera3dBundle, Color, Commands,
+        Component, Mesh, ResMut, SpatialBundle, Transform,
+    },
+    reflect::TypeUuid,
+    render::render_resource::{AsBindGroup, ShaderRef},
+    DefaultPlugins,
+};
+
+use bevy_instancing::prelude::{
+    AsBatch, IndirectRenderingPlugin, Instance, InstanceBufferMode, InstanceColor,
+    InstancedMaterialPlugin, MaterialInstanced, MeshInstance, MeshInstanceBundle,
+};
+
+fn main() {
+    let instance_buffer_mode = match std::env::args().nth(1).as_deref() {
+        Some("vertex") => InstanceBufferMode::Vertex,
+        Some("soa") => InstanceBufferMode::StructOfArrays,
+        _ => InstanceBufferMode::Binding,
+    };
+
+    let mut app = App::default();
+
+    app.insert_resource(instance_buffer_mode)
+        .add_plugins(DefaultPlugins)
+        .add_plugin(IndirectRenderingPlugin)
+        .add_plugin(InstancedMaterialPlugin::<PulseMaterial>::default());
+
+    app.add_startup_system(setup_instancing);
+
+    app.run()
+}
+
+/// Offset into the pulse cycle of an instance, in radians
+#[derive(Debug, Default, Copy, Clone, Component)]
+struct PulsePhase(f32);
+
+impl From<PulsePhase> for f32 {
+    fn from(phase: PulsePhase) -> Self {
+        phase.0
+    }
+}
+
+/// Mesh instance with a color and pulse phase,
+/// declared in WGSL under `derive_instance::pulse_instance`
+#[derive(Debug, Default, Clone, Component, Instance)]
+#[instance(import_path = "derive_instance::pulse_instance")]
+struct PulseInstance {
+    #[instance(base)]
+    base: MeshInstance,
+    #[instance(component = InstanceColor)]
+    color: Vec4,
+    #[instance(component = PulsePhase)]
+    phase: f32,
+}
+
+/// Blends instances toward `glow` at the peak of their pulse
+#[derive(Debug, Clone, AsBindGroup, TypeUuid)]
+#[uuid = "0ac1a4f6-7c2e-4c8e-9a53-2f6be4f0d1c7"]
+struct PulseMaterial {
+    #[uniform(0)]
+    glow: Color,
+}
+
+impl From<&PulseMaterial> for () {
+    fn from(_: &PulseMaterial) -> Self {}
+}
+
+impl AsBatch for PulseMaterial {
+    type BatchKey = ();
+}
+
+impl MaterialInstanced for PulseMaterial {
+    type Instance = PulseInstance;
+
+    fn vertex_shader(asset_server: &AssetServer) -> ShaderRef {
+        asset_server.load("shader/pulse_instance.wgsl").into()
+    }
+
+    fn fragment_shader(asset_server: &AssetServer) -> ShaderRef {
+        asset_server.load("shader/pulse_instance.wgsl").into()
+    }
+}
+
+fn setup_instancing(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<PulseMaterial>>,
+    mut commands: Commands,
+) {
+    // Perspective camera
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(20.0, 20.0, 20.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+
+    // Populate scene
+    let mesh = meshes.add(
+        Icosphere {
+            radius: 0.5,
+            subdivisions: 2,
+        }
+        .into(),
+    );
+
+    let material = materials.add(PulseMaterial {
+        glow: Color::rgba(1.0, 1.0, 1.0, 0.5),
+    });
+
+    const SIZE: i32 = 16;
+    for x in -SIZE / 2..SIZE / 2 {
+        for z in -SIZE / 2..SIZE / 2 {
+            let hue = ((x + z).rem_euclid(SIZE) as f32 / SIZE as f32) * 360.0;
+
+            commands.spawn((
+                MeshInstanceBundle::<PulseMaterial> {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    spatial_bundle: SpatialBundle {
+                        transform: Transform::from_xyz(x as f32 * 1.25, 0.0, z as f32 * 1.25),
+                        ..default()
+                    },
+                },
+                InstanceColor(Color::hsl(hue, 0.8, 0.5)),
+                PulsePhase(Vec3::new(x as f32, 0.0, z as f32).length() * 0.5),
+            ));
+        }
+    }
+}
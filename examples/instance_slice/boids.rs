@@ -1,14 +1,21 @@
-//! Demonstration of InstanceSlice compute functionality
+//! Demonstration of InstanceSlice compute functionality,
+//! using persistent per-instance state to flock across frames,
+//! bevy's globals for frame timing,
+//! and reading the flock back to the CPU once per second
 //!
 //! Also highlights alpha ordering behaviour for transparent instance blocks;
 //! batch order is visible when instances from different blocks draw on top
 //! of one another.
 //!
 
+use std::num::NonZeroU64;
+
 use bevy::ecs::system::lifetimeless::Read;
-use bevy::prelude::{Camera3dBundle, Component, Query, Res};
+use bevy::prelude::{
+    info, Camera3dBundle, Component, Entity, EventReader, EventWriter, Query, Res, With,
+};
 use bevy::render::extract_component::ExtractComponent;
-use bevy::render::render_resource::{AsBindGroup, Face, ShaderRef};
+use bevy::render::render_resource::{AsBindGroup, Face, ShaderRef, ShaderSize, ShaderType};
 use bevy::time::Time;
 use bevy::{
     core::Name,
@@ -19,8 +26,9 @@ use bevy::{
 };
 
 use bevy_instancing::prelude::{
-    ColorMeshInstance, CustomMaterial, CustomMaterialPlugin, IndirectRenderingPlugin,
+    ColorMeshInstance, CustomMaterial, CustomMaterialPlugin, IndirectRenderingPlugin, Instance,
     InstanceCompute, InstanceComputePlugin, InstanceSlice, InstanceSliceBundle,
+    InstanceSliceReadback, InstanceSliceReadbackPlugin, InstanceSliceReadbackRequest,
 };
 
 // Test indirect rendering
@@ -29,21 +37,26 @@ fn main() {
 
     app.add_plugins(DefaultPlugins)
         .add_plugin(IndirectRenderingPlugin)
-        .add_plugin(CustomMaterialPlugin);
+        .add_plugin(CustomMaterialPlugin)
+        .add_plugin(InstanceSliceReadbackPlugin::<CustomMaterial>::default());
 
     app.add_plugin(InstanceComputePlugin::<BoidsInstances>::default());
 
     app.add_startup_system(setup_instancing);
 
-    app.add_system(instance_compute_time);
+    app.add_system(request_readback).add_system(log_readback);
 
     app.run()
 }
 
 #[derive(Debug, Default, Copy, Clone, Component, AsBindGroup)]
-pub struct BoidsInstances {
-    #[uniform(0)]
-    time: f32,
+pub struct BoidsInstances {}
+
+/// Per-boid simulation state, carried between dispatches
+#[derive(Debug, Default, Copy, Clone, ShaderType)]
+pub struct BoidState {
+    position: Vec3,
+    velocity: Vec3,
 }
 
 impl From<&BoidsInstances> for () {
@@ -63,17 +76,17 @@ impl ExtractComponent for BoidsInstances {
 impl InstanceCompute for BoidsInstances {
     type Instance = ColorMeshInstance;
 
+    const STATE_SIZE: Option<NonZeroU64> = Some(BoidState::SHADER_SIZE);
+
+    const GLOBALS: bool = true;
+
     fn shader() -> ShaderRef {
         "shader/boids.wgsl".into()
     }
 
-    fn specialize(
-        pipeline: &bevy_instancing::prelude::InstanceComputePipeline<Self>,
-        descriptor: &mut bevy::render::render_resource::ComputePipelineDescriptor,
-        data: Self::Data,
-    ) {
-        let descriptor_layout = descriptor.layout.as_mut().unwrap();
-        descriptor_layout.insert(1, data.bind_group);
+    /// Every stream but the mesh index, which the shader leaves untouched
+    fn streams() -> Vec<usize> {
+        (1..<ColorMeshInstance as Instance>::streams().len()).collect()
     }
 }
 
@@ -125,8 +138,35 @@ fn setup_instancing(
         .insert(BoidsInstances::default());
 }
 
-fn instance_compute_time(time: Res<Time>, mut query_uniform: Query<&mut BoidsInstances>) {
-    for mut uniform in query_uniform.iter_mut() {
-        uniform.time = time.elapsed_seconds();
+fn request_readback(
+    time: Res<Time>,
+    query_boids: Query<Entity, With<BoidsInstances>>,
+    mut requests: EventWriter<InstanceSliceReadbackRequest>,
+) {
+    let seconds = time.elapsed_seconds();
+    if (seconds - time.delta_seconds()).floor() == seconds.floor() {
+        return;
+    }
+
+    requests.send_batch(
+        query_boids
+            .iter()
+            .map(|entity| InstanceSliceReadbackRequest {
+                entity,
+                instances: None,
+            }),
+    );
+}
+
+fn log_readback(mut readbacks: EventReader<InstanceSliceReadback<ColorMeshInstance>>) {
+    for readback in readbacks.iter() {
+        let center = readback
+            .instances
+            .iter()
+            .map(|instance| instance.base.transform.w_axis.truncate())
+            .sum::<Vec3>()
+            / readback.instances.len().max(1) as f32;
+
+        info!("Flock {:?} centered at {center}", readback.entity);
     }
 }
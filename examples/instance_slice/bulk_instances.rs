@@ -0,0 +1,107 @@
+//! Demonstration of drawing many instances from a single entity,
+//! scattering a field of grass blades without an entity per blade
+//!
+
+use bevy::prelude::Camera3dBundle;
+use bevy::render::render_resource::Face;
+use bevy::{
+    core::Name,
+    math::{Quat, Vec3, Vec4},
+    pbr::{AlphaMode, DirectionalLight, DirectionalLightBundle},
+    prelude::{default, shape::Cube, App, Assets, Commands, Mesh, ResMut, Transform},
+    DefaultPlugins,
+};
+
+use bevy_instancing::prelude::{
+    BulkInstances, BulkInstancesBundle, BulkInstancesPlugin, ColorMeshInstance, CustomMaterial,
+    CustomMaterialPlugin, IndirectRenderingPlugin,
+};
+
+const FIELD_SIZE: usize = 300;
+const SPACING: f32 = 0.2;
+
+fn main() {
+    let mut app = App::default();
+
+    app.add_plugins(DefaultPlugins)
+        .add_plugin(IndirectRenderingPlugin)
+        .add_plugin(CustomMaterialPlugin)
+        .add_plugin(BulkInstancesPlugin::<ColorMeshInstance>::default());
+
+    app.add_startup_system(setup_instancing);
+
+    app.run()
+}
+
+/// Cheap deterministic jitter in `0.0..1.0`
+fn hash(x: usize, y: usize, seed: u32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(73856093)
+        ^ (y as u32).wrapping_mul(19349663)
+        ^ seed.wrapping_mul(83492791);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1e995);
+    h ^= h >> 15;
+    (h & 0xffff) as f32 / 65536.0
+}
+
+fn setup_instancing(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<CustomMaterial>>,
+    mut commands: Commands,
+) {
+    // Perspective camera
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(-40.0, 25.0, 40.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+
+    // Directional Light
+    commands.spawn(DirectionalLightBundle {
+        directional_light: DirectionalLight {
+            illuminance: 4000.,
+            ..default()
+        },
+        transform: Transform {
+            // Workaround: Pointing straight up or down prevents directional shadow from rendering
+            rotation: Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2 * 0.6),
+            ..default()
+        },
+        ..default()
+    });
+
+    // Populate scene
+    let mut instances = BulkInstances::<ColorMeshInstance>::default();
+    let offset = FIELD_SIZE as f32 * SPACING * 0.5;
+
+    for x in 0..FIELD_SIZE {
+        for y in 0..FIELD_SIZE {
+            let height = 0.5 + hash(x, y, 0) * 1.5;
+
+            instances.push(
+                Transform {
+                    translation: Vec3::new(
+                        x as f32 * SPACING - offset + hash(x, y, 1) * SPACING,
+                        height * 0.5,
+                        y as f32 * SPACING - offset + hash(x, y, 2) * SPACING,
+                    ),
+                    rotation: Quat::from_rotation_y(hash(x, y, 3) * std::f32::consts::TAU),
+                    scale: Vec3::new(0.04, height, 0.04),
+                },
+                Vec4::new(0.1, 0.4 + hash(x, y, 4) * 0.4, 0.1, 1.0),
+            );
+        }
+    }
+
+    commands.spawn((
+        Name::new("Grass Field"),
+        BulkInstancesBundle {
+            material: materials.add(CustomMaterial {
+                alpha_mode: AlphaMode::Opaque,
+                cull_mode: Some(Face::Back),
+            }),
+            mesh: meshes.add(Cube::default().into()),
+            instances,
+            ..default()
+        },
+    ));
+}
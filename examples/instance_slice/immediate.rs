@@ -0,0 +1,159 @@
+//! Demonstration of immediate-mode instanced drawing,
+//! redrawing a rotating helix of cubes every frame without spawning any entities
+//!
+//! Rendered split-screen by two cameras, each of which draws its own copy of the helix
+//!
+
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::math::UVec2;
+use bevy::prelude::{
+    Camera, Camera3d, Camera3dBundle, Component, Handle, Query, Res, Resource, Windows,
+};
+use bevy::render::{camera::Viewport, render_resource::Face};
+use bevy::time::Time;
+use bevy::{
+    math::{Quat, Vec3, Vec4},
+    pbr::{AlphaMode, DirectionalLight, DirectionalLightBundle},
+    prelude::{default, shape::Cube, App, Assets, Commands, Mesh, ResMut, Transform},
+    DefaultPlugins,
+};
+
+use bevy_instancing::prelude::{
+    CustomMaterial, CustomMaterialPlugin, ImmediateInstances, IndirectRenderingPlugin,
+};
+
+const INSTANCE_COUNT: usize = 500;
+
+/// Which half of the window a camera renders to
+#[derive(Component)]
+struct SplitScreen {
+    index: u32,
+}
+
+#[derive(Resource)]
+struct HelixAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<CustomMaterial>,
+}
+
+fn main() {
+    let mut app = App::default();
+
+    app.add_plugins(DefaultPlugins)
+        .add_plugin(IndirectRenderingPlugin)
+        .add_plugin(CustomMaterialPlugin);
+
+    app.add_startup_system(setup_instancing);
+
+    app.add_system(draw_helix);
+    app.add_system(set_camera_viewports);
+
+    app.run()
+}
+
+fn setup_instancing(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<CustomMaterial>>,
+    mut commands: Commands,
+) {
+    // Perspective cameras
+    commands.spawn((
+        Camera3dBundle {
+            transform: Transform::from_xyz(-30.0, 20.0, 30.0).looking_at(Vec3::Y * 10.0, Vec3::Y),
+            ..default()
+        },
+        SplitScreen { index: 0 },
+    ));
+
+    commands.spawn((
+        Camera3dBundle {
+            transform: Transform::from_xyz(0.0, 40.0, 0.01).looking_at(Vec3::Y * 10.0, Vec3::Y),
+            camera: Camera {
+                priority: 1,
+                ..default()
+            },
+            camera_3d: Camera3d {
+                // Don't clear the first camera's half of the window
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            ..default()
+        },
+        SplitScreen { index: 1 },
+    ));
+
+    // Directional Light
+    commands.spawn(DirectionalLightBundle {
+        directional_light: DirectionalLight {
+            illuminance: 4000.,
+            ..default()
+        },
+        transform: Transform {
+            // Workaround: Pointing straight up or down prevents directional shadow from rendering
+            rotation: Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2 * 0.6),
+            ..default()
+        },
+        ..default()
+    });
+
+    commands.insert_resource(HelixAssets {
+        mesh: meshes.add(Cube::default().into()),
+        material: materials.add(CustomMaterial {
+            alpha_mode: AlphaMode::Opaque,
+            cull_mode: Some(Face::Back),
+        }),
+    });
+}
+
+/// Queue this frame's helix, discarded automatically before the next
+fn draw_helix(
+    time: Res<Time>,
+    helix_assets: Res<HelixAssets>,
+    mut immediate_instances: ResMut<ImmediateInstances<CustomMaterial>>,
+) {
+    let t = time.elapsed_seconds();
+
+    immediate_instances.draw_instances(
+        &helix_assets.mesh,
+        &helix_assets.material,
+        (0..INSTANCE_COUNT).map(|i| {
+            let f = i as f32 / INSTANCE_COUNT as f32;
+            let angle = f * std::f32::consts::TAU * 6.0 + t;
+
+            (
+                Transform {
+                    translation: Vec3::new(angle.cos() * 8.0, f * 20.0, angle.sin() * 8.0),
+                    rotation: Quat::from_rotation_y(-angle),
+                    scale: Vec3::splat(0.4),
+                },
+                Vec4::new(f, 0.5 + 0.5 * (t + f * 10.0).sin(), 1.0 - f, 1.0),
+            )
+        }),
+    );
+}
+
+/// Give each camera its half of the window
+fn set_camera_viewports(windows: Res<Windows>, mut query: Query<(&SplitScreen, &mut Camera)>) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+
+    let size = UVec2::new(window.physical_width() / 2, window.physical_height());
+    for (split_screen, mut camera) in query.iter_mut() {
+        let position = UVec2::new(size.x * split_screen.index, 0);
+
+        let current = camera
+            .viewport
+            .as_ref()
+            .map(|viewport| (viewport.physical_position, viewport.physical_size));
+
+        if current != Some((position, size)) {
+            camera.viewport = Some(Viewport {
+                physical_position: position,
+                physical_size: size,
+                ..default()
+            });
+        }
+    }
+}
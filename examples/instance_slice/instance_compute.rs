@@ -5,6 +5,8 @@
 //! of one another.
 //!
 
+use std::f32::consts::PI;
+
 use bevy::ecs::system::lifetimeless::Read;
 use bevy::prelude::{Camera3dBundle, Component, Query, Res};
 use bevy::render::extract_component::ExtractComponent;
@@ -12,7 +14,7 @@ use bevy::render::render_resource::{AsBindGroup, Face, ShaderRef};
 use bevy::time::Time;
 use bevy::{
     core::Name,
-    math::{Quat, Vec3},
+    math::{Mat4, Quat, Vec3},
     pbr::{AlphaMode, DirectionalLight, DirectionalLightBundle},
     prelude::{
         default,
@@ -23,14 +25,20 @@ use bevy::{
 };
 
 use bevy_instancing::prelude::{
-    ColorMeshInstance, CustomMaterial, CustomMaterialPlugin, IndirectRenderingPlugin,
-    InstanceCompute, InstanceComputePlugin, InstanceSlice, InstanceSliceBundle,
+    ColorMeshInstance, CustomMaterial, CustomMaterialPlugin, GpuColorMeshInstance,
+    IndirectRenderingPlugin, Instance, InstanceCompute, InstanceComputeBackend,
+    InstanceComputePlugin, InstanceSlice, InstanceSliceBundle,
 };
 
 // Test indirect rendering
 fn main() {
     let mut app = App::default();
 
+    // Run the CPU implementation instead of the shader if requested
+    if std::env::var_os("INSTANCE_COMPUTE_CPU").is_some() {
+        app.insert_resource(InstanceComputeBackend::Cpu);
+    }
+
     app.add_plugins(DefaultPlugins)
         .add_plugin(IndirectRenderingPlugin)
         .add_plugin(CustomMaterialPlugin);
@@ -76,6 +84,34 @@ impl InstanceCompute for RadialSineInstances {
     fn shader() -> ShaderRef {
         "shader/radial_sine.wgsl".into()
     }
+
+    /// Every stream but the mesh index, which the shader leaves untouched
+    fn streams() -> Vec<usize> {
+        (1..<ColorMeshInstance as Instance>::streams().len()).collect()
+    }
+
+    fn compute_cpu(&self, instances: &mut [GpuColorMeshInstance]) -> bool {
+        let max_instance = instances.len() as f32;
+
+        for (instance_idx, instance) in instances.iter_mut().enumerate() {
+            let f = instance_idx as f32 / max_instance;
+
+            let frequency = f * PI * 0.1;
+            let amplitude = f * PI * 0.5;
+            let scale = f * 25.0;
+
+            let fac = (self.time * frequency).sin() * amplitude;
+
+            let pos = ((self.normal * fac.sin()) + (self.tangent * fac.cos())) * scale;
+
+            let transform = Mat4::from_translation(pos);
+            instance.base.transform = transform;
+            instance.base.inverse_transpose_model = transform.inverse().transpose();
+            instance.color = self.tint.extend(f.abs());
+        }
+
+        true
+    }
 }
 
 fn setup_instancing(
@@ -201,3 +237,54 @@ fn instance_compute_time(time: Res<Time>, mut query_uniform: Query<&mut RadialSi
         uniform.time = time.elapsed_seconds();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compute(radial_sine: &RadialSineInstances, count: usize) -> Vec<GpuColorMeshInstance> {
+        let mut instances = vec![GpuColorMeshInstance::default(); count];
+        assert!(radial_sine.compute_cpu(&mut instances));
+        instances
+    }
+
+    #[test]
+    fn instances_start_along_the_tangent() {
+        let radial_sine = RadialSineInstances {
+            tint: Vec3::new(1.0, 0.5, 0.0),
+            normal: Vec3::Z,
+            tangent: Vec3::Y,
+            ..default()
+        };
+
+        for (i, instance) in compute(&radial_sine, 10).iter().enumerate() {
+            let f = i as f32 / 10.0;
+            let translation = instance.base.transform.w_axis.truncate();
+
+            assert!(translation.abs_diff_eq(Vec3::Y * f * 25.0, 1e-5));
+            assert_eq!(instance.color, radial_sine.tint.extend(f));
+        }
+    }
+
+    #[test]
+    fn instances_swing_within_the_normal_tangent_plane() {
+        let radial_sine = RadialSineInstances {
+            time: 3.7,
+            normal: Vec3::X,
+            tangent: Vec3::Y,
+            ..default()
+        };
+
+        for (i, instance) in compute(&radial_sine, 10).iter().enumerate() {
+            let scale = i as f32 / 10.0 * 25.0;
+            let translation = instance.base.transform.w_axis.truncate();
+
+            assert!((translation.length() - scale).abs() < 1e-4);
+            assert!(translation.z.abs() < 1e-5);
+            assert!(instance
+                .base
+                .inverse_transpose_model
+                .abs_diff_eq(instance.base.transform.inverse().transpose(), 1e-5));
+        }
+    }
+}
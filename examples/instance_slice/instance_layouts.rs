@@ -0,0 +1,192 @@
+//! Demonstration of the built-in instance layouts,
+//! placing instance slices procedurally without any custom shaders
+//!
+
+use bevy::prelude::{Camera3dBundle, Color};
+use bevy::render::render_resource::Face;
+use bevy::{
+    core::Name,
+    math::{Quat, UVec2, UVec3, Vec2, Vec3},
+    pbr::{AlphaMode, DirectionalLight, DirectionalLightBundle},
+    prelude::{
+        default,
+        shape::{Cube, Icosphere},
+        App, Assets, Commands, Handle, Mesh, ResMut, Transform,
+    },
+    DefaultPlugins,
+};
+
+use bevy_instancing::prelude::{
+    ColorMeshInstance, CustomMaterial, CustomMaterialPlugin, IndirectRenderingPlugin,
+    InstanceComputeRunMode, InstanceLayout, InstanceLayoutPlugin, InstanceLayoutShape,
+    InstanceSlice, InstanceSliceBundle,
+};
+
+fn main() {
+    let mut app = App::default();
+
+    app.add_plugins(DefaultPlugins)
+        .add_plugin(IndirectRenderingPlugin)
+        .add_plugin(CustomMaterialPlugin)
+        .add_plugin(InstanceLayoutPlugin::<ColorMeshInstance>::default());
+
+    app.add_startup_system(setup_instancing);
+
+    app.run()
+}
+
+fn setup_instancing(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<CustomMaterial>>,
+    mut commands: Commands,
+) {
+    // Perspective camera
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(-40.0, 40.0, 40.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+
+    // Directional Light
+    commands.spawn(DirectionalLightBundle {
+        directional_light: DirectionalLight {
+            illuminance: 4000.,
+            ..default()
+        },
+        transform: Transform {
+            // Workaround: Pointing straight up or down prevents directional shadow from rendering
+            rotation: Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2 * 0.6),
+            ..default()
+        },
+        ..default()
+    });
+
+    // Populate scene
+    let mesh_cube = meshes.add(Cube { size: 0.5 }.into());
+    let mesh_sphere = meshes.add(
+        Icosphere {
+            radius: 0.25,
+            subdivisions: 1,
+        }
+        .into(),
+    );
+
+    let material = materials.add(CustomMaterial {
+        alpha_mode: AlphaMode::Opaque,
+        cull_mode: Some(Face::Back),
+    });
+
+    let mut spawn_layout = |name: &'static str,
+                            mesh: &Handle<Mesh>,
+                            instance_count: usize,
+                            layout: InstanceLayout<ColorMeshInstance>| {
+        commands.spawn((
+            Name::new(name),
+            InstanceSliceBundle {
+                material: material.clone(),
+                mesh: mesh.clone(),
+                mesh_instance_slice: InstanceSlice { instance_count },
+                ..default()
+            },
+            layout,
+            // Layouts are static, so only recompute them when they change
+            InstanceComputeRunMode::OnChange,
+        ));
+    };
+
+    spawn_layout(
+        "Grid",
+        &mesh_cube,
+        400,
+        InstanceLayout::new(InstanceLayoutShape::grid_2d(
+            UVec2::splat(20),
+            Vec2::splat(1.0),
+        ))
+        .with_color(Color::WHITE),
+    );
+
+    spawn_layout(
+        "Tower",
+        &mesh_cube,
+        125,
+        InstanceLayout::new(InstanceLayoutShape::grid_3d(
+            UVec3::splat(5),
+            Vec3::splat(0.75),
+        ))
+        .with_transform(Transform::from_xyz(0.0, 5.0, 0.0))
+        .with_color(Color::GRAY),
+    );
+
+    spawn_layout(
+        "Ring",
+        &mesh_sphere,
+        64,
+        InstanceLayout::new(InstanceLayoutShape::Ring { radius: 14.0 }).with_color(Color::RED),
+    );
+
+    spawn_layout(
+        "Spiral",
+        &mesh_sphere,
+        200,
+        InstanceLayout::new(InstanceLayoutShape::Spiral {
+            inner_radius: 1.0,
+            outer_radius: 8.0,
+            turns: 4.0,
+            height: 10.0,
+        })
+        .with_transform(Transform::from_xyz(-20.0, 0.0, 0.0))
+        .with_color(Color::GREEN),
+    );
+
+    spawn_layout(
+        "Random Box",
+        &mesh_cube,
+        300,
+        InstanceLayout::new(InstanceLayoutShape::RandomBox {
+            half_extents: Vec3::new(4.0, 2.0, 4.0),
+            seed: 1,
+        })
+        .with_transform(Transform::from_xyz(20.0, 2.0, 0.0))
+        .with_instance_transform(Transform::from_rotation(Quat::from_rotation_y(0.5)))
+        .with_color(Color::BLUE),
+    );
+
+    spawn_layout(
+        "Random Sphere",
+        &mesh_sphere,
+        300,
+        InstanceLayout::new(InstanceLayoutShape::RandomSphere {
+            radius: 4.0,
+            seed: 2,
+        })
+        .with_transform(Transform::from_xyz(0.0, 4.0, -20.0))
+        .with_color(Color::YELLOW),
+    );
+
+    spawn_layout(
+        "Random Disk",
+        &mesh_sphere,
+        300,
+        InstanceLayout::new(InstanceLayoutShape::RandomDisk {
+            radius: 5.0,
+            seed: 3,
+        })
+        .with_transform(Transform::from_xyz(0.0, 0.5, 20.0))
+        .with_color(Color::ORANGE),
+    );
+
+    spawn_layout(
+        "Polyline",
+        &mesh_cube,
+        100,
+        InstanceLayout::new(InstanceLayoutShape::Polyline {
+            points: vec![
+                Vec3::new(-20.0, 0.5, -20.0),
+                Vec3::new(-20.0, 0.5, 20.0),
+                Vec3::new(20.0, 6.0, 20.0),
+                Vec3::new(20.0, 0.5, -20.0),
+            ],
+        })
+        .with_instance_transform(Transform::from_scale(Vec3::splat(1.5)))
+        .with_color(Color::PURPLE),
+    );
+}
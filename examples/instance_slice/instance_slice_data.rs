@@ -0,0 +1,124 @@
+//! Demonstration of CPU-uploaded InstanceSlice contents,
+//! laying out a spiral once and recoloring a moving window of it every frame
+//!
+
+use bevy::prelude::{Camera3dBundle, Local, Query};
+use bevy::render::render_resource::Face;
+use bevy::{
+    core::Name,
+    math::{Mat4, Quat, Vec3, Vec4},
+    pbr::{AlphaMode, DirectionalLight, DirectionalLightBundle},
+    prelude::{default, shape::Cube, App, Assets, Commands, Mesh, ResMut, Transform},
+    DefaultPlugins,
+};
+
+use bevy_instancing::prelude::{
+    ColorMeshInstance, CustomMaterial, CustomMaterialPlugin, GpuColorMeshInstance, GpuMeshInstance,
+    IndirectRenderingPlugin, InstanceSlice, InstanceSliceBundle, InstanceSliceData,
+};
+
+const INSTANCE_COUNT: usize = 2000;
+const WINDOW: usize = 100;
+
+fn main() {
+    let mut app = App::default();
+
+    app.add_plugins(DefaultPlugins)
+        .add_plugin(IndirectRenderingPlugin)
+        .add_plugin(CustomMaterialPlugin);
+
+    app.add_startup_system(setup_instancing);
+
+    app.add_system(highlight_window);
+
+    app.run()
+}
+
+fn spiral_instance(index: usize, color: Vec4) -> GpuColorMeshInstance {
+    let f = index as f32 / INSTANCE_COUNT as f32;
+    let angle = f * std::f32::consts::TAU * 12.0;
+    let radius = 5.0 + f * 25.0;
+
+    let transform = Mat4::from_scale_rotation_translation(
+        Vec3::splat(0.5),
+        Quat::from_rotation_y(angle),
+        Vec3::new(angle.cos() * radius, f * 10.0, angle.sin() * radius),
+    );
+
+    GpuColorMeshInstance {
+        base: GpuMeshInstance {
+            mesh: 0,
+            transform,
+            inverse_transpose_model: transform.inverse().transpose(),
+        },
+        color,
+    }
+}
+
+fn base_color(index: usize) -> Vec4 {
+    let f = index as f32 / INSTANCE_COUNT as f32;
+    Vec4::new(f, 0.3, 1.0 - f, 1.0)
+}
+
+fn setup_instancing(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<CustomMaterial>>,
+    mut commands: Commands,
+) {
+    // Perspective camera
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(-40.0, 40.0, 40.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+
+    // Directional Light
+    commands.spawn(DirectionalLightBundle {
+        directional_light: DirectionalLight {
+            illuminance: 4000.,
+            ..default()
+        },
+        transform: Transform {
+            // Workaround: Pointing straight up or down prevents directional shadow from rendering
+            rotation: Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2 * 0.6),
+            ..default()
+        },
+        ..default()
+    });
+
+    // Populate scene
+    commands.spawn((
+        Name::new("Spiral Instance Slice"),
+        InstanceSliceBundle {
+            material: materials.add(CustomMaterial {
+                alpha_mode: AlphaMode::Opaque,
+                cull_mode: Some(Face::Back),
+            }),
+            mesh: meshes.add(Cube::default().into()),
+            mesh_instance_slice: InstanceSlice {
+                instance_count: INSTANCE_COUNT,
+            },
+            ..default()
+        },
+        InstanceSliceData::<ColorMeshInstance>::new(
+            (0..INSTANCE_COUNT)
+                .map(|i| spiral_instance(i, base_color(i)))
+                .collect(),
+        ),
+    ));
+}
+
+/// Recolor a window sweeping one instance per frame along the spiral,
+/// uploading only the instances that changed
+fn highlight_window(
+    mut head: Local<usize>,
+    mut query_data: Query<&mut InstanceSliceData<ColorMeshInstance>>,
+) {
+    *head = (*head + 1) % INSTANCE_COUNT;
+    let head = *head;
+    let tail = (head + INSTANCE_COUNT - WINDOW) % INSTANCE_COUNT;
+
+    for mut data in query_data.iter_mut() {
+        data.set(tail, spiral_instance(tail, base_color(tail)));
+        data.set(head, spiral_instance(head, Vec4::ONE));
+    }
+}
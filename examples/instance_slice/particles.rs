@@ -0,0 +1,148 @@
+//! Demonstration of the built-in GPU particle system,
+//! emitting a fountain and periodic bursts into instance slices
+//!
+
+use bevy::prelude::{Camera3dBundle, Color, Query, Res};
+use bevy::render::render_resource::Face;
+use bevy::time::Time;
+use bevy::{
+    core::Name,
+    math::{Quat, Vec3},
+    pbr::{AlphaMode, DirectionalLight, DirectionalLightBundle},
+    prelude::{default, shape::Icosphere, App, Assets, Commands, Mesh, ResMut, Transform},
+    DefaultPlugins,
+};
+
+use bevy_instancing::prelude::{
+    CustomMaterial, CustomMaterialPlugin, EmitterShape, IndirectRenderingPlugin, InstanceSlice,
+    InstanceSliceBundle, ParticleEmitter, ParticleEmitterBundle, ParticlePlugin,
+};
+
+fn main() {
+    let mut app = App::default();
+
+    app.add_plugins(DefaultPlugins)
+        .add_plugin(IndirectRenderingPlugin)
+        .add_plugin(CustomMaterialPlugin)
+        .add_plugin(ParticlePlugin);
+
+    app.add_startup_system(setup_instancing);
+
+    app.add_system(burst_emitters);
+
+    app.run()
+}
+
+fn setup_instancing(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<CustomMaterial>>,
+    mut commands: Commands,
+) {
+    // Perspective camera
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(-30.0, 20.0, 30.0).looking_at(Vec3::Y * 8.0, Vec3::Y),
+        ..default()
+    });
+
+    // Directional Light
+    commands.spawn(DirectionalLightBundle {
+        directional_light: DirectionalLight {
+            illuminance: 4000.,
+            ..default()
+        },
+        transform: Transform {
+            // Workaround: Pointing straight up or down prevents directional shadow from rendering
+            rotation: Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2 * 0.6),
+            ..default()
+        },
+        ..default()
+    });
+
+    // Populate scene
+    let mesh_particle = meshes.add(
+        Icosphere {
+            radius: 0.25,
+            subdivisions: 1,
+        }
+        .into(),
+    );
+
+    let material = materials.add(CustomMaterial {
+        alpha_mode: AlphaMode::Blend,
+        cull_mode: Some(Face::Back),
+    });
+
+    commands.spawn((
+        Name::new("Fountain"),
+        ParticleEmitterBundle {
+            instance_slice_bundle: InstanceSliceBundle {
+                material: material.clone(),
+                mesh: mesh_particle.clone(),
+                mesh_instance_slice: InstanceSlice {
+                    instance_count: 4096,
+                },
+                ..default()
+            },
+            emitter: ParticleEmitter {
+                rate: 800.0,
+                shape: EmitterShape::Cone {
+                    radius: 0.5,
+                    angle: 0.3,
+                },
+                lifetime: 3.0,
+                lifetime_variance: 0.5,
+                speed: 15.0,
+                acceleration: Vec3::new(0.0, -9.81, 0.0),
+                size_over_life: vec![(0.0, 0.5), (0.2, 1.0), (1.0, 0.0)],
+                color_over_life: vec![
+                    (0.0, Color::rgba(0.6, 0.8, 1.0, 1.0)),
+                    (1.0, Color::rgba(0.1, 0.2, 1.0, 0.0)),
+                ],
+                ..default()
+            },
+            ..default()
+        },
+    ));
+
+    commands.spawn((
+        Name::new("Bursts"),
+        ParticleEmitterBundle {
+            instance_slice_bundle: InstanceSliceBundle {
+                material,
+                mesh: mesh_particle,
+                mesh_instance_slice: InstanceSlice {
+                    instance_count: 1024,
+                },
+                ..default()
+            },
+            emitter: ParticleEmitter {
+                rate: 0.0,
+                shape: EmitterShape::Sphere { radius: 1.0 },
+                lifetime: 1.5,
+                speed: 8.0,
+                size_over_life: vec![(0.0, 1.0), (1.0, 0.0)],
+                color_over_life: vec![
+                    (0.0, Color::YELLOW),
+                    (0.5, Color::ORANGE_RED),
+                    (1.0, Color::rgba(0.2, 0.0, 0.0, 0.0)),
+                ],
+                ..default()
+            },
+            transform: Transform::from_xyz(10.0, 12.0, 0.0),
+            ..default()
+        },
+    ));
+}
+
+fn burst_emitters(time: Res<Time>, mut query_emitter: Query<&mut ParticleEmitter>) {
+    let seconds = time.elapsed_seconds();
+    if (seconds - time.delta_seconds()).floor() == seconds.floor() {
+        return;
+    }
+
+    for mut emitter in query_emitter.iter_mut() {
+        if emitter.rate == 0.0 {
+            emitter.burst = 500;
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Demonstration of placing instances along splines,
+//! building a fence, a sagging cable and a swaying line of markers
+//! that regenerates as its curve changes
+//!
+
+use bevy::prelude::{Camera3dBundle, Component, Query, Res, With};
+use bevy::render::render_resource::Face;
+use bevy::time::Time;
+use bevy::{
+    core::Name,
+    math::{Quat, Vec3},
+    pbr::{AlphaMode, DirectionalLight, DirectionalLightBundle},
+    prelude::{
+        default,
+        shape::{self, Cube, Icosphere},
+        App, Assets, Commands, Mesh, ResMut, Transform,
+    },
+    DefaultPlugins,
+};
+
+use bevy_instancing::prelude::{
+    BulkInstancesBundle, BulkInstancesPlugin, ColorMeshInstance, CustomMaterial,
+    CustomMaterialPlugin, IndirectRenderingPlugin, SplineCurve, SplineInstances,
+    SplineInstancesBundle, SplineInstancesPlugin, SplineSpacing,
+};
+
+fn main() {
+    let mut app = App::default();
+
+    app.add_plugins(DefaultPlugins)
+        .add_plugin(IndirectRenderingPlugin)
+        .add_plugin(CustomMaterialPlugin)
+        .add_plugin(BulkInstancesPlugin::<ColorMeshInstance>::default())
+        .add_plugin(SplineInstancesPlugin::<ColorMeshInstance>::default());
+
+    app.add_startup_system(setup_instancing);
+
+    app.add_system(sway_markers);
+
+    app.run()
+}
+
+/// Marks the spline whose curve is animated
+#[derive(Component)]
+struct Swaying;
+
+fn setup_instancing(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<CustomMaterial>>,
+    mut commands: Commands,
+) {
+    // Perspective camera
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(-30.0, 25.0, 30.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+
+    // Directional Light
+    commands.spawn(DirectionalLightBundle {
+        directional_light: DirectionalLight {
+            illuminance: 4000.,
+            ..default()
+        },
+        transform: Transform {
+            // Workaround: Pointing straight up or down prevents directional shadow from rendering
+            rotation: Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2 * 0.6),
+            ..default()
+        },
+        ..default()
+    });
+
+    // Populate scene
+    let mesh_post = meshes.add(shape::Box::new(0.2, 1.5, 0.2).into());
+    let mesh_bead = meshes.add(
+        Icosphere {
+            radius: 0.15,
+            subdivisions: 1,
+        }
+        .into(),
+    );
+    let mesh_marker = meshes.add(Cube { size: 0.5 }.into());
+
+    let material = materials.add(CustomMaterial {
+        alpha_mode: AlphaMode::Opaque,
+        cull_mode: Some(Face::Back),
+    });
+
+    // Fence posts along a winding path, each turned and sized slightly differently
+    commands.spawn((
+        Name::new("Fence"),
+        SplineInstancesBundle {
+            bulk_instances_bundle: BulkInstancesBundle {
+                material: material.clone(),
+                mesh: mesh_post,
+                ..default()
+            },
+            spline: SplineInstances {
+                curve: SplineCurve::CatmullRom(vec![
+                    Vec3::new(-15.0, 0.75, -10.0),
+                    Vec3::new(-5.0, 0.75, -12.0),
+                    Vec3::new(5.0, 0.75, -6.0),
+                    Vec3::new(15.0, 0.75, -10.0),
+                ]),
+                spacing: SplineSpacing::Distance(1.5),
+                rotation_jitter: 0.2,
+                position_jitter: Vec3::new(0.05, 0.0, 0.05),
+                min_scale: 0.9,
+                max_scale: 1.1,
+                seed: 1,
+                ..default()
+            },
+        },
+    ));
+
+    // Cable sagging between two poles
+    commands.spawn((
+        Name::new("Cable"),
+        SplineInstancesBundle {
+            bulk_instances_bundle: BulkInstancesBundle {
+                material: material.clone(),
+                mesh: mesh_bead,
+                ..default()
+            },
+            spline: SplineInstances::new(
+                SplineCurve::Bezier(vec![
+                    Vec3::new(-15.0, 8.0, 5.0),
+                    Vec3::new(-5.0, 2.0, 5.0),
+                    Vec3::new(5.0, 2.0, 5.0),
+                    Vec3::new(15.0, 8.0, 5.0),
+                ]),
+                SplineSpacing::Count(80),
+            ),
+        },
+    ));
+
+    // Road markings that follow the curve as it moves
+    commands.spawn((
+        Name::new("Markers"),
+        SplineInstancesBundle {
+            bulk_instances_bundle: BulkInstancesBundle {
+                material,
+                mesh: mesh_marker,
+                ..default()
+            },
+            spline: SplineInstances {
+                instance_transform: Transform::from_scale(Vec3::new(0.5, 0.1, 2.0)),
+                ..SplineInstances::new(
+                    SplineCurve::CatmullRom(vec![]),
+                    SplineSpacing::Distance(2.0),
+                )
+            },
+        },
+        Swaying,
+    ));
+}
+
+fn sway_markers(time: Res<Time>, mut query_spline: Query<&mut SplineInstances, With<Swaying>>) {
+    let t = time.elapsed_seconds();
+
+    for mut spline in query_spline.iter_mut() {
+        spline.curve = SplineCurve::CatmullRom(
+            (0..6)
+                .map(|i| {
+                    let x = i as f32 * 6.0 - 15.0;
+                    Vec3::new(x, 0.05, 14.0 + (t + i as f32).sin() * 2.0)
+                })
+                .collect(),
+        );
+    }
+}
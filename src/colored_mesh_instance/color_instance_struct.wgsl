@@ -18,3 +18,28 @@ struct ColorInstances {
 };
 #endif
 
+
+#ifdef VERTEX_INSTANCES
+struct ColorInstanceVertex {
+    @location(14) color: vec4<f32>,
+};
+
+fn color_instance_from_vertex(base: InstanceVertex, in: ColorInstanceVertex) -> ColorInstanceData {
+    var instance: ColorInstanceData;
+    instance.base = instance_from_vertex(base);
+    instance.color = in.color;
+    return instance;
+}
+#endif
+
+#ifdef SOA_INSTANCES
+@group(2) @binding(3)
+var<storage> color_instance_color: array<vec4<f32>>;
+
+fn load_color_instance(index: u32) -> ColorInstanceData {
+    var instance: ColorInstanceData;
+    instance.base = load_instance(index);
+    instance.color = color_instance_color[index];
+    return instance;
+}
+#endif
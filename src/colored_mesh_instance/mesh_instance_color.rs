@@ -1,5 +1,6 @@
 use bevy::{
     ecs::reflect::ReflectComponent,
+    math::Vec4,
     prelude::{Color, Component, Deref, DerefMut, Reflect},
 };
 
@@ -18,3 +19,9 @@ impl From<InstanceColor> for Color {
         color.0
     }
 }
+
+impl From<InstanceColor> for Vec4 {
+    fn from(color: InstanceColor) -> Self {
+        color.0.into()
+    }
+}
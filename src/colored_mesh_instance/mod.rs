@@ -3,19 +3,25 @@ pub mod mesh_instance_color;
 pub mod plugin;
 
 use bevy::{
-    ecs::{system::lifetimeless::Read, query::ROQueryItem},
+    ecs::query::ROQueryItem,
     math::{Mat4, Vec4},
-    prelude::{default, Component}, render::render_resource::ShaderType, 
+    prelude::{default, Component},
+    render::render_resource::{ShaderType, VertexAttribute},
+};
+use crate::prelude::{
+    BulkInstance, DataMeshInstance, GpuMeshInstance, Instance, InstanceColor,
+    InstancePayloadSource, InstanceStream, InstanceWgsl, MeshInstance,
 };
-use crate::prelude::{GpuMeshInstance, Instance, InstanceColor, MeshInstance};
 
+/// Mesh instance tinted by an [`InstanceColor`],
+/// laid out like a [`DataMeshInstance`] with a `Vec4` payload
 #[derive(Debug, Default, Clone, PartialEq, Component)]
 pub struct ColorMeshInstance {
     pub base: MeshInstance,
     pub color: Vec4,
 }
 
-/// GPU-friendly data for a since mesh instance
+/// GPU-friendly data for a single colored mesh instance
 #[derive(Debug, Copy, Clone, PartialEq, ShaderType, Component)]
 pub struct GpuColorMeshInstance {
     #[size(144)]
@@ -37,14 +43,13 @@ impl Instance for ColorMeshInstance {
     type ExtractedInstance = Self;
     type PreparedInstance = GpuColorMeshInstance;
 
-    type Query = (<MeshInstance as Instance>::Query, Read<InstanceColor>);
+    type Query = <DataMeshInstance<Vec4, InstanceColor> as Instance>::Query;
 
-    fn extract_instance<'w>(
-        (base, color): ROQueryItem<Self::Query>,
-    ) -> Self::ExtractedInstance {
+    fn extract_instance(item: ROQueryItem<Self::Query>) -> Self::ExtractedInstance {
+        let instance = DataMeshInstance::<Vec4, InstanceColor>::extract_instance(item);
         ColorMeshInstance {
-            base: MeshInstance::extract_instance(base),
-            color: Vec4::new(color.r(), color.g(), color.b(), color.a()),
+            base: instance.base,
+            color: instance.data,
         }
     }
 
@@ -58,4 +63,41 @@ impl Instance for ColorMeshInstance {
     fn transform(instance: &Self::ExtractedInstance) -> Mat4 {
         instance.base.transform
     }
+
+    fn vertex_attributes(shader_location: u32) -> Vec<VertexAttribute> {
+        DataMeshInstance::<Vec4, InstanceColor>::vertex_attributes(shader_location)
+    }
+
+    fn streams() -> Vec<InstanceStream> {
+        DataMeshInstance::<Vec4, InstanceColor>::streams()
+    }
+}
+
+impl BulkInstance for ColorMeshInstance {
+    type Data = Vec4;
+
+    fn prepare_bulk_instance(transform: Mat4, color: &Self::Data) -> Self::PreparedInstance {
+        GpuColorMeshInstance {
+            base: MeshInstance::prepare_bulk_instance(transform, &()),
+            color: *color,
+        }
+    }
+}
+
+impl InstancePayloadSource<Vec4> for InstanceColor {
+    fn payload(&self) -> Vec4 {
+        (*self).into()
+    }
+}
+
+impl InstanceWgsl for ColorMeshInstance {
+    const IMPORT_PATH: &'static str = "indirect_instancing::color_instance_struct";
+    const STRUCT_NAME: &'static str = "ColorInstanceData";
+    const ARRAY_NAME: &'static str = "ColorInstances";
+    const SOA_ACCESSOR: &'static str = "load_color_instance";
+    const VERTEX_ACCESSOR: &'static str = "color_instance_from_vertex";
+
+    fn vertex_structs() -> Vec<&'static str> {
+        vec!["InstanceVertex", "ColorInstanceVertex"]
+    }
 }
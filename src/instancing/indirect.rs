@@ -38,6 +38,19 @@ pub enum IndirectDraw {
     NonIndexed(DrawIndirect),
 }
 
+impl IndirectDraw {
+    /// Size of the draw's arguments within an indirect buffer
+    pub fn size(&self) -> usize {
+        match self {
+            IndirectDraw::Indexed(_) => std::mem::size_of::<DrawIndexedIndirect>(),
+            IndirectDraw::NonIndexed(_) => std::mem::size_of::<DrawIndirect>(),
+        }
+    }
+
+    /// Offset of `instance_count` within the draw's arguments, which is the same for both layouts
+    pub const INSTANCE_COUNT_OFFSET: usize = std::mem::size_of::<u32>();
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum DrawOffsets {
     Indexed { base_index: u32, vertex_offset: i32 },
@@ -0,0 +1,40 @@
+use std::{collections::BTreeMap, marker::PhantomData};
+
+use bevy::{
+    prelude::{default, Entity, Resource},
+    render::render_resource::{BindGroup, BufferId, PreparedBindGroup},
+};
+
+use super::InstanceCompute;
+
+/// Everything an instance bind group refers to,
+/// used to tell when a cached one is out of date
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceBindingKey {
+    pub buffers: Vec<BufferId>,
+    pub offset: u64,
+    pub instance_count: u64,
+    pub live_count: Option<BufferId>,
+}
+
+/// Bind groups for each instance slice driven by `T`,
+/// reused across frames until their inputs change
+#[derive(Resource)]
+pub struct InstanceComputeBindGroups<T: InstanceCompute> {
+    /// Rebuilt whenever the slice's `T` component changes
+    pub uniform: BTreeMap<Entity, PreparedBindGroup<T>>,
+    /// Rebuilt whenever the slice moves, resizes or changes buffers,
+    /// keyed by view for [`InstanceCompute::VIEW`] jobs
+    pub instance: BTreeMap<(Entity, Option<Entity>), (InstanceBindingKey, BindGroup)>,
+    marker: PhantomData<T>,
+}
+
+impl<T: InstanceCompute> Default for InstanceComputeBindGroups<T> {
+    fn default() -> Self {
+        Self {
+            uniform: default(),
+            instance: default(),
+            marker: default(),
+        }
+    }
+}
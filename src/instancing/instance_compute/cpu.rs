@@ -0,0 +1,257 @@
+use std::{collections::BTreeMap, marker::PhantomData};
+
+use bevy::{
+    prelude::{default, Entity, Query, Res, ResMut, Resource},
+    render::renderer::RenderQueue,
+    time::Time,
+};
+
+use crate::prelude::{write_instance_slice, Instance, ViewInstanceSliceTargets};
+
+use super::{
+    run_mode::{InstanceComputeChanged, InstanceComputeRunMode, InstanceComputeSchedule},
+    InstanceCompute,
+};
+
+/// Where [`InstanceCompute`] jobs run.
+///
+/// Insert before adding [`IndirectRenderingPlugin`](crate::prelude::IndirectRenderingPlugin)
+/// to override the default.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Resource)]
+pub enum InstanceComputeBackend {
+    /// Dispatch each type's compute shader
+    #[default]
+    Gpu,
+    /// Run each type's [`InstanceCompute::compute_cpu`] and upload the results,
+    /// for platforms without compute shaders or for debugging.
+    ///
+    /// Where storage buffers are unsupported, [`InstanceBufferMode::Binding`] and
+    /// [`InstanceBufferMode::StructOfArrays`] fall back to [`InstanceBufferMode::Vertex`]
+    /// so that slices can still be written.
+    ///
+    /// [`InstanceBufferMode::Binding`]: crate::prelude::InstanceBufferMode::Binding
+    /// [`InstanceBufferMode::StructOfArrays`]: crate::prelude::InstanceBufferMode::StructOfArrays
+    /// [`InstanceBufferMode::Vertex`]: crate::prelude::InstanceBufferMode::Vertex
+    Cpu,
+}
+
+/// CPU-side contents of each slice driven by `T` under [`InstanceComputeBackend::Cpu`],
+/// persisting across runs like the GPU contents do
+#[derive(Resource)]
+pub struct InstanceComputeCpuInstances<T: InstanceCompute> {
+    pub instances: BTreeMap<Entity, Vec<<T::Instance as Instance>::PreparedInstance>>,
+    marker: PhantomData<T>,
+}
+
+impl<T: InstanceCompute> Default for InstanceComputeCpuInstances<T> {
+    fn default() -> Self {
+        Self {
+            instances: default(),
+            marker: default(),
+        }
+    }
+}
+
+/// Run `T`'s CPU implementation over a slice's persistent contents,
+/// starting from default instances if the slice was reset or resized.
+///
+/// Returns `true` if `instances` were computed and should be uploaded.
+pub fn compute_cpu_instances<T: InstanceCompute>(
+    instance_compute: &T,
+    instances: &mut Vec<<T::Instance as Instance>::PreparedInstance>,
+    instance_count: usize,
+    reset: bool,
+) -> bool {
+    if reset || instances.len() != instance_count {
+        instances.clear();
+        instances.resize(instance_count, default());
+    }
+
+    !instances.is_empty() && instance_compute.compute_cpu(instances)
+}
+
+/// Run `T`'s CPU implementation for each slice due to run,
+/// and upload the results into every view's copy of the slice
+#[allow(clippy::type_complexity)]
+pub fn queue_compute_instances_cpu<T: InstanceCompute>(
+    render_queue: Res<RenderQueue>,
+    time: Res<Time>,
+    mut compute_schedule: ResMut<InstanceComputeSchedule<T>>,
+    mut cpu_instances: ResMut<InstanceComputeCpuInstances<T>>,
+    query_instance_slice: Query<(
+        Entity,
+        &T,
+        &ViewInstanceSliceTargets,
+        Option<&InstanceComputeRunMode>,
+        Option<&InstanceComputeChanged<T>>,
+    )>,
+) {
+    compute_schedule
+        .last_run
+        .retain(|entity, _| query_instance_slice.contains(*entity));
+    compute_schedule.clear_dispatched();
+    cpu_instances
+        .instances
+        .retain(|entity, _| query_instance_slice.contains(*entity));
+
+    for (instance_slice_entity, instance_compute, view_targets, run_mode, changed) in
+        query_instance_slice.iter()
+    {
+        // Every view's copy holds the same number of instances
+        let instance_count = match view_targets.0.first() {
+            Some(view_target) => view_target.range.instance_count as usize,
+            None => continue,
+        };
+
+        let reset = view_targets
+            .0
+            .iter()
+            .any(|view_target| view_target.target.reset);
+
+        if !compute_schedule.should_run(
+            instance_slice_entity,
+            run_mode.copied().unwrap_or_default(),
+            changed.is_some(),
+            reset,
+            time.elapsed(),
+        ) {
+            continue;
+        }
+
+        let instances = cpu_instances
+            .instances
+            .entry(instance_slice_entity)
+            .or_default();
+
+        if !compute_cpu_instances(instance_compute, instances, instance_count, reset) {
+            continue;
+        }
+
+        for view_target in &view_targets.0 {
+            write_instance_slice::<T::Instance>(
+                &render_queue,
+                &view_target.range,
+                &view_target.target,
+                0,
+                instances,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        ecs::system::lifetimeless::Read,
+        prelude::Component,
+        render::{extract_component::ExtractComponent, render_resource::AsBindGroup},
+    };
+
+    use super::*;
+    use crate::prelude::{GpuMeshInstance, MeshInstance};
+
+    /// Adds `step` to each instance's mesh index, exposing whether contents persisted
+    #[derive(Debug, Clone, Component, AsBindGroup)]
+    struct Accumulate {
+        #[uniform(0)]
+        step: u32,
+        cpu: bool,
+    }
+
+    impl ExtractComponent for Accumulate {
+        type Query = Read<Self>;
+
+        type Filter = ();
+
+        fn extract_component(item: bevy::ecs::query::QueryItem<Self::Query>) -> Self {
+            item.clone()
+        }
+    }
+
+    impl InstanceCompute for Accumulate {
+        type Instance = MeshInstance;
+
+        fn compute_cpu(&self, instances: &mut [GpuMeshInstance]) -> bool {
+            if !self.cpu {
+                return false;
+            }
+
+            for instance in instances {
+                instance.mesh += self.step;
+            }
+
+            true
+        }
+    }
+
+    const ACCUMULATE: Accumulate = Accumulate { step: 1, cpu: true };
+
+    fn meshes(instances: &[GpuMeshInstance]) -> Vec<u32> {
+        instances.iter().map(|instance| instance.mesh).collect()
+    }
+
+    #[test]
+    fn contents_persist_between_runs() {
+        let mut instances = vec![];
+
+        assert!(compute_cpu_instances(&ACCUMULATE, &mut instances, 3, false));
+        assert_eq!(meshes(&instances), [1, 1, 1]);
+
+        assert!(compute_cpu_instances(&ACCUMULATE, &mut instances, 3, false));
+        assert_eq!(meshes(&instances), [2, 2, 2]);
+    }
+
+    #[test]
+    fn reset_restarts_from_defaults() {
+        let mut instances = vec![];
+
+        compute_cpu_instances(&ACCUMULATE, &mut instances, 3, false);
+        compute_cpu_instances(&ACCUMULATE, &mut instances, 3, false);
+
+        assert!(compute_cpu_instances(&ACCUMULATE, &mut instances, 3, true));
+        assert_eq!(meshes(&instances), [1, 1, 1]);
+    }
+
+    #[test]
+    fn resize_restarts_from_defaults() {
+        let mut instances = vec![];
+
+        compute_cpu_instances(&ACCUMULATE, &mut instances, 3, false);
+
+        assert!(compute_cpu_instances(&ACCUMULATE, &mut instances, 5, false));
+        assert_eq!(meshes(&instances), [1, 1, 1, 1, 1]);
+
+        assert!(compute_cpu_instances(&ACCUMULATE, &mut instances, 2, false));
+        assert_eq!(meshes(&instances), [1, 1]);
+    }
+
+    #[test]
+    fn empty_slices_are_skipped() {
+        let mut instances = vec![GpuMeshInstance::default(); 2];
+
+        assert!(!compute_cpu_instances(
+            &ACCUMULATE,
+            &mut instances,
+            0,
+            false
+        ));
+        assert!(instances.is_empty());
+    }
+
+    #[test]
+    fn missing_cpu_implementation_is_reported() {
+        let accumulate = Accumulate {
+            cpu: false,
+            ..ACCUMULATE
+        };
+        let mut instances = vec![];
+
+        assert!(!compute_cpu_instances(
+            &accumulate,
+            &mut instances,
+            3,
+            false
+        ));
+        assert_eq!(meshes(&instances), [0, 0, 0]);
+    }
+}
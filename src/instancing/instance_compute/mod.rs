@@ -1,3 +1,10 @@
+pub mod bind_groups;
+pub mod cpu;
+pub mod run_mode;
+pub mod state;
+pub mod view;
+
+use std::collections::{btree_map::Entry, HashSet};
 use std::marker::PhantomData;
 use std::num::NonZeroU64;
 use std::{borrow::Cow, hash::Hash};
@@ -5,30 +12,48 @@ use std::{borrow::Cow, hash::Hash};
 use bevy::prelude::Resource;
 use bevy::{
     asset::load_internal_asset,
+    math::UVec3,
     prelude::{
-        debug, default, App, AssetServer, Commands, Entity, FromWorld, HandleUntyped, Image,
+        debug, default, error, App, AssetServer, Commands, Entity, FromWorld, HandleUntyped, Image,
         Plugin, Query, Res, ResMut, Shader, World,
     },
     reflect::TypeUuid,
     render::{
         extract_component::{ExtractComponent, ExtractComponentPlugin},
+        globals::GlobalsBuffer,
         render_asset::RenderAssets,
-        render_graph::{Node, NodeLabel, RenderGraph},
+        render_graph::{Node, NodeId, NodeLabel, RenderGraph, RenderGraphError},
         render_resource::{
-            AsBindGroup, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
-            BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType,
-            BufferBinding, BufferBindingType, ComputePassDescriptor, ComputePipelineDescriptor,
-            PipelineCache, PreparedBindGroup, ShaderRef, ShaderStages, SpecializedComputePipeline,
-            SpecializedComputePipelines,
+            AsBindGroup, AsBindGroupError, BindGroup, BindGroupDescriptor, BindGroupEntry,
+            BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource,
+            BindingType, Buffer, BufferBinding, BufferBindingType, ComputePassDescriptor,
+            ComputePipelineDescriptor, PipelineCache, ShaderRef, ShaderSize, ShaderStages,
+            SpecializedComputePipeline, SpecializedComputePipelines,
         },
         renderer::RenderDevice,
         texture::FallbackImage,
+        view::{ViewUniformOffset, ViewUniforms},
         RenderApp, RenderStage,
     },
+    time::Time,
 };
 use bevy::{prelude::Handle, render::render_resource::CachedComputePipelineId};
 
-use crate::prelude::{InstanceSliceRange, InstanceSliceTarget};
+use crate::prelude::{
+    InstanceBufferMode, InstanceSliceLiveCounts, InstanceSliceRange, InstanceSliceTarget,
+    InstanceStream, InstancedMeshPipeline, ViewInstanceSliceTargets,
+};
+
+use self::{
+    bind_groups::{InstanceBindingKey, InstanceComputeBindGroups},
+    cpu::{queue_compute_instances_cpu, InstanceComputeBackend, InstanceComputeCpuInstances},
+    run_mode::{
+        extract_instance_compute_changes, InstanceComputeChanged, InstanceComputeRunMode,
+        InstanceComputeSchedule,
+    },
+    state::{state_bind_group_layout, InstanceComputeState},
+    view::{view_bind_group, view_bind_group_layout},
+};
 
 use super::render::instance::Instance;
 
@@ -57,8 +82,90 @@ impl<T> Into<NodeLabel> for InstanceComputeLabel<T> {
 pub const INSTANCE_COMPUTE_SHADER_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 3197649561934630342);
 
-#[derive(Debug, Default, Copy, Clone)]
-pub struct InstanceComputePlugin<T: InstanceCompute>(PhantomData<T>);
+/// Phases of instance compute, run in order with each finishing before the next begins
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum InstanceComputeStage {
+    /// Writes new instances, such as emitting particles
+    Spawn,
+    /// Updates existing instances
+    #[default]
+    Simulate,
+    /// Reads simulated instances to decide what gets drawn
+    Cull,
+}
+
+impl InstanceComputeStage {
+    pub const ALL: [Self; 3] = [Self::Spawn, Self::Simulate, Self::Cull];
+
+    /// Render graph node that runs once every job in this stage has been dispatched
+    pub fn label(self) -> &'static str {
+        match self {
+            InstanceComputeStage::Spawn => "instance_compute_spawn",
+            InstanceComputeStage::Simulate => "instance_compute_simulate",
+            InstanceComputeStage::Cull => "instance_compute_cull",
+        }
+    }
+
+    /// The stage that must finish before this one starts
+    pub fn previous(self) -> Option<Self> {
+        match self {
+            InstanceComputeStage::Spawn => None,
+            InstanceComputeStage::Simulate => Some(InstanceComputeStage::Spawn),
+            InstanceComputeStage::Cull => Some(InstanceComputeStage::Simulate),
+        }
+    }
+}
+
+/// Render graph node running every `T` job
+pub fn instance_compute_node<T: InstanceCompute>() -> NodeLabel {
+    InstanceComputeLabel::<T>::default().into()
+}
+
+/// Ordering constraints between [`InstanceCompute`] nodes
+/// waiting for the other node's plugin to be added
+#[derive(Debug, Default, Clone, Resource)]
+struct InstanceComputeOrdering(Vec<(NodeLabel, NodeLabel)>);
+
+/// Dispatches `T` jobs during [`InstanceCompute::STAGE`].
+///
+/// Within a stage, jobs of different types run in an undefined order
+/// unless constrained with [`before`](Self::before) or [`after`](Self::after),
+/// which is how several types can operate on the same slice in sequence.
+/// Orderings that would form a cycle, including across stages, are logged and ignored.
+///
+/// Under [`InstanceComputeBackend::Cpu`], runs [`InstanceCompute::compute_cpu`] instead,
+/// before any GPU work for the frame.
+///
+/// Requires [`IndirectRenderingPlugin`](crate::prelude::IndirectRenderingPlugin).
+pub struct InstanceComputePlugin<T: InstanceCompute> {
+    before: Vec<NodeLabel>,
+    after: Vec<NodeLabel>,
+    marker: PhantomData<T>,
+}
+
+impl<T: InstanceCompute> Default for InstanceComputePlugin<T> {
+    fn default() -> Self {
+        Self {
+            before: default(),
+            after: default(),
+            marker: default(),
+        }
+    }
+}
+
+impl<T: InstanceCompute> InstanceComputePlugin<T> {
+    /// Dispatch before `U` jobs in the same stage
+    pub fn before<U: InstanceCompute>(mut self) -> Self {
+        self.before.push(instance_compute_node::<U>());
+        self
+    }
+
+    /// Dispatch after `U` jobs in the same stage
+    pub fn after<U: InstanceCompute>(mut self) -> Self {
+        self.after.push(instance_compute_node::<U>());
+        self
+    }
+}
 
 impl<T> Plugin for InstanceComputePlugin<T>
 where
@@ -76,22 +183,92 @@ where
         app.add_plugin(ExtractComponentPlugin::<T>::default());
 
         let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .init_resource::<InstanceComputeSchedule<T>>()
+            .add_system_to_stage(RenderStage::Extract, extract_instance_compute_changes::<T>);
+
+        let backend = render_app
+            .world
+            .get_resource::<InstanceComputeBackend>()
+            .copied()
+            .unwrap_or_default();
+
+        if backend == InstanceComputeBackend::Cpu {
+            render_app
+                .init_resource::<InstanceComputeCpuInstances<T>>()
+                .add_system_to_stage(RenderStage::Queue, queue_compute_instances_cpu::<T>);
+            return;
+        }
+
         render_app
             .init_resource::<InstanceComputePipeline<T>>()
+            .init_resource::<InstanceComputeState<T>>()
+            .init_resource::<InstanceComputeBindGroups<T>>()
             .init_resource::<SpecializedComputePipelines<InstanceComputePipeline<T>>>()
             .add_system_to_stage(RenderStage::Queue, queue_compute_instances::<T>);
 
+        let mut ordering = render_app
+            .world
+            .remove_resource::<InstanceComputeOrdering>()
+            .unwrap_or_default();
+
         let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
         render_graph.add_node(
             InstanceComputeLabel::<T>::default(),
             InstanceComputeNode::<T>::default(),
         );
+
+        // Run within this type's stage
+        if let Some(previous) = T::STAGE.previous() {
+            render_graph
+                .add_node_edge(previous.label(), instance_compute_node::<T>())
+                .unwrap();
+        }
         render_graph
-            .add_node_edge(
-                InstanceComputeLabel::<T>::default(),
-                bevy::render::main_graph::node::CAMERA_DRIVER,
-            )
+            .add_node_edge(instance_compute_node::<T>(), T::STAGE.label())
             .unwrap();
+
+        // Apply orderings whose nodes both exist,
+        // including those declared by earlier plugins against this one
+        ordering.0.extend(
+            self.before
+                .iter()
+                .map(|before| (instance_compute_node::<T>(), before.clone())),
+        );
+        ordering.0.extend(
+            self.after
+                .iter()
+                .map(|after| (after.clone(), instance_compute_node::<T>())),
+        );
+        ordering.0.retain(|(output, input)| {
+            let (output_id, input_id) = match (
+                render_graph.get_node_id(output.clone()),
+                render_graph.get_node_id(input.clone()),
+            ) {
+                (Ok(output_id), Ok(input_id)) => (output_id, input_id),
+                // Wait for the other node's plugin
+                _ => return true,
+            };
+
+            if node_reaches(&render_graph, input_id, output_id) {
+                error!(
+                    "Ignoring instance compute ordering of {output:?} before {input:?}, \
+                    as it would form a cycle"
+                );
+                return false;
+            }
+
+            match render_graph.add_node_edge(output_id, input_id) {
+                Ok(()) | Err(RenderGraphError::EdgeAlreadyExists(_)) => (),
+                Err(e) => {
+                    error!("Ignoring instance compute ordering of {output:?} before {input:?}: {e}")
+                }
+            }
+
+            false
+        });
+
+        render_app.world.insert_resource(ordering);
     }
 }
 
@@ -99,6 +276,14 @@ where
 pub struct InstanceComputePipeline<T: InstanceCompute> {
     pub uniform_bind_group_layout: BindGroupLayout,
     pub instance_bind_group_layout: BindGroupLayout,
+    /// Streams bound at group 1 under [`InstanceBufferMode::StructOfArrays`], in binding order
+    pub instance_streams: Vec<InstanceStream>,
+    /// Present when [`InstanceCompute::STATE_SIZE`] is set, bound at group 2
+    pub state_bind_group_layout: Option<BindGroupLayout>,
+    /// Present when [`InstanceCompute::GLOBALS`] or [`InstanceCompute::VIEW`] is set, bound at group 3
+    pub view_bind_group_layout: Option<BindGroupLayout>,
+    /// Bound at group 2 in place of per-instance state when only group 3 is in use
+    pub empty_bind_group: Option<(BindGroupLayout, BindGroup)>,
     pub shader: Option<Handle<Shader>>,
     marker: PhantomData<T>,
 }
@@ -115,19 +300,47 @@ where
 
         let mut descriptor = ComputePipelineDescriptor {
             label: Some("instance compute".into()),
-            layout: Some(vec![
-                self.uniform_bind_group_layout.clone(),
-                self.instance_bind_group_layout.clone(),
-            ]),
+            layout: Some(
+                [
+                    self.uniform_bind_group_layout.clone(),
+                    self.instance_bind_group_layout.clone(),
+                ]
+                .into_iter()
+                .chain(self.state_bind_group_layout.clone().or_else(|| {
+                    self.empty_bind_group
+                        .as_ref()
+                        .map(|(layout, _)| layout.clone())
+                }))
+                .chain(self.view_bind_group_layout.clone())
+                .collect(),
+            ),
             shader: if let Some(shader) = &self.shader {
                 shader.clone_weak()
             } else {
                 INSTANCE_COMPUTE_SHADER_HANDLE.typed()
             },
-            shader_defs: vec![],
+            shader_defs: if self.instance_streams.is_empty() {
+                vec![]
+            } else {
+                vec!["SOA_INSTANCES".into()]
+            },
             entry_point: Cow::from("instances"),
         };
 
+        descriptor
+            .shader_defs
+            .push(workgroup_size_shader_def(T::WORKGROUP_SIZE));
+
+        if T::GLOBALS {
+            descriptor
+                .shader_defs
+                .push("INSTANCE_COMPUTE_GLOBALS".into());
+        }
+
+        if T::VIEW {
+            descriptor.shader_defs.push("INSTANCE_COMPUTE_VIEW".into());
+        }
+
         T::specialize(self, &mut descriptor, key);
 
         descriptor
@@ -140,19 +353,60 @@ impl<T: InstanceCompute> FromWorld for InstanceComputePipeline<T> {
 
         let uniform_bind_group_layout = T::bind_group_layout(render_device);
 
+        let instance_streams = match world
+            .resource::<InstancedMeshPipeline>()
+            .instance_buffer_mode
+        {
+            InstanceBufferMode::StructOfArrays => {
+                let streams = <T::Instance as Instance>::streams();
+                T::streams()
+                    .into_iter()
+                    .map(|stream| streams[stream])
+                    .collect()
+            }
+            _ => vec![],
+        };
+
+        // Instance buffer or streams, followed by the live count if requested
+        let binding_count = instance_streams.len().max(1) + T::LIVE_COUNT as usize;
+
+        let entries = (0..binding_count as u32)
+            .map(|binding| BindGroupLayoutEntry {
+                binding,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            })
+            .collect::<Vec<_>>();
+
         let instance_bind_group_layout =
             render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
                 label: Some("instance buffer bind group"),
-                entries: &[BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: ShaderStages::COMPUTE,
-                    ty: BindingType::Buffer {
-                        ty: BufferBindingType::Storage { read_only: false },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
+                entries: &entries,
+            });
+
+        let state_bind_group_layout = T::STATE_SIZE.map(|_| state_bind_group_layout(render_device));
+
+        let view_bind_group_layout =
+            (T::GLOBALS || T::VIEW).then(|| view_bind_group_layout::<T>(render_device));
+
+        // Bind groups can't be skipped, so pad group 2 if it would otherwise be missing
+        let empty_bind_group =
+            (view_bind_group_layout.is_some() && state_bind_group_layout.is_none()).then(|| {
+                let layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    label: Some("instance compute empty bind group"),
+                    entries: &[],
+                });
+                let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("instance compute empty bind group"),
+                    layout: &layout,
+                    entries: &[],
+                });
+                (layout, bind_group)
             });
 
         let asset_server = world.resource::<AssetServer>();
@@ -165,12 +419,38 @@ impl<T: InstanceCompute> FromWorld for InstanceComputePipeline<T> {
         InstanceComputePipeline {
             uniform_bind_group_layout,
             instance_bind_group_layout,
+            instance_streams,
+            state_bind_group_layout,
+            view_bind_group_layout,
+            empty_bind_group,
             shader,
             marker: default(),
         }
     }
 }
 
+/// Whether `to` is reachable from `from` by following node outputs
+fn node_reaches(render_graph: &RenderGraph, from: NodeId, to: NodeId) -> bool {
+    let mut visited = HashSet::new();
+    let mut stack = vec![from];
+
+    while let Some(node) = stack.pop() {
+        if node == to {
+            return true;
+        }
+
+        if !visited.insert(node) {
+            continue;
+        }
+
+        if let Ok(outputs) = render_graph.iter_node_outputs(node) {
+            stack.extend(outputs.map(|(_, input)| input.id));
+        }
+    }
+
+    false
+}
+
 struct InstanceComputeNode<T>(PhantomData<T>);
 
 impl<T: InstanceCompute> Default for InstanceComputeNode<T> {
@@ -180,16 +460,33 @@ impl<T: InstanceCompute> Default for InstanceComputeNode<T> {
 }
 
 #[derive(Resource)]
-struct InstanceComputeQueue<T: InstanceCompute>(Vec<InstanceComputeJob<T>>);
+struct InstanceComputeQueue<T: InstanceCompute>(Vec<InstanceComputeJob>, PhantomData<T>);
 
-struct InstanceComputeJob<T: InstanceCompute> {
+struct InstanceComputeJob {
     pipeline: CachedComputePipelineId,
-    uniform_bind_group: PreparedBindGroup<T>,
+    uniform_bind_group: BindGroup,
     instance_bind_group: BindGroup,
+    /// Per-instance state, or the pipeline's empty bind group
+    state_bind_group: Option<BindGroup>,
+    view_bind_group: Option<BindGroup>,
+    view_offsets: Vec<u32>,
     instance_count: u64,
+    workgroups: UVec3,
 }
 
-const WORKGROUP_SIZE: u64 = 64;
+/// Shader def exposing an [`InstanceCompute::WORKGROUP_SIZE`] to WGSL,
+/// i.e. `WORKGROUP_SIZE_64_1_1`, for use with `#ifdef` around `@workgroup_size`
+pub fn workgroup_size_shader_def(workgroup_size: UVec3) -> String {
+    format!(
+        "WORKGROUP_SIZE_{}_{}_{}",
+        workgroup_size.x, workgroup_size.y, workgroup_size.z
+    )
+}
+
+/// Number of workgroups needed to cover every invocation in `dispatch_size`
+pub fn workgroup_count(dispatch_size: UVec3, workgroup_size: UVec3) -> UVec3 {
+    (dispatch_size + workgroup_size - UVec3::ONE) / workgroup_size
+}
 
 impl<T> Node for InstanceComputeNode<T>
 where
@@ -205,6 +502,17 @@ where
         let pipeline_cache = world.resource::<PipelineCache>();
 
         let compute_jobs = &world.resource::<InstanceComputeQueue<T>>().0;
+        if compute_jobs.is_empty() {
+            return Ok(());
+        }
+
+        // Dispatches within a pass are ordered, so every job can share one
+        let mut pass = render_context
+            .command_encoder
+            .begin_compute_pass(&ComputePassDescriptor {
+                label: Some("instance compute"),
+            });
+
         for compute_job in compute_jobs {
             if let Some(instance_pipeline) =
                 pipeline_cache.get_compute_pipeline(compute_job.pipeline)
@@ -214,18 +522,19 @@ where
                     compute_job.instance_count
                 );
 
-                let mut pass = render_context
-                    .command_encoder
-                    .begin_compute_pass(&ComputePassDescriptor::default());
-
-                pass.set_bind_group(0, &compute_job.uniform_bind_group.bind_group, &[]);
+                pass.set_bind_group(0, &compute_job.uniform_bind_group, &[]);
                 pass.set_bind_group(1, &compute_job.instance_bind_group, &[]);
+                if let Some(state_bind_group) = &compute_job.state_bind_group {
+                    pass.set_bind_group(2, state_bind_group, &[]);
+                }
+                if let Some(view_bind_group) = &compute_job.view_bind_group {
+                    pass.set_bind_group(3, view_bind_group, &compute_job.view_offsets);
+                }
 
-                let instance_workgroups =
-                    (compute_job.instance_count / WORKGROUP_SIZE).max(1) as u32;
+                let UVec3 { x, y, z } = compute_job.workgroups;
 
                 pass.set_pipeline(instance_pipeline);
-                pass.dispatch_workgroups(instance_workgroups, 1, 1);
+                pass.dispatch_workgroups(x, y, z);
             }
         }
 
@@ -233,14 +542,31 @@ where
     }
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn queue_compute_instances<T>(
     pipeline: Res<InstanceComputePipeline<T>>,
     render_device: Res<RenderDevice>,
+    time: Res<Time>,
+    mut compute_state: ResMut<InstanceComputeState<T>>,
+    mut compute_schedule: ResMut<InstanceComputeSchedule<T>>,
+    mut bind_groups: ResMut<InstanceComputeBindGroups<T>>,
+    mut live_counts: ResMut<InstanceSliceLiveCounts>,
     mut pipeline_cache: ResMut<PipelineCache>,
     mut compute_pipelines: ResMut<SpecializedComputePipelines<InstanceComputePipeline<T>>>,
     render_images: Res<RenderAssets<Image>>,
     fallback_image: Res<FallbackImage>,
-    query_instance_slice: Query<(Entity, &T, &InstanceSliceRange, &InstanceSliceTarget)>,
+    globals_buffer: Res<GlobalsBuffer>,
+    view_uniforms: Res<ViewUniforms>,
+    query_views: Query<&ViewUniformOffset>,
+    query_instance_slice: Query<(
+        Entity,
+        &T,
+        &InstanceSliceRange,
+        &InstanceSliceTarget,
+        Option<&ViewInstanceSliceTargets>,
+        Option<&InstanceComputeRunMode>,
+        Option<&InstanceComputeChanged<T>>,
+    )>,
     mut commands: Commands,
 ) where
     T: InstanceCompute,
@@ -249,71 +575,340 @@ pub fn queue_compute_instances<T>(
     debug!("queue_compute_instances");
     let mut instance_compute_queue = vec![];
 
+    // Drop state and bind groups belonging to slices that no longer exist
+    compute_state
+        .buffers
+        .retain(|entity, _| query_instance_slice.contains(*entity));
+    compute_schedule
+        .last_run
+        .retain(|entity, _| query_instance_slice.contains(*entity));
+    compute_schedule
+        .deferred
+        .retain(|entity| query_instance_slice.contains(*entity));
+    compute_schedule.clear_dispatched();
+    bind_groups
+        .uniform
+        .retain(|entity, _| query_instance_slice.contains(*entity));
+    bind_groups.instance.retain(|(entity, view_entity), _| {
+        query_instance_slice.contains(*entity)
+            && view_entity.is_none_or(|view_entity| query_views.contains(view_entity))
+    });
+
+    // Shared by every job, with views selected by dynamic offset
+    let view_bind_group = pipeline.view_bind_group_layout.as_ref().map(|layout| {
+        view_bind_group::<T>(&render_device, layout, &globals_buffer, &view_uniforms)
+    });
+
     for (
         instance_slice_entity,
         instance_compute_uniform,
         instance_slice_range,
         instance_slice_buffer,
+        view_targets,
+        run_mode,
+        changed,
     ) in query_instance_slice.iter()
     {
         debug!("Instance slice {instance_slice_entity:?}");
-        let uniform_bind_group = match instance_compute_uniform.as_bind_group(
-            &pipeline.uniform_bind_group_layout,
-            &render_device,
-            &render_images,
-            &fallback_image,
-        ) {
-            Ok(uniform_bind_group) => uniform_bind_group,
-            Err(_) => panic!("Failed to create uniform bind group"),
-        };
 
-        let instance_bind_group = render_device.create_bind_group(&BindGroupDescriptor {
-            label: None,
-            layout: &pipeline.instance_bind_group_layout,
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: BindingResource::Buffer(BufferBinding {
-                    buffer: &instance_slice_buffer.buffer,
-                    offset: std::mem::size_of::<<T::Instance as Instance>::PreparedInstance>()
-                        as u64
-                        * instance_slice_range.offset,
-                    size: NonZeroU64::new(
-                        std::mem::size_of::<<T::Instance as Instance>::PreparedInstance>() as u64
-                            * instance_slice_range.instance_count,
-                    ),
-                }),
-            }],
+        let live_count = T::LIVE_COUNT.then(|| {
+            live_counts
+                .get_or_create(&render_device, instance_slice_entity)
+                .clone()
         });
 
-        let pipeline = compute_pipelines.specialize(
+        let pipeline_id = compute_pipelines.specialize(
             &mut pipeline_cache,
             &pipeline,
             instance_compute_uniform.into(),
         );
 
-        debug!(
-            "Queueing InstanceComputeJob for {} cells",
-            instance_slice_range.instance_count
-        );
+        if changed.is_some() {
+            bind_groups.uniform.remove(&instance_slice_entity);
+        }
 
-        instance_compute_queue.push(InstanceComputeJob {
-            pipeline,
-            uniform_bind_group,
-            instance_bind_group,
-            instance_count: instance_slice_range.instance_count,
+        if let Entry::Vacant(entry) = bind_groups.uniform.entry(instance_slice_entity) {
+            match instance_compute_uniform.as_bind_group(
+                &pipeline.uniform_bind_group_layout,
+                &render_device,
+                &render_images,
+                &fallback_image,
+            ) {
+                Ok(uniform_bind_group) => {
+                    entry.insert(uniform_bind_group);
+                }
+                Err(AsBindGroupError::RetryNextUpdate) => {
+                    debug!("Deferring instance compute for {instance_slice_entity:?}");
+                }
+            }
+        }
+
+        // Defer until every input is ready, making sure the job runs once it is
+        if !bind_groups.uniform.contains_key(&instance_slice_entity)
+            || pipeline_cache.get_compute_pipeline(pipeline_id).is_none()
+            || matches!(view_bind_group, Some(None))
+        {
+            compute_schedule.deferred.insert(instance_slice_entity);
+            continue;
+        }
+
+        // View-dependent jobs run against each view's copy of the slice
+        let targets = if T::VIEW {
+            view_targets
+                .map(|view_targets| {
+                    view_targets
+                        .0
+                        .iter()
+                        .map(|view_target| {
+                            (
+                                Some(view_target.view),
+                                &view_target.range,
+                                &view_target.target,
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        } else {
+            vec![(None, instance_slice_range, instance_slice_buffer)]
+        };
+
+        // Not visible in any view
+        if targets.is_empty() {
+            continue;
+        }
+
+        let deferred = compute_schedule.deferred.remove(&instance_slice_entity);
+
+        let reset = targets
+            .iter()
+            .any(|(_, _, slice_target)| slice_target.reset);
+
+        if !compute_schedule.should_run(
+            instance_slice_entity,
+            run_mode.copied().unwrap_or_default(),
+            changed.is_some(),
+            reset || deferred,
+            time.elapsed(),
+        ) {
+            continue;
+        }
+
+        // Per-instance state advances once per run, however many views are dispatched
+        let state_bind_group = pipeline
+            .state_bind_group_layout
+            .as_ref()
+            .zip(T::STATE_SIZE)
+            .map(|(layout, state_size)| {
+                compute_state
+                    .get_or_create(
+                        &render_device,
+                        layout,
+                        instance_slice_entity,
+                        state_size.get(),
+                        instance_slice_range.instance_count,
+                    )
+                    .swap()
+            })
+            .or_else(|| {
+                pipeline
+                    .empty_bind_group
+                    .as_ref()
+                    .map(|(_, bind_group)| bind_group.clone())
+            });
+
+        for (view_entity, slice_range, slice_target) in targets {
+            let view_offsets = match view_entity {
+                Some(view_entity) => match query_views.get(view_entity) {
+                    Ok(view_uniform_offset) => vec![view_uniform_offset.offset],
+                    Err(_) => continue,
+                },
+                None => vec![],
+            };
+
+            let instance_binding_key = InstanceBindingKey {
+                buffers: slice_target
+                    .buffers
+                    .iter()
+                    .map(|buffer| buffer.id())
+                    .collect(),
+                offset: slice_range.offset,
+                instance_count: slice_range.instance_count,
+                live_count: live_count.as_ref().map(|live_count| live_count.id()),
+            };
+
+            let instance_bind_group = match bind_groups
+                .instance
+                .get(&(instance_slice_entity, view_entity))
+            {
+                Some((key, instance_bind_group)) if *key == instance_binding_key => {
+                    instance_bind_group.clone()
+                }
+                _ => {
+                    let instance_bind_group = create_instance_bind_group::<T>(
+                        &render_device,
+                        &pipeline,
+                        slice_range,
+                        slice_target,
+                        live_count.as_ref(),
+                    );
+
+                    bind_groups.instance.insert(
+                        (instance_slice_entity, view_entity),
+                        (instance_binding_key, instance_bind_group.clone()),
+                    );
+
+                    instance_bind_group
+                }
+            };
+
+            debug!(
+                "Queueing InstanceComputeJob for {} cells",
+                slice_range.instance_count
+            );
+
+            instance_compute_queue.push(InstanceComputeJob {
+                pipeline: pipeline_id,
+                uniform_bind_group: bind_groups.uniform[&instance_slice_entity]
+                    .bind_group
+                    .clone(),
+                instance_bind_group,
+                state_bind_group: state_bind_group.clone(),
+                view_bind_group: view_bind_group.clone().flatten(),
+                view_offsets,
+                instance_count: slice_range.instance_count,
+                workgroups: workgroup_count(
+                    T::dispatch_size(slice_range.instance_count),
+                    T::WORKGROUP_SIZE,
+                ),
+            });
+        }
+    }
+
+    commands.insert_resource(InstanceComputeQueue::<T>(instance_compute_queue, default()));
+}
+
+/// Bind the instance buffer or streams of a slice, followed by its live count if any
+fn create_instance_bind_group<T: InstanceCompute>(
+    render_device: &RenderDevice,
+    pipeline: &InstanceComputePipeline<T>,
+    slice_range: &InstanceSliceRange,
+    slice_target: &InstanceSliceTarget,
+    live_count: Option<&Buffer>,
+) -> BindGroup {
+    let mut entries = if pipeline.instance_streams.is_empty() {
+        vec![BindGroupEntry {
+            binding: 0,
+            resource: BindingResource::Buffer(BufferBinding {
+                buffer: &slice_target.buffers[0],
+                offset: <T::Instance as Instance>::PreparedInstance::SHADER_SIZE.get()
+                    * slice_range.offset,
+                size: NonZeroU64::new(
+                    <T::Instance as Instance>::PreparedInstance::SHADER_SIZE.get()
+                        * slice_range.instance_count,
+                ),
+            }),
+        }]
+    } else {
+        let streams = <T::Instance as Instance>::streams();
+        T::streams()
+            .into_iter()
+            .enumerate()
+            .map(|(binding, stream)| BindGroupEntry {
+                binding: binding as u32,
+                resource: BindingResource::Buffer(BufferBinding {
+                    buffer: &slice_target.buffers[stream],
+                    offset: streams[stream].stride * slice_range.offset,
+                    size: NonZeroU64::new(streams[stream].stride * slice_range.instance_count),
+                }),
+            })
+            .collect()
+    };
+
+    if let Some(live_count) = live_count {
+        entries.push(BindGroupEntry {
+            binding: entries.len() as u32,
+            resource: live_count.as_entire_binding(),
         });
     }
 
-    commands.insert_resource(InstanceComputeQueue(instance_compute_queue));
+    render_device.create_bind_group(&BindGroupDescriptor {
+        label: Some("instance compute instance bind group"),
+        layout: &pipeline.instance_bind_group_layout,
+        entries: &entries,
+    })
 }
 
 pub trait InstanceCompute: AsBindGroup + ExtractComponent {
     type Instance: Instance;
 
+    /// Invocations per workgroup, which must match the shader's `@workgroup_size`
+    const WORKGROUP_SIZE: UVec3 = UVec3::new(64, 1, 1);
+
+    /// Stage in which this type's jobs are dispatched
+    const STAGE: InstanceComputeStage = InstanceComputeStage::Simulate;
+
+    /// Size of a per-instance state struct, usually `State::SHADER_SIZE`.
+    ///
+    /// When set, each slice gets a pair of zero-initialized storage buffers at group 2
+    /// that persist across frames and swap every dispatch:
+    /// binding 0 holds the previous dispatch's state, binding 1 receives the next.
+    const STATE_SIZE: Option<NonZeroU64> = None;
+
+    /// Whether the shader maintains a live instance count for each slice,
+    /// bound as `atomic<u32>` storage at group 1 after the instance bindings.
+    ///
+    /// Slices marked with [`InstanceSliceLiveCount`](crate::prelude::InstanceSliceLiveCount)
+    /// draw only that many instances.
+    const LIVE_COUNT: bool = false;
+
+    /// Whether the shader reads bevy's `Globals` (time, delta time and frame count),
+    /// bound as a uniform at group 3 binding 0 and defining `INSTANCE_COMPUTE_GLOBALS`.
+    ///
+    /// Group 2 is bound to an empty bind group if [`STATE_SIZE`](Self::STATE_SIZE) is unset.
+    const GLOBALS: bool = false;
+
+    /// Whether the shader reads the current `View`,
+    /// bound as a uniform at group 3 binding 1 and defining `INSTANCE_COMPUTE_VIEW`.
+    ///
+    /// Jobs then run once for each view's copy of the slice, in place of once per slice.
+    /// Per-instance state and live counts are shared between views,
+    /// with state advancing once per run rather than once per view.
+    const VIEW: bool = false;
+
+    /// Invocation grid for a slice of `instance_count` instances.
+    ///
+    /// Each axis is rounded up to a whole number of workgroups,
+    /// so shaders should bounds-check against the instance count.
+    fn dispatch_size(instance_count: u64) -> UVec3 {
+        UVec3::new(instance_count as u32, 1, 1)
+    }
+
     fn shader() -> ShaderRef {
         ShaderRef::Default
     }
 
+    /// Indices into [`Instance::streams`] this compute pass writes under
+    /// [`InstanceBufferMode::StructOfArrays`], bound in order at group 1
+    fn streams() -> Vec<usize> {
+        (0..<Self::Instance as Instance>::streams().len()).collect()
+    }
+
+    /// Rust equivalent of the compute shader, run in its place under [`InstanceComputeBackend::Cpu`].
+    ///
+    /// `instances` holds the slice's contents from the previous run,
+    /// or default instances after the slice is reset.
+    /// Per-instance state, live counts, globals and views are unavailable on the CPU.
+    ///
+    /// Returns `false` if there is no CPU implementation, leaving the slice untouched.
+    #[allow(unused_variables)]
+    fn compute_cpu(
+        &self,
+        instances: &mut [<Self::Instance as Instance>::PreparedInstance],
+    ) -> bool {
+        false
+    }
+
     #[allow(unused_variables)]
     fn specialize(
         pipeline: &InstanceComputePipeline<Self>,
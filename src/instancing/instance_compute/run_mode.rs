@@ -0,0 +1,123 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    marker::PhantomData,
+    time::Duration,
+};
+
+use bevy::{
+    ecs::{reflect::ReflectComponent, system::lifetimeless::Read},
+    prelude::{default, Changed, Commands, Component, Entity, Query, Resource},
+    reflect::Reflect,
+    render::{extract_component::ExtractComponent, Extract},
+};
+
+use super::InstanceCompute;
+
+/// When the [`InstanceCompute`] jobs of an instance slice are dispatched.
+///
+/// Whatever the mode, jobs also run whenever the slice's contents are reset,
+/// so that persistent layouts are regenerated after (re)allocation.
+/// Jobs with per-instance state or a live count usually need [`EveryFrame`](Self::EveryFrame).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub enum InstanceComputeRunMode {
+    #[default]
+    EveryFrame,
+    /// Only when the slice is (re)allocated
+    Once,
+    /// When the slice's [`InstanceCompute`] component changes
+    OnChange,
+    /// At most once per interval
+    Interval(Duration),
+}
+
+impl ExtractComponent for InstanceComputeRunMode {
+    type Query = Read<Self>;
+
+    type Filter = ();
+
+    fn extract_component(item: bevy::ecs::query::QueryItem<Self::Query>) -> Self {
+        *item
+    }
+}
+
+/// Marks a render world slice whose `T` changed since the last extraction
+#[derive(Debug, Component)]
+pub struct InstanceComputeChanged<T: InstanceCompute>(PhantomData<T>);
+
+impl<T: InstanceCompute> Default for InstanceComputeChanged<T> {
+    fn default() -> Self {
+        Self(default())
+    }
+}
+
+pub fn extract_instance_compute_changes<T: InstanceCompute>(
+    query_changed: Extract<Query<Entity, Changed<T>>>,
+    mut commands: Commands,
+) {
+    commands.insert_or_spawn_batch(
+        query_changed
+            .iter()
+            .map(|entity| (entity, InstanceComputeChanged::<T>::default()))
+            .collect::<Vec<_>>(),
+    );
+}
+
+/// When each slice driven by `T` was last dispatched
+#[derive(Resource)]
+pub struct InstanceComputeSchedule<T: InstanceCompute> {
+    pub last_run: BTreeMap<Entity, Duration>,
+    /// Slices whose jobs were held back until their inputs are ready,
+    /// and which run as soon as they are
+    pub deferred: BTreeSet<Entity>,
+    /// Slices whose jobs were dispatched this frame
+    pub dispatched: BTreeSet<Entity>,
+    marker: PhantomData<T>,
+}
+
+impl<T: InstanceCompute> Default for InstanceComputeSchedule<T> {
+    fn default() -> Self {
+        Self {
+            last_run: default(),
+            deferred: default(),
+            dispatched: default(),
+            marker: default(),
+        }
+    }
+}
+
+impl<T: InstanceCompute> InstanceComputeSchedule<T> {
+    /// Whether `entity`'s job should be dispatched at `now`, recording the run if so.
+    ///
+    /// Call [`clear_dispatched`](Self::clear_dispatched) once per frame beforehand.
+    pub fn should_run(
+        &mut self,
+        entity: Entity,
+        run_mode: InstanceComputeRunMode,
+        changed: bool,
+        reset: bool,
+        now: Duration,
+    ) -> bool {
+        let run = reset
+            || match run_mode {
+                InstanceComputeRunMode::EveryFrame => true,
+                InstanceComputeRunMode::Once => !self.last_run.contains_key(&entity),
+                InstanceComputeRunMode::OnChange => changed,
+                InstanceComputeRunMode::Interval(interval) => self
+                    .last_run
+                    .get(&entity)
+                    .is_none_or(|last_run| now.saturating_sub(*last_run) >= interval),
+            };
+
+        if run {
+            self.last_run.insert(entity, now);
+            self.dispatched.insert(entity);
+        }
+
+        run
+    }
+    /// Forget the previous frame's dispatches
+    pub fn clear_dispatched(&mut self) {
+        self.dispatched.clear();
+    }
+}
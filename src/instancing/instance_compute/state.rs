@@ -0,0 +1,145 @@
+use std::{collections::BTreeMap, marker::PhantomData};
+
+use bevy::{
+    prelude::{default, Entity, Resource},
+    render::{
+        render_resource::{
+            BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+            BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, Buffer,
+            BufferBindingType, BufferDescriptor, BufferUsages, ShaderStages,
+        },
+        renderer::RenderDevice,
+    },
+};
+
+use super::InstanceCompute;
+
+/// Bind group layout for an [`InstanceCompute`] with per-instance state,
+/// binding the previous dispatch's state at 0 and the state to write at 1
+pub fn state_bind_group_layout(render_device: &RenderDevice) -> BindGroupLayout {
+    let entry = |binding, read_only| BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
+
+    render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("instance compute state bind group"),
+        entries: &[entry(0, true), entry(1, false)],
+    })
+}
+
+/// Pair of state buffers belonging to a single instance slice
+#[derive(Debug)]
+pub struct InstanceComputeStateBuffers {
+    pub buffers: [Buffer; 2],
+    /// Bind groups reading from the buffer of the same index and writing to the other
+    pub bind_groups: [BindGroup; 2],
+    pub instance_count: u64,
+    /// Index of the buffer written by the most recent dispatch
+    pub front: usize,
+}
+
+impl InstanceComputeStateBuffers {
+    pub fn new(
+        render_device: &RenderDevice,
+        layout: &BindGroupLayout,
+        state_size: u64,
+        instance_count: u64,
+    ) -> Self {
+        let buffer = || {
+            render_device.create_buffer(&BufferDescriptor {
+                label: Some("instance compute state buffer"),
+                size: state_size * instance_count,
+                usage: BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            })
+        };
+
+        let buffers = [buffer(), buffer()];
+
+        let bind_group = |previous: &Buffer, next: &Buffer| {
+            render_device.create_bind_group(&BindGroupDescriptor {
+                label: Some("instance compute state bind group"),
+                layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: previous.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: next.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+
+        let bind_groups = [
+            bind_group(&buffers[0], &buffers[1]),
+            bind_group(&buffers[1], &buffers[0]),
+        ];
+
+        InstanceComputeStateBuffers {
+            buffers,
+            bind_groups,
+            instance_count,
+            front: 0,
+        }
+    }
+
+    /// Bind group with the front buffer as input and the back buffer as output,
+    /// swapping them for the next dispatch
+    pub fn swap(&mut self) -> BindGroup {
+        let bind_group = self.bind_groups[self.front].clone();
+
+        self.front ^= 1;
+
+        bind_group
+    }
+}
+
+/// Ping-pong simulation state for each instance slice driven by `T`,
+/// persisting across frames until the slice is removed or resized
+#[derive(Resource)]
+pub struct InstanceComputeState<T: InstanceCompute> {
+    pub buffers: BTreeMap<Entity, InstanceComputeStateBuffers>,
+    marker: PhantomData<T>,
+}
+
+impl<T: InstanceCompute> Default for InstanceComputeState<T> {
+    fn default() -> Self {
+        Self {
+            buffers: default(),
+            marker: default(),
+        }
+    }
+}
+
+impl<T: InstanceCompute> InstanceComputeState<T> {
+    /// Fetch the state buffers for `entity`, reallocating them if its instance count has changed
+    pub fn get_or_create(
+        &mut self,
+        render_device: &RenderDevice,
+        layout: &BindGroupLayout,
+        entity: Entity,
+        state_size: u64,
+        instance_count: u64,
+    ) -> &mut InstanceComputeStateBuffers {
+        let buffers = self.buffers.entry(entity).or_insert_with(|| {
+            InstanceComputeStateBuffers::new(render_device, layout, state_size, instance_count)
+        });
+
+        if buffers.instance_count != instance_count {
+            *buffers =
+                InstanceComputeStateBuffers::new(render_device, layout, state_size, instance_count);
+        }
+
+        buffers
+    }
+}
@@ -0,0 +1,70 @@
+use bevy::render::{
+    globals::{GlobalsBuffer, GlobalsUniform},
+    render_resource::{
+        BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+        BindGroupLayoutEntry, BindingType, BufferBindingType, ShaderStages, ShaderType,
+    },
+    renderer::RenderDevice,
+    view::{ViewUniform, ViewUniforms},
+};
+
+use super::InstanceCompute;
+
+/// Bind group layout exposing bevy's globals at binding 0
+/// and the current view at binding 1, as requested by
+/// [`InstanceCompute::GLOBALS`] and [`InstanceCompute::VIEW`]
+pub fn view_bind_group_layout<T: InstanceCompute>(render_device: &RenderDevice) -> BindGroupLayout {
+    let entry = |binding, has_dynamic_offset, min_binding_size| BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset,
+            min_binding_size: Some(min_binding_size),
+        },
+        count: None,
+    };
+
+    let entries = T::GLOBALS
+        .then(|| entry(0, false, GlobalsUniform::min_size()))
+        .into_iter()
+        .chain(T::VIEW.then(|| entry(1, true, ViewUniform::min_size())))
+        .collect::<Vec<_>>();
+
+    render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("instance compute view bind group"),
+        entries: &entries,
+    })
+}
+
+/// Bind group for [`view_bind_group_layout`], shared by every view through a dynamic offset.
+///
+/// Returns `None` until this frame's uniforms have been written.
+pub fn view_bind_group<T: InstanceCompute>(
+    render_device: &RenderDevice,
+    layout: &BindGroupLayout,
+    globals_buffer: &GlobalsBuffer,
+    view_uniforms: &ViewUniforms,
+) -> Option<BindGroup> {
+    let mut entries = vec![];
+
+    if T::GLOBALS {
+        entries.push(BindGroupEntry {
+            binding: 0,
+            resource: globals_buffer.buffer.binding()?,
+        });
+    }
+
+    if T::VIEW {
+        entries.push(BindGroupEntry {
+            binding: 1,
+            resource: view_uniforms.uniforms.binding()?,
+        });
+    }
+
+    Some(render_device.create_bind_group(&BindGroupDescriptor {
+        label: Some("instance compute view bind group"),
+        layout,
+        entries: &entries,
+    }))
+}
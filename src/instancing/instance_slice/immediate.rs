@@ -0,0 +1,158 @@
+use bevy::{
+    prelude::{
+        default, Commands, Component, Handle, Mesh, Query, Res, ResMut, Resource, Transform, With,
+    },
+    render::{renderer::RenderQueue, Extract},
+};
+
+use crate::prelude::{
+    write_instance_slice, BulkInstance, Instance, InstanceSlice, MaterialInstanced,
+    ViewInstanceSliceTargets,
+};
+
+/// Instances drawn with a given mesh and material for a single frame
+pub struct ImmediateDraw<M: MaterialInstanced> {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<M>,
+    pub instances: Vec<<M::Instance as Instance>::PreparedInstance>,
+}
+
+/// Immediate-mode instanced drawing, for debug visualization, projectiles
+/// and other effects that would otherwise spawn and despawn entities every frame.
+///
+/// Draws queued during a frame are extracted into transient [`InstanceSlice`]s,
+/// batched alongside every other instance of the same mesh and material,
+/// then discarded at the start of the next frame.
+/// They are drawn in every view, without frustum culling or depth sorting.
+#[derive(Resource)]
+pub struct ImmediateInstances<M: MaterialInstanced> {
+    pub draws: Vec<ImmediateDraw<M>>,
+}
+
+impl<M: MaterialInstanced> Default for ImmediateInstances<M> {
+    fn default() -> Self {
+        Self { draws: default() }
+    }
+}
+
+impl<M: MaterialInstanced> ImmediateInstances<M> {
+    /// Draw prepared instances of `mesh` with `material` this frame
+    pub fn draw(
+        &mut self,
+        mesh: &Handle<Mesh>,
+        material: &Handle<M>,
+        instances: impl IntoIterator<Item = <M::Instance as Instance>::PreparedInstance>,
+    ) {
+        self.draws.push(ImmediateDraw {
+            mesh: mesh.clone_weak(),
+            material: material.clone_weak(),
+            instances: instances.into_iter().collect(),
+        });
+    }
+}
+
+impl<M: MaterialInstanced> ImmediateInstances<M>
+where
+    M::Instance: BulkInstance,
+{
+    /// Draw instances of `mesh` with `material` at `transforms` this frame,
+    /// using the default per-instance data
+    pub fn draw_transforms(
+        &mut self,
+        mesh: &Handle<Mesh>,
+        material: &Handle<M>,
+        transforms: impl IntoIterator<Item = Transform>,
+    ) {
+        self.draw_instances(
+            mesh,
+            material,
+            transforms
+                .into_iter()
+                .map(|transform| (transform, default())),
+        );
+    }
+
+    /// Draw instances of `mesh` with `material` this frame,
+    /// each with a transform and per-instance data
+    pub fn draw_instances(
+        &mut self,
+        mesh: &Handle<Mesh>,
+        material: &Handle<M>,
+        instances: impl IntoIterator<Item = (Transform, <M::Instance as BulkInstance>::Data)>,
+    ) {
+        self.draw(
+            mesh,
+            material,
+            instances.into_iter().map(|(transform, data)| {
+                <M::Instance as BulkInstance>::prepare_bulk_instance(
+                    transform.compute_matrix(),
+                    &data,
+                )
+            }),
+        );
+    }
+}
+
+/// Discard last frame's draws
+pub fn clear_immediate_instances<M: MaterialInstanced>(
+    mut immediate_instances: ResMut<ImmediateInstances<M>>,
+) {
+    immediate_instances.draws.clear();
+}
+
+/// Contents of a transient [`InstanceSlice`] spawned for an [`ImmediateDraw`]
+#[derive(Component)]
+pub struct ExtractedImmediateInstances<I: Instance> {
+    pub instances: Vec<I::PreparedInstance>,
+}
+
+/// Spawn a render world slice for each immediate draw
+pub fn extract_immediate_instances<M: MaterialInstanced>(
+    immediate_instances: Extract<Res<ImmediateInstances<M>>>,
+    mut commands: Commands,
+) {
+    commands.spawn_batch(
+        immediate_instances
+            .draws
+            .iter()
+            .filter(|draw| !draw.instances.is_empty())
+            .map(|draw| {
+                (
+                    draw.material.clone_weak(),
+                    draw.mesh.clone_weak(),
+                    InstanceSlice {
+                        instance_count: draw.instances.len(),
+                    },
+                    ExtractedImmediateInstances::<M::Instance> {
+                        instances: draw.instances.clone(),
+                    },
+                )
+            })
+            .collect::<Vec<_>>(),
+    );
+}
+
+/// Write immediate instances into every view's copy of their freshly allocated slices
+#[allow(clippy::type_complexity)]
+pub fn queue_immediate_instances<M: MaterialInstanced>(
+    render_queue: Res<RenderQueue>,
+    query_immediate: Query<
+        (
+            &ViewInstanceSliceTargets,
+            &ExtractedImmediateInstances<M::Instance>,
+        ),
+        With<Handle<M>>,
+    >,
+) {
+    for (view_targets, immediate) in query_immediate.iter() {
+        for view_target in &view_targets.0 {
+            write_instance_slice::<M::Instance>(
+                &render_queue,
+                &view_target.range,
+                &view_target.target,
+                0,
+                &immediate.instances,
+            );
+        }
+    }
+}
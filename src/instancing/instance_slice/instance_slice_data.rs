@@ -0,0 +1,246 @@
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    marker::PhantomData,
+    ops::{Bound, Range, RangeBounds},
+};
+
+use bevy::{
+    prelude::{
+        default, Changed, Component, DetectChanges, Entity, Handle, Query, Res, ResMut, Resource,
+        With,
+    },
+    render::{
+        render_resource::{encase::StorageBuffer, ShaderType},
+        renderer::RenderQueue,
+        Extract,
+    },
+};
+
+use crate::prelude::{
+    Instance, InstanceSliceRange, InstanceSliceTarget, MaterialInstanced, ViewInstanceSliceTargets,
+};
+
+/// CPU-side contents for the [`InstanceSlice`](super::InstanceSlice) on the same entity,
+/// uploaded into its range whenever they change.
+///
+/// Only the instances touched since the last upload are rewritten,
+/// unless the slice was reset, in which case everything is uploaded again.
+/// Instances beyond the slice's `instance_count` are ignored.
+#[derive(Component)]
+pub struct InstanceSliceData<I: Instance> {
+    instances: Vec<I::PreparedInstance>,
+    /// Instances modified since the last extraction
+    dirty: Option<Range<usize>>,
+}
+
+impl<I: Instance> Default for InstanceSliceData<I> {
+    fn default() -> Self {
+        Self::new(default())
+    }
+}
+
+impl<I: Instance> InstanceSliceData<I> {
+    pub fn new(instances: Vec<I::PreparedInstance>) -> Self {
+        Self {
+            dirty: Some(0..instances.len()),
+            instances,
+        }
+    }
+
+    pub fn instances(&self) -> &[I::PreparedInstance] {
+        &self.instances
+    }
+
+    /// Mutable access to every instance, marking them all for upload
+    pub fn instances_mut(&mut self) -> &mut Vec<I::PreparedInstance> {
+        self.mark_dirty(0..usize::MAX);
+        &mut self.instances
+    }
+
+    /// Overwrite a single instance
+    pub fn set(&mut self, index: usize, instance: I::PreparedInstance) {
+        self.instances[index] = instance;
+        self.mark_dirty(index..index + 1);
+    }
+
+    /// Overwrite consecutive instances starting at `offset`
+    pub fn write(&mut self, offset: usize, instances: &[I::PreparedInstance]) {
+        self.instances[offset..offset + instances.len()].clone_from_slice(instances);
+        self.mark_dirty(offset..offset + instances.len());
+    }
+
+    /// Mutable access to a range of instances, marking only that range for upload
+    pub fn range_mut(&mut self, range: impl RangeBounds<usize>) -> &mut [I::PreparedInstance] {
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => end + 1,
+            Bound::Excluded(end) => *end,
+            Bound::Unbounded => self.instances.len(),
+        };
+
+        self.mark_dirty(start..end);
+        &mut self.instances[start..end]
+    }
+
+    fn mark_dirty(&mut self, range: Range<usize>) {
+        self.dirty = Some(match self.dirty.take() {
+            Some(dirty) => dirty.start.min(range.start)..dirty.end.max(range.end),
+            None => range,
+        });
+    }
+}
+
+/// Forget dirty ranges once they have been extracted
+pub fn clear_instance_slice_data<I: Instance + 'static>(
+    mut query_data: Query<&mut InstanceSliceData<I>, Changed<InstanceSliceData<I>>>,
+) {
+    for mut data in query_data.iter_mut() {
+        data.bypass_change_detection().dirty = None;
+    }
+}
+
+/// Render world copy of an [`InstanceSliceData`]
+pub struct ExtractedInstanceSliceData<I: Instance> {
+    pub instances: Vec<I::PreparedInstance>,
+    /// Instances awaiting upload
+    pub dirty: Option<Range<usize>>,
+}
+
+/// [`InstanceSliceData`] for slices drawn with `M`, retained across frames
+/// so that reset slices can be re-uploaded
+#[derive(Resource)]
+pub struct InstanceSliceDataCache<M: MaterialInstanced> {
+    pub data: BTreeMap<Entity, ExtractedInstanceSliceData<M::Instance>>,
+    marker: PhantomData<M>,
+}
+
+impl<M: MaterialInstanced> Default for InstanceSliceDataCache<M> {
+    fn default() -> Self {
+        Self {
+            data: default(),
+            marker: default(),
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn extract_instance_slice_data<M: MaterialInstanced>(
+    query_data: Extract<Query<(Entity, &InstanceSliceData<M::Instance>), With<Handle<M>>>>,
+    mut cache: ResMut<InstanceSliceDataCache<M>>,
+) {
+    cache.data.retain(|entity, _| query_data.contains(*entity));
+
+    for (entity, data) in query_data.iter() {
+        let extracted = match cache.data.entry(entity) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                entry.insert(ExtractedInstanceSliceData {
+                    instances: data.instances.clone(),
+                    dirty: Some(0..data.instances.len()),
+                });
+                continue;
+            }
+        };
+
+        let dirty = if extracted.instances.len() != data.instances.len() {
+            extracted.instances.clone_from(&data.instances);
+            0..data.instances.len()
+        } else if let Some(dirty) = &data.dirty {
+            let dirty = dirty.start.min(data.instances.len())..dirty.end.min(data.instances.len());
+            extracted.instances[dirty.clone()].clone_from_slice(&data.instances[dirty.clone()]);
+            dirty
+        } else {
+            continue;
+        };
+
+        extracted.dirty = Some(match extracted.dirty.take() {
+            Some(pending) => pending.start.min(dirty.start)..pending.end.max(dirty.end),
+            None => dirty,
+        });
+    }
+}
+
+/// Write dirty instances into every view's copy of their slices,
+/// or everything into copies that were reset
+pub fn queue_instance_slice_data<M: MaterialInstanced>(
+    render_queue: Res<RenderQueue>,
+    mut cache: ResMut<InstanceSliceDataCache<M>>,
+    query_instance_slice: Query<&ViewInstanceSliceTargets, With<Handle<M>>>,
+) {
+    for (entity, data) in cache.data.iter_mut() {
+        let view_targets = match query_instance_slice.get(*entity) {
+            Ok(view_targets) => view_targets,
+            // Keep pending changes until the slice is allocated
+            Err(_) => continue,
+        };
+
+        let dirty = data.dirty.take();
+
+        for view_target in &view_targets.0 {
+            let len = data
+                .instances
+                .len()
+                .min(view_target.range.instance_count as usize);
+            let dirty = if view_target.target.reset {
+                0..len
+            } else {
+                match &dirty {
+                    Some(dirty) => dirty.start.min(len)..dirty.end.min(len),
+                    None => continue,
+                }
+            };
+
+            if dirty.is_empty() {
+                continue;
+            }
+
+            write_instance_slice::<M::Instance>(
+                &render_queue,
+                &view_target.range,
+                &view_target.target,
+                dirty.start,
+                &data.instances[dirty],
+            );
+        }
+    }
+}
+
+/// Write `instances` into a slice, starting `offset` instances into its range
+pub fn write_instance_slice<I: Instance>(
+    render_queue: &RenderQueue,
+    slice_range: &InstanceSliceRange,
+    slice_target: &InstanceSliceTarget,
+    offset: usize,
+    instances: &[I::PreparedInstance],
+) {
+    // Encode with the same layout the instance buffer uses
+    let mut bytes = StorageBuffer::new(Vec::<u8>::new());
+    bytes.write(&instances.to_vec()).unwrap();
+    let bytes = bytes.into_inner();
+
+    let first = slice_range.offset as usize + offset;
+
+    if let [buffer] = slice_target.buffers.as_slice() {
+        let stride = <I::PreparedInstance as ShaderType>::min_size().get() as usize;
+        render_queue.write_buffer(buffer, (first * stride) as u64, &bytes);
+    } else {
+        // Scatter each field into its stream
+        let stride = bytes.len() / instances.len();
+        for (buffer, stream) in slice_target.buffers.iter().zip(I::streams()) {
+            let (offset, size) = (stream.offset as usize, stream.size as usize);
+            let padding = vec![0; stream.stride as usize - size];
+
+            let mut stream_bytes = Vec::with_capacity(instances.len() * stream.stride as usize);
+            for instance in bytes.chunks_exact(stride) {
+                stream_bytes.extend_from_slice(&instance[offset..offset + size]);
+                stream_bytes.extend_from_slice(&padding);
+            }
+
+            render_queue.write_buffer(buffer, first as u64 * stream.stride, &stream_bytes);
+        }
+    }
+}
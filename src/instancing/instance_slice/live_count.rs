@@ -0,0 +1,107 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bevy::{
+    ecs::{reflect::ReflectComponent, system::lifetimeless::Read},
+    prelude::{Component, Entity, ResMut, Resource, World},
+    reflect::Reflect,
+    render::{
+        extract_component::ExtractComponent,
+        render_graph::{Node, NodeRunError, RenderGraphContext},
+        render_resource::{Buffer, BufferDescriptor, BufferUsages},
+        renderer::{RenderContext, RenderDevice},
+    },
+};
+
+pub const INSTANCE_SLICE_LIVE_COUNT_NODE: &str = "instance_slice_live_count";
+
+/// Marks an [`InstanceSlice`](super::InstanceSlice) as drawing a GPU-controlled number of instances.
+///
+/// `instance_count` becomes a capacity, and the number actually drawn is read
+/// from a `u32` written by an [`InstanceCompute`](crate::prelude::InstanceCompute)
+/// with [`LIVE_COUNT`](crate::prelude::InstanceCompute::LIVE_COUNT) set.
+/// Live instances are the first `count` in the slice;
+/// the count persists across frames, and the shader is responsible
+/// for keeping it within capacity.
+#[derive(Debug, Default, Copy, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub struct InstanceSliceLiveCount;
+
+impl ExtractComponent for InstanceSliceLiveCount {
+    type Query = Read<Self>;
+
+    type Filter = ();
+
+    fn extract_component(item: bevy::ecs::query::QueryItem<Self::Query>) -> Self {
+        *item
+    }
+}
+
+/// Copy of a slice's live count into the `instance_count` field of its indirect draw
+#[derive(Debug, Clone)]
+pub struct LiveCountCopy {
+    pub count: Buffer,
+    pub indirect: Buffer,
+    pub offset: u64,
+}
+
+/// Persistent per-slice live count buffers, and the copies queued for this frame
+#[derive(Default, Resource)]
+pub struct InstanceSliceLiveCounts {
+    pub buffers: BTreeMap<Entity, Buffer>,
+    pub copies: Vec<LiveCountCopy>,
+    used: BTreeSet<Entity>,
+}
+
+impl InstanceSliceLiveCounts {
+    /// Fetch the live count buffer for a slice entity, creating a zeroed one if necessary
+    pub fn get_or_create(&mut self, render_device: &RenderDevice, entity: Entity) -> &Buffer {
+        self.used.insert(entity);
+
+        self.buffers.entry(entity).or_insert_with(|| {
+            render_device.create_buffer(&BufferDescriptor {
+                label: Some("instance slice live count buffer"),
+                size: std::mem::size_of::<u32>() as u64,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        })
+    }
+}
+
+/// Drop queued copies and buffers belonging to slices that weren't used this frame
+pub fn cleanup_live_counts(mut live_counts: ResMut<InstanceSliceLiveCounts>) {
+    let InstanceSliceLiveCounts {
+        buffers,
+        copies,
+        used,
+    } = &mut *live_counts;
+
+    buffers.retain(|entity, _| used.contains(entity));
+    used.clear();
+    copies.clear();
+}
+
+/// Writes live counts into indirect draws once instance compute has run
+#[derive(Debug, Default, Copy, Clone)]
+pub struct InstanceSliceLiveCountNode;
+
+impl Node for InstanceSliceLiveCountNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        for copy in &world.resource::<InstanceSliceLiveCounts>().copies {
+            render_context.command_encoder.copy_buffer_to_buffer(
+                &copy.count,
+                0,
+                &copy.indirect,
+                copy.offset,
+                std::mem::size_of::<u32>() as u64,
+            );
+        }
+
+        Ok(())
+    }
+}
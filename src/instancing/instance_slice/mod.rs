@@ -1,8 +1,14 @@
+pub mod immediate;
 pub mod instance_slice_bundle;
+pub mod instance_slice_data;
+pub mod live_count;
+pub mod readback;
+
+use std::collections::BTreeMap;
 
 use bevy::{
     ecs::{reflect::ReflectComponent, system::lifetimeless::Read},
-    prelude::Component,
+    prelude::{Component, Entity},
     reflect::Reflect,
     render::{extract_component::ExtractComponent, render_resource::Buffer},
 };
@@ -31,7 +37,144 @@ pub struct InstanceSliceRange {
     pub instance_count: u64,
 }
 
+impl InstanceSliceRange {
+    /// Index one past the last instance in the range
+    pub fn end(&self) -> u64 {
+        self.offset + self.instance_count
+    }
+}
+
+/// Smallest instance count whose byte size is a multiple of `offset_alignment` for every stride,
+/// i.e. the granularity at which slices can be bound with a storage buffer offset
+pub fn instance_slice_alignment(
+    strides: impl IntoIterator<Item = u64>,
+    offset_alignment: u64,
+) -> u64 {
+    fn gcd(a: u64, b: u64) -> u64 {
+        if b == 0 {
+            a
+        } else {
+            gcd(b, a % b)
+        }
+    }
+
+    strides.into_iter().fold(1, |alignment, stride| {
+        let stride_alignment = offset_alignment / gcd(offset_alignment, stride);
+        alignment / gcd(alignment, stride_alignment) * stride_alignment
+    })
+}
+
+/// Lay out slices one after another in the given order,
+/// starting each at a multiple of `alignment` instances.
+///
+/// Empty slices are left out, as they have nothing to allocate or draw.
+pub fn instance_slice_ranges(
+    instance_slices: impl IntoIterator<Item = (Entity, usize)>,
+    alignment: usize,
+) -> BTreeMap<Entity, InstanceSliceRange> {
+    let mut offset = 0usize;
+    let mut instance_slice_ranges = BTreeMap::new();
+    for (entity, instance_count) in instance_slices {
+        if instance_count == 0 {
+            continue;
+        }
+
+        offset = offset.next_multiple_of(alignment);
+
+        instance_slice_ranges.insert(
+            entity,
+            InstanceSliceRange {
+                offset: offset as u64,
+                instance_count: instance_count as u64,
+            },
+        );
+
+        offset += instance_count;
+    }
+
+    instance_slice_ranges
+}
+
 #[derive(Debug, Clone, Component)]
 pub struct InstanceSliceTarget {
-    pub buffer: Buffer,
+    /// The batch's instance buffer, or one buffer per [`Instance::streams`](crate::prelude::Instance::streams)
+    /// entry under [`InstanceBufferMode::StructOfArrays`](crate::prelude::InstanceBufferMode::StructOfArrays)
+    pub buffers: Vec<Buffer>,
+    /// Whether the slice's contents were reset to default this frame,
+    /// either because its range changed or because the buffer was reallocated.
+    ///
+    /// Otherwise, data written to the slice on previous frames is still present.
+    pub reset: bool,
+}
+
+/// A slice's range and buffers within a single view's instance data
+#[derive(Debug, Clone)]
+pub struct ViewInstanceSliceTarget {
+    pub view: Entity,
+    pub range: InstanceSliceRange,
+    pub target: InstanceSliceTarget,
+}
+
+/// Every view's copy of a slice.
+///
+/// Views batch their instances separately, so each has its own copy of every visible slice;
+/// [`InstanceSliceRange`] and [`InstanceSliceTarget`] describe only the last view's.
+#[derive(Debug, Default, Clone, Component)]
+pub struct ViewInstanceSliceTargets(pub Vec<ViewInstanceSliceTarget>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slices_are_laid_out_in_order_and_aligned() {
+        let ranges = instance_slice_ranges(
+            [
+                (Entity::from_raw(0), 3),
+                (Entity::from_raw(1), 4),
+                (Entity::from_raw(2), 1),
+            ],
+            4,
+        );
+
+        assert_eq!(
+            ranges
+                .iter()
+                .map(|(entity, range)| (entity.index(), range.offset, range.instance_count))
+                .collect::<Vec<_>>(),
+            [(0, 0, 3), (1, 4, 4), (2, 8, 1)]
+        );
+    }
+
+    #[test]
+    fn empty_slices_are_left_out() {
+        let ranges = instance_slice_ranges(
+            [
+                (Entity::from_raw(0), 0),
+                (Entity::from_raw(1), 2),
+                (Entity::from_raw(2), 0),
+                (Entity::from_raw(3), 2),
+            ],
+            1,
+        );
+
+        assert_eq!(
+            ranges
+                .iter()
+                .map(|(entity, range)| (entity.index(), range.offset, range.instance_count))
+                .collect::<Vec<_>>(),
+            [(1, 0, 2), (3, 2, 2)]
+        );
+        assert!(instance_slice_ranges([(Entity::from_raw(0), 0)], 4).is_empty());
+    }
+
+    #[test]
+    fn alignment_covers_every_stride() {
+        // Smallest instance counts spanning a multiple of 256 bytes for every stride
+        assert_eq!(instance_slice_alignment([16], 256), 16);
+        assert_eq!(instance_slice_alignment([48], 256), 16);
+        assert_eq!(instance_slice_alignment([144, 16], 256), 16);
+        assert_eq!(instance_slice_alignment([64], 256), 4);
+        assert_eq!(instance_slice_alignment(std::iter::empty(), 256), 1);
+    }
 }
@@ -0,0 +1,465 @@
+use std::{
+    marker::PhantomData,
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use bevy::{
+    prelude::{
+        debug, default, warn, App, Entity, EventReader, EventWriter, Events, Handle, Plugin, Query,
+        Res, ResMut, Resource, With, World,
+    },
+    render::{
+        render_graph::{Node, NodeRunError, RenderGraphContext},
+        render_resource::{
+            encase::{private::CreateFrom, StorageBuffer},
+            Buffer, BufferDescriptor, BufferUsages, MapMode, ShaderType,
+        },
+        renderer::{RenderContext, RenderDevice},
+        Extract, RenderApp, RenderStage,
+    },
+};
+
+use crate::prelude::{
+    Instance, InstanceSlice, InstanceSliceRange, InstanceSliceTarget, InstanceStream,
+    MaterialInstanced,
+};
+
+pub const INSTANCE_SLICE_READBACK_NODE: &str = "instance_slice_readback";
+
+/// Requests an asynchronous copy of an [`InstanceSlice`]'s GPU contents,
+/// delivered as an [`InstanceSliceReadback`] event once the GPU has finished with it
+#[derive(Debug, Clone)]
+pub struct InstanceSliceReadbackRequest {
+    pub entity: Entity,
+    /// Instances to read, relative to the start of the slice, or the whole slice if `None`
+    pub instances: Option<Range<u64>>,
+}
+
+/// Instances read back from the GPU in response to an [`InstanceSliceReadbackRequest`],
+/// usually arriving a frame or two after the request
+#[derive(Debug, Clone)]
+pub struct InstanceSliceReadback<I: Instance> {
+    pub entity: Entity,
+    /// Index within the slice of the first instance read
+    pub offset: u64,
+    pub instances: Vec<I::PreparedInstance>,
+}
+
+/// Completed readbacks, handed from the render world to the main world
+#[derive(Resource)]
+pub struct InstanceSliceReadbacks<I: Instance>(Arc<Mutex<Vec<InstanceSliceReadback<I>>>>);
+
+impl<I: Instance> Default for InstanceSliceReadbacks<I> {
+    fn default() -> Self {
+        Self(default())
+    }
+}
+
+impl<I: Instance> Clone for InstanceSliceReadbacks<I> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// Copy from an instance buffer into a mappable staging buffer
+#[derive(Debug, Clone)]
+pub struct ReadbackCopy {
+    pub source: Buffer,
+    pub source_offset: u64,
+    pub destination: Buffer,
+    pub size: u64,
+}
+
+/// Readback copies to run this frame, once instance compute has finished
+#[derive(Debug, Default, Resource)]
+pub struct InstanceSliceReadbackCopies(pub Vec<ReadbackCopy>);
+
+/// Clear the copies run this frame
+pub fn cleanup_readback_copies(mut copies: ResMut<InstanceSliceReadbackCopies>) {
+    copies.0.clear();
+}
+
+/// Copies requested slice ranges into staging buffers
+#[derive(Debug, Default, Copy, Clone)]
+pub struct InstanceSliceReadbackNode;
+
+impl Node for InstanceSliceReadbackNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        for copy in &world.resource::<InstanceSliceReadbackCopies>().0 {
+            render_context.command_encoder.copy_buffer_to_buffer(
+                &copy.source,
+                copy.source_offset,
+                &copy.destination,
+                0,
+                copy.size,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// A readback whose staging buffers are being filled or mapped
+struct InstanceSliceReadbackJob {
+    entity: Entity,
+    offset: u64,
+    count: u64,
+    /// One staging buffer for the instance buffer, or one per stream
+    staging: Vec<Buffer>,
+    /// Progress mapping the staging buffers, or `None` if mapping hasn't been requested
+    mapping: Option<Arc<ReadbackMapping>>,
+}
+
+/// Outcome of mapping a job's staging buffers, updated by the mapping callbacks
+#[derive(Default)]
+struct ReadbackMapping {
+    /// Number of staging buffers mapped so far
+    mapped: AtomicUsize,
+    /// Whether any staging buffer failed to map
+    failed: AtomicBool,
+}
+
+/// Render world readback state for instance slices drawn with `M`
+#[derive(Resource)]
+pub struct InstanceSliceReadbackQueue<M: MaterialInstanced> {
+    requests: Vec<InstanceSliceReadbackRequest>,
+    jobs: Vec<InstanceSliceReadbackJob>,
+    marker: PhantomData<M>,
+}
+
+impl<M: MaterialInstanced> Default for InstanceSliceReadbackQueue<M> {
+    fn default() -> Self {
+        Self {
+            requests: default(),
+            jobs: default(),
+            marker: default(),
+        }
+    }
+}
+
+/// Reads back instance slices drawn with `M`
+/// in response to [`InstanceSliceReadbackRequest`] events.
+///
+/// Requires [`IndirectRenderingPlugin`](crate::prelude::IndirectRenderingPlugin).
+pub struct InstanceSliceReadbackPlugin<M: MaterialInstanced>(PhantomData<M>);
+
+impl<M: MaterialInstanced> Default for InstanceSliceReadbackPlugin<M> {
+    fn default() -> Self {
+        Self(default())
+    }
+}
+
+impl<M: MaterialInstanced> Plugin for InstanceSliceReadbackPlugin<M>
+where
+    <M::Instance as Instance>::PreparedInstance: CreateFrom,
+{
+    fn build(&self, app: &mut App) {
+        // Instance types can be shared between materials
+        if !app
+            .world
+            .contains_resource::<InstanceSliceReadbacks<M::Instance>>()
+        {
+            app.add_event::<InstanceSliceReadback<M::Instance>>()
+                .init_resource::<InstanceSliceReadbacks<M::Instance>>()
+                .add_system(send_instance_slice_readbacks::<M::Instance>);
+        }
+
+        if !app
+            .world
+            .contains_resource::<Events<InstanceSliceReadbackRequest>>()
+        {
+            app.add_event::<InstanceSliceReadbackRequest>();
+        }
+
+        let readbacks = app
+            .world
+            .resource::<InstanceSliceReadbacks<M::Instance>>()
+            .clone();
+
+        app.sub_app_mut(RenderApp)
+            .insert_resource(readbacks)
+            .init_resource::<InstanceSliceReadbackQueue<M>>()
+            .add_system_to_stage(
+                RenderStage::Extract,
+                extract_instance_slice_readback_requests::<M>,
+            )
+            .add_system_to_stage(RenderStage::Queue, queue_instance_slice_readbacks::<M>)
+            .add_system_to_stage(RenderStage::Cleanup, map_instance_slice_readbacks::<M>);
+    }
+}
+
+/// Forward completed readbacks to [`InstanceSliceReadback`] events
+pub fn send_instance_slice_readbacks<I: Instance + 'static>(
+    readbacks: Res<InstanceSliceReadbacks<I>>,
+    mut events: EventWriter<InstanceSliceReadback<I>>,
+) {
+    events.send_batch(readbacks.0.lock().unwrap().drain(..));
+}
+
+pub fn extract_instance_slice_readback_requests<M: MaterialInstanced>(
+    mut requests: Extract<EventReader<InstanceSliceReadbackRequest>>,
+    mut queue: ResMut<InstanceSliceReadbackQueue<M>>,
+) {
+    queue.requests.extend(requests.iter().cloned());
+}
+
+/// Schedule copies for requests whose slices have been allocated,
+/// keeping requests for slices still awaiting allocation
+#[allow(clippy::type_complexity)]
+pub fn queue_instance_slice_readbacks<M: MaterialInstanced>(
+    render_device: Res<RenderDevice>,
+    mut queue: ResMut<InstanceSliceReadbackQueue<M>>,
+    mut copies: ResMut<InstanceSliceReadbackCopies>,
+    query_instance_slice: Query<
+        Option<(&InstanceSliceRange, &InstanceSliceTarget)>,
+        (With<InstanceSlice>, With<Handle<M>>),
+    >,
+) {
+    let InstanceSliceReadbackQueue { requests, jobs, .. } = &mut *queue;
+
+    requests.retain(|request| {
+        let (slice_range, slice_target) = match query_instance_slice.get(request.entity) {
+            Ok(Some(slice)) => slice,
+            // Not allocated yet
+            Ok(None) => return true,
+            // Not a slice drawn with this material
+            Err(_) => return false,
+        };
+
+        let instances = request
+            .instances
+            .clone()
+            .unwrap_or(0..slice_range.instance_count);
+        let end = instances.end.min(slice_range.instance_count);
+        let start = instances.start.min(end);
+        if start == end {
+            return false;
+        }
+
+        let first = slice_range.offset + start;
+        let count = end - start;
+
+        let strides = if slice_target.buffers.len() > 1 {
+            <M::Instance as Instance>::streams()
+                .iter()
+                .map(|stream| stream.stride)
+                .collect::<Vec<_>>()
+        } else {
+            vec![<<M::Instance as Instance>::PreparedInstance as ShaderType>::min_size().get()]
+        };
+
+        let staging = slice_target
+            .buffers
+            .iter()
+            .zip(strides)
+            .map(|(buffer, stride)| {
+                let destination = render_device.create_buffer(&BufferDescriptor {
+                    label: Some("instance slice readback buffer"),
+                    size: stride * count,
+                    usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                });
+
+                copies.0.push(ReadbackCopy {
+                    source: buffer.clone(),
+                    source_offset: stride * first,
+                    destination: destination.clone(),
+                    size: stride * count,
+                });
+
+                destination
+            })
+            .collect();
+
+        debug!(
+            "Reading back {count} instances from slice {:?}",
+            request.entity
+        );
+
+        jobs.push(InstanceSliceReadbackJob {
+            entity: request.entity,
+            offset: start,
+            count,
+            staging,
+            mapping: None,
+        });
+
+        false
+    });
+}
+
+/// Map staging buffers copied this frame, and decode any that have finished mapping.
+///
+/// Jobs whose staging buffers fail to map are dropped.
+pub fn map_instance_slice_readbacks<M: MaterialInstanced>(
+    render_device: Res<RenderDevice>,
+    readbacks: Res<InstanceSliceReadbacks<M::Instance>>,
+    mut queue: ResMut<InstanceSliceReadbackQueue<M>>,
+) where
+    <M::Instance as Instance>::PreparedInstance: CreateFrom,
+{
+    // Mapping callbacks fire as later frames are submitted
+    queue.jobs.retain(|job| {
+        let mapping = match &job.mapping {
+            Some(mapping) => mapping,
+            None => return true,
+        };
+
+        if mapping.failed.load(Ordering::Acquire) {
+            warn!(
+                "Failed to map readback of instance slice {:?}, dropping it",
+                job.entity
+            );
+            return false;
+        }
+
+        if mapping.mapped.load(Ordering::Acquire) < job.staging.len() {
+            return true;
+        }
+
+        let instances = read_staging::<M::Instance>(&job.staging, job.count as usize);
+        for buffer in &job.staging {
+            buffer.unmap();
+        }
+
+        readbacks.0.lock().unwrap().push(InstanceSliceReadback {
+            entity: job.entity,
+            offset: job.offset,
+            instances,
+        });
+
+        false
+    });
+
+    // Copies into the remaining staging buffers have now been submitted
+    for job in queue.jobs.iter_mut().filter(|job| job.mapping.is_none()) {
+        let mapping = Arc::new(ReadbackMapping::default());
+
+        for buffer in &job.staging {
+            let mapping = mapping.clone();
+            render_device.map_buffer(&buffer.slice(..), MapMode::Read, move |result| {
+                if result.is_ok() {
+                    mapping.mapped.fetch_add(1, Ordering::Release);
+                } else {
+                    mapping.failed.store(true, Ordering::Release);
+                }
+            });
+        }
+
+        job.mapping = Some(mapping);
+    }
+}
+
+/// Decode mapped staging buffers, reassembling instances from their streams if necessary
+fn read_staging<I: Instance>(staging: &[Buffer], count: usize) -> Vec<I::PreparedInstance>
+where
+    I::PreparedInstance: CreateFrom,
+{
+    let bytes = if let [buffer] = staging {
+        buffer.slice(..).get_mapped_range().to_vec()
+    } else {
+        let views = staging
+            .iter()
+            .map(|buffer| buffer.slice(..).get_mapped_range())
+            .collect::<Vec<_>>();
+
+        interleave_streams(
+            &I::streams(),
+            &views.iter().map(|view| &**view).collect::<Vec<_>>(),
+            <I::PreparedInstance as ShaderType>::min_size().get() as usize,
+            count,
+        )
+    };
+
+    StorageBuffer::new(bytes).create().unwrap()
+}
+
+/// Scatter each stream's fields back into `count` instances of `stride` bytes,
+/// undoing the struct-of-arrays split described by `streams`
+fn interleave_streams(
+    streams: &[InstanceStream],
+    data: &[&[u8]],
+    stride: usize,
+    count: usize,
+) -> Vec<u8> {
+    let mut bytes = vec![0; count * stride];
+    for (stream, data) in streams.iter().zip(data) {
+        let (offset, size) = (stream.offset as usize, stream.size as usize);
+
+        for (instance, field) in bytes
+            .chunks_exact_mut(stride)
+            .zip(data.chunks_exact(stream.stride as usize))
+        {
+            instance[offset..offset + size].copy_from_slice(&field[..size]);
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streams_are_interleaved_into_instances() {
+        // A u32 followed by a vec3<f32>, whose stream is padded to 16 bytes per element
+        let streams = [
+            InstanceStream {
+                offset: 0,
+                size: 4,
+                stride: 4,
+            },
+            InstanceStream {
+                offset: 16,
+                size: 12,
+                stride: 16,
+            },
+        ];
+
+        let ids = [1u8, 0, 0, 0, 2, 0, 0, 0];
+        let positions = (0..32).map(|i| 100 + i as u8).collect::<Vec<_>>();
+
+        let bytes = interleave_streams(&streams, &[&ids, &positions], 32, 2);
+
+        let mut expected = vec![0u8; 64];
+        expected[0..4].copy_from_slice(&ids[0..4]);
+        expected[16..28].copy_from_slice(&positions[0..12]);
+        expected[32..36].copy_from_slice(&ids[4..8]);
+        expected[48..60].copy_from_slice(&positions[16..28]);
+
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn short_streams_leave_the_rest_zeroed() {
+        let streams = [InstanceStream {
+            offset: 4,
+            size: 4,
+            stride: 4,
+        }];
+
+        let bytes = interleave_streams(&streams, &[&[7, 7, 7, 7]], 8, 2);
+
+        assert_eq!(bytes, [0, 0, 0, 0, 7, 7, 7, 7, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn no_instances_produce_no_bytes() {
+        let streams = [InstanceStream {
+            offset: 0,
+            size: 4,
+            stride: 4,
+        }];
+
+        assert!(interleave_streams(&streams, &[&[]], 4, 0).is_empty());
+    }
+}
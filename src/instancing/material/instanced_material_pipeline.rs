@@ -8,14 +8,19 @@ use bevy::{
     render::{
         mesh::MeshVertexBufferLayout,
         render_resource::{
-            BindGroupLayout, RenderPipelineDescriptor, Shader, SpecializedMeshPipeline,
-            SpecializedMeshPipelineError,
+            BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType,
+            BufferBindingType, RenderPipelineDescriptor, Shader, ShaderSize, ShaderStages,
+            SpecializedMeshPipeline, SpecializedMeshPipelineError, VertexBufferLayout,
+            VertexStepMode,
         },
         renderer::RenderDevice,
     },
 };
 
-use crate::prelude::{InstancedMeshPipeline, MaterialInstanced};
+use crate::prelude::{
+    Instance, InstanceBufferMode, InstancedMeshPipeline, MaterialInstanced,
+    INSTANCE_VERTEX_LOCATION,
+};
 
 pub struct InstancedMaterialPipelineKey<M: MaterialInstanced> {
     pub mesh_key: MeshPipelineKey,
@@ -59,6 +64,9 @@ where
 pub struct InstancedMaterialPipeline<M: MaterialInstanced> {
     pub instanced_mesh_pipeline: InstancedMeshPipeline,
     pub material_layout: BindGroupLayout,
+    /// One read-only storage binding per [`Instance::streams`] entry,
+    /// present under [`InstanceBufferMode::StructOfArrays`]
+    pub instance_stream_layout: Option<BindGroupLayout>,
     pub vertex_shader: Option<Handle<Shader>>,
     pub fragment_shader: Option<Handle<Shader>>,
     marker: PhantomData<M>,
@@ -91,6 +99,18 @@ where
         let descriptor_layout = descriptor.layout.as_mut().unwrap();
         descriptor_layout.insert(1, self.material_layout.clone());
 
+        if self.instanced_mesh_pipeline.instance_buffer_mode == InstanceBufferMode::Vertex {
+            descriptor.vertex.buffers.push(VertexBufferLayout {
+                array_stride: <M::Instance as Instance>::PreparedInstance::SHADER_SIZE.get(),
+                step_mode: VertexStepMode::Instance,
+                attributes: <M::Instance as Instance>::vertex_attributes(INSTANCE_VERTEX_LOCATION),
+            });
+        }
+
+        if let Some(instance_stream_layout) = &self.instance_stream_layout {
+            descriptor_layout.push(instance_stream_layout.clone());
+        }
+
         M::specialize(self, &mut descriptor, key.material_key, layout)?;
         Ok(descriptor)
     }
@@ -101,10 +121,34 @@ impl<M: MaterialInstanced> FromWorld for InstancedMaterialPipeline<M> {
         let asset_server = world.resource::<AssetServer>();
         let render_device = world.resource::<RenderDevice>();
         let material_layout = M::bind_group_layout(render_device);
+        let instanced_mesh_pipeline = world.resource::<InstancedMeshPipeline>().clone();
+
+        let instance_stream_layout = (instanced_mesh_pipeline.instance_buffer_mode
+            == InstanceBufferMode::StructOfArrays)
+            .then(|| {
+                let entries = (0..<M::Instance as Instance>::streams().len() as u32)
+                    .map(|binding| BindGroupLayoutEntry {
+                        binding,
+                        visibility: ShaderStages::VERTEX,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    })
+                    .collect::<Vec<_>>();
+
+                render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    label: Some("instance stream bind group"),
+                    entries: &entries,
+                })
+            });
 
         InstancedMaterialPipeline {
-            instanced_mesh_pipeline: world.resource::<InstancedMeshPipeline>().clone(),
+            instanced_mesh_pipeline,
             material_layout,
+            instance_stream_layout,
             vertex_shader: match M::vertex_shader(asset_server) {
                 bevy::render::render_resource::ShaderRef::Default => None,
                 bevy::render::render_resource::ShaderRef::Handle(handle) => Some(handle),
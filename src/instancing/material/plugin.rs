@@ -3,7 +3,7 @@ use crate::{
         indirect::IndirectDraw, mesh_instance::MeshInstance,
         render::instance::InstanceUniformLength,
     },
-    prelude::{DrawIndexedIndirect, DrawIndirect},
+    prelude::{DrawIndexedIndirect, DrawIndirect, InstanceBufferMode},
 };
 use bevy::{
     app::{App, Plugin},
@@ -18,8 +18,9 @@ use bevy::{
     },
     pbr::{AlphaMode, SetMeshViewBindGroup},
     prelude::{
-        debug, default, AssetEvent, Assets, Commands, Deref, DerefMut, Entity, EventReader, Handle,
-        Image, IntoSystemDescriptor, Local, Mesh, Res, ResMut, Resource,
+        debug, default, AssetEvent, Assets, Commands, CoreStage, Deref, DerefMut, Entity,
+        EventReader, Handle, Image, IntoSystemDescriptor, Local, Mesh, Res, ResMut, Resource,
+        Shader,
     },
     render::{
         extract_component::ExtractComponentPlugin,
@@ -30,8 +31,8 @@ use bevy::{
             TrackedRenderPass,
         },
         render_resource::{
-            AsBindGroupError, BufferBindingType, IndexFormat, OwnedBindingResource, ShaderType,
-            SpecializedMeshPipelines, StorageBuffer, UniformBuffer,
+            encase, AsBindGroupError, BufferBindingType, BufferUsages, IndexFormat,
+            OwnedBindingResource, ShaderSize, ShaderType, SpecializedMeshPipelines, UniformBuffer,
         },
         renderer::RenderQueue,
         texture::FallbackImage,
@@ -48,14 +49,18 @@ use bevy::{
 };
 
 use crate::prelude::{
-    extract_mesh_instances, Instance, InstanceSliceRange, InstancedMaterialPipeline,
-    MaterialInstanced, SetInstancedMaterialBindGroup,
+    clear_immediate_instances, clear_instance_slice_data, extract_immediate_instances,
+    extract_instance_slice_data, extract_mesh_instances, queue_immediate_instances,
+    queue_instance_slice_data, ImmediateInstances, Instance, InstanceSliceDataCache,
+    InstanceSliceRange, InstancedMaterialPipeline, MaterialInstanced, PersistentBuffer,
+    SetInstancedMaterialBindGroup,
 };
 
 use std::{
     collections::{BTreeMap, BTreeSet},
     fmt::Debug,
     hash::Hash,
+    ops::Range,
 };
 
 use std::marker::PhantomData;
@@ -63,7 +68,7 @@ use std::marker::PhantomData;
 use super::systems::{
     extract_instanced_meshes, extract_instanced_view_meta,
     prepare_batched_instances::{self, ViewIndirectData},
-    prepare_instance_batches::{self, ViewInstanceData},
+    prepare_instance_batches::{self, ViewInstanceData, ViewInstanceSliceRanges},
     prepare_instance_slice_targets,
     prepare_material_batches::{self, MaterialBatches},
     prepare_mesh_batches, prepare_view_instance_slices, prepare_view_instances,
@@ -89,10 +94,20 @@ where
         app.add_asset::<M>()
             .add_plugin(ExtractComponentPlugin::<Handle<M>>::default());
 
+        if let Some((handle, shader)) = <M::Instance as Instance>::shader() {
+            app.world
+                .resource_mut::<Assets<Shader>>()
+                .set_untracked(handle, shader);
+        }
+
         if !app.is_plugin_added::<ExtractComponentPlugin<Handle<Mesh>>>() {
             app.add_plugin(ExtractComponentPlugin::<Handle<Mesh>>::default());
         }
 
+        app.init_resource::<ImmediateInstances<M>>()
+            .add_system_to_stage(CoreStage::First, clear_instance_slice_data::<M::Instance>)
+            .add_system_to_stage(CoreStage::First, clear_immediate_instances::<M>);
+
         if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
             render_app
                 .add_render_command::<Transparent3d, DrawInstanced<M>>()
@@ -104,11 +119,15 @@ where
                 .init_resource::<RenderMaterials<M>>()
                 .init_resource::<MaterialBatches<M>>()
                 .init_resource::<ViewInstanceData<M>>()
+                .init_resource::<ViewInstanceSliceRanges<M>>()
                 .init_resource::<ViewIndirectData<M>>()
+                .init_resource::<InstanceSliceDataCache<M>>()
                 .init_resource::<SpecializedMeshPipelines<InstancedMaterialPipeline<M>>>()
                 .add_system_to_stage(RenderStage::Extract, extract_materials::<M>)
                 .add_system_to_stage(RenderStage::Extract, extract_mesh_instances::<M>)
                 .add_system_to_stage(RenderStage::Extract, extract_instanced_meshes::system)
+                .add_system_to_stage(RenderStage::Extract, extract_instance_slice_data::<M>)
+                .add_system_to_stage(RenderStage::Extract, extract_immediate_instances::<M>)
                 .add_system_to_stage(
                     RenderStage::Extract,
                     extract_instanced_view_meta::system::<M>,
@@ -153,7 +172,9 @@ where
                     prepare_instance_slice_targets::system::<M>
                         .after(prepare_batched_instances::system::<M>),
                 )
-                .add_system_to_stage(RenderStage::Queue, queue_instanced_materials::system::<M>);
+                .add_system_to_stage(RenderStage::Queue, queue_instanced_materials::system::<M>)
+                .add_system_to_stage(RenderStage::Queue, queue_instance_slice_data::<M>)
+                .add_system_to_stage(RenderStage::Queue, queue_immediate_instances::<M>);
         }
     }
 }
@@ -340,6 +361,9 @@ where
 pub struct InstanceBatchKey<M: MaterialInstanced> {
     pub mesh_key: InstancedMeshKey,
     pub material_key: InstancedMaterialBatchKey<M>,
+    /// [`InstanceOrigin`](crate::prelude::InstanceOrigin) of the batch's instances,
+    /// so that instances relative to different origins are never drawn together
+    pub origin: [u32; 3],
 }
 
 impl<M: MaterialInstanced> Component for InstanceBatchKey<M> {
@@ -354,13 +378,16 @@ where
         Self {
             mesh_key: self.mesh_key.clone(),
             material_key: self.material_key.clone(),
+            origin: self.origin,
         }
     }
 }
 
 impl<M: MaterialInstanced> PartialEq for InstanceBatchKey<M> {
     fn eq(&self, other: &Self) -> bool {
-        self.mesh_key == other.mesh_key && self.material_key == other.material_key
+        self.mesh_key == other.mesh_key
+            && self.material_key == other.material_key
+            && self.origin == other.origin
     }
 }
 
@@ -375,7 +402,11 @@ where
             Some(core::cmp::Ordering::Equal) => {}
             ord => return ord,
         }
-        self.material_key.partial_cmp(&other.material_key)
+        match self.material_key.partial_cmp(&other.material_key) {
+            Some(core::cmp::Ordering::Equal) => {}
+            ord => return ord,
+        }
+        self.origin.partial_cmp(&other.origin)
     }
 }
 
@@ -388,7 +419,11 @@ where
             core::cmp::Ordering::Equal => {}
             ord => return ord,
         }
-        self.material_key.cmp(&other.material_key)
+        match self.material_key.cmp(&other.material_key) {
+            core::cmp::Ordering::Equal => {}
+            ord => return ord,
+        }
+        self.origin.cmp(&other.origin)
     }
 }
 
@@ -400,6 +435,7 @@ where
         f.debug_struct("InstanceKey")
             .field("mesh_key", &self.mesh_key)
             .field("material_key", &self.material_key)
+            .field("origin", &self.origin)
             .finish()
     }
 }
@@ -413,15 +449,26 @@ pub enum GpuInstances<M: MaterialInstanced> {
         >,
     },
     Storage {
-        buffer: StorageBuffer<Vec<<M::Instance as Instance>::PreparedInstance>>,
+        buffer: PersistentBuffer,
+    },
+    Vertex {
+        buffer: PersistentBuffer,
+    },
+    StructOfArrays {
+        streams: Vec<PersistentBuffer>,
     },
 }
 
 impl<M: MaterialInstanced> GpuInstances<M> {
-    pub fn new(buffer_binding_type: BufferBindingType) -> Self {
-        match buffer_binding_type {
-            BufferBindingType::Storage { .. } => Self::storage(),
-            BufferBindingType::Uniform => Self::uniform(),
+    pub fn new(
+        instance_buffer_mode: InstanceBufferMode,
+        buffer_binding_type: BufferBindingType,
+    ) -> Self {
+        match (instance_buffer_mode, buffer_binding_type) {
+            (InstanceBufferMode::Vertex, buffer_binding_type) => Self::vertex(buffer_binding_type),
+            (InstanceBufferMode::StructOfArrays, _) => Self::struct_of_arrays(),
+            (InstanceBufferMode::Binding, BufferBindingType::Storage { .. }) => Self::storage(),
+            (InstanceBufferMode::Binding, BufferBindingType::Uniform) => Self::uniform(),
         }
     }
 
@@ -431,14 +478,42 @@ impl<M: MaterialInstanced> GpuInstances<M> {
 
     pub fn storage() -> Self {
         Self::Storage {
-            buffer: StorageBuffer::default(),
+            buffer: PersistentBuffer::new(BufferUsages::STORAGE | BufferUsages::COPY_SRC),
+        }
+    }
+
+    /// Vertex buffer, additionally usable as a compute target where storage buffers are supported
+    pub fn vertex(buffer_binding_type: BufferBindingType) -> Self {
+        let usage = match buffer_binding_type {
+            BufferBindingType::Storage { .. } => BufferUsages::VERTEX | BufferUsages::STORAGE,
+            BufferBindingType::Uniform => BufferUsages::VERTEX,
+        } | BufferUsages::COPY_SRC;
+
+        Self::Vertex {
+            buffer: PersistentBuffer::new(usage),
+        }
+    }
+
+    /// One storage buffer per [`Instance::streams`] entry
+    pub fn struct_of_arrays() -> Self {
+        Self::StructOfArrays {
+            streams: <M::Instance as Instance>::streams()
+                .iter()
+                .map(|_| PersistentBuffer::new(BufferUsages::STORAGE | BufferUsages::COPY_SRC))
+                .collect(),
         }
     }
 
     pub fn clear(&mut self) {
         match self {
             Self::Uniform { buffers } => buffers.clear(),
-            Self::Storage { buffer } => buffer.get_mut().clear(),
+            Self::Storage { buffer } => buffer.clear(),
+            Self::Vertex { buffer } => buffer.clear(),
+            Self::StructOfArrays { streams } => {
+                for buffer in streams {
+                    buffer.clear()
+                }
+            }
         }
     }
 
@@ -467,27 +542,90 @@ impl<M: MaterialInstanced> GpuInstances<M> {
                     buffers.push(buf);
                 }
             }
-            Self::Storage { buffer } => {
-                buffer.get_mut().extend(instances);
+            Self::Storage { buffer } | Self::Vertex { buffer } => {
+                // Encode with the same layout a storage buffer would use
+                let mut bytes = encase::StorageBuffer::new(Vec::<u8>::new());
+                bytes.write(&instances).unwrap();
+
+                buffer.extend_from_slice(&bytes.into_inner());
+            }
+            Self::StructOfArrays { streams } => {
+                // Encode as an array of structs, then scatter each field into its stream
+                let mut bytes = encase::StorageBuffer::new(Vec::<u8>::new());
+                bytes.write(&instances).unwrap();
+                let bytes = bytes.into_inner();
+
+                let stride =
+                    <Vec<<M::Instance as Instance>::PreparedInstance> as ShaderType>::min_size()
+                        .get() as usize;
+
+                for (buffer, stream) in streams.iter_mut().zip(<M::Instance as Instance>::streams())
+                {
+                    let offset = stream.offset as usize;
+                    let size = stream.size as usize;
+                    let padding = vec![0; stream.stride as usize - size];
+
+                    for instance in bytes.chunks_exact(stride) {
+                        buffer.extend_from_slice(&instance[offset..offset + size]);
+                        buffer.extend_from_slice(&padding);
+                    }
+                }
             }
         }
     }
 
-    pub fn write_buffer(&mut self, render_device: &RenderDevice, render_queue: &RenderQueue) {
+    /// Upload to the GPU, leaving the instances in `preserve` untouched.
+    ///
+    /// `preserve` must be sorted and non-overlapping.
+    /// Returns `true` if the GPU buffers were reallocated, resetting any preserved instances.
+    pub fn write_buffer(
+        &mut self,
+        render_device: &RenderDevice,
+        render_queue: &RenderQueue,
+        preserve: &[Range<usize>],
+    ) -> bool {
+        let bytes = |stride: u64| {
+            preserve
+                .iter()
+                .map(|range| range.start * stride as usize..range.end * stride as usize)
+                .collect::<Vec<_>>()
+        };
+
         match self {
             Self::Uniform { buffers } => {
                 for buffer in buffers {
                     buffer.write_buffer(render_device, render_queue)
                 }
+                true
             }
-            Self::Storage { buffer } => buffer.write_buffer(render_device, render_queue),
+            Self::Storage { buffer } | Self::Vertex { buffer } => buffer.write_buffer(
+                render_device,
+                render_queue,
+                &bytes(<M::Instance as Instance>::PreparedInstance::SHADER_SIZE.get()),
+            ),
+            Self::StructOfArrays { streams } => streams
+                .iter_mut()
+                .zip(<M::Instance as Instance>::streams())
+                .fold(false, |reallocated, (buffer, stream)| {
+                    buffer.write_buffer(render_device, render_queue, &bytes(stream.stride))
+                        || reallocated
+                }),
         }
     }
 
     pub fn len(&self) -> usize {
         match self {
             Self::Uniform { buffers } => buffers.len() * 128,
-            Self::Storage { buffer } => buffer.get().len(),
+            Self::Storage { buffer } | Self::Vertex { buffer } => {
+                buffer.len()
+                    / <M::Instance as Instance>::PreparedInstance::SHADER_SIZE.get() as usize
+            }
+            Self::StructOfArrays { streams } => streams
+                .iter()
+                .zip(<M::Instance as Instance>::streams())
+                .next()
+                .map(|(buffer, stream)| buffer.len() / stream.stride as usize)
+                .unwrap_or_default(),
         }
     }
 
@@ -499,6 +637,8 @@ impl<M: MaterialInstanced> GpuInstances<M> {
 pub struct InstanceBatch<M: MaterialInstanced> {
     pub instances: BTreeSet<Entity>,
     pub instance_slice_ranges: BTreeMap<Entity, InstanceSliceRange>,
+    /// Slices whose contents were reset by this frame's upload
+    pub reset_instance_slices: BTreeSet<Entity>,
     pub _phantom: PhantomData<M>,
 }
 
@@ -507,6 +647,7 @@ impl<M: MaterialInstanced> Debug for InstanceBatch<M> {
         f.debug_struct("InstanceBatch")
             .field("instances", &self.instances)
             .field("instance_slice_ranges", &self.instance_slice_ranges)
+            .field("reset_instance_slices", &self.reset_instance_slices)
             .finish()
     }
 }
@@ -554,13 +695,20 @@ pub struct GpuIndirectBufferData {
     pub buffer: Buffer,
 }
 
+/// How a set of batched instances exposes its instance data to the vertex shader
+#[derive(Debug, Clone)]
+pub enum GpuInstanceBinding {
+    BindGroup(BindGroup),
+    VertexBuffer(Buffer),
+}
+
 /// The data necessary to render one set of mutually compatible instances
 #[derive(Component)]
 pub struct BatchedInstances {
     pub vertex_buffer: Buffer,
     pub index_buffer: Option<(Buffer, IndexFormat)>,
     pub indirect_buffer: GpuIndirectBufferData,
-    pub bind_group: BindGroup,
+    pub instance_binding: GpuInstanceBinding,
 }
 
 pub type DrawInstanced<M> = (
@@ -600,10 +748,17 @@ impl<M: MaterialInstanced> EntityRenderCommand for DrawBatchedInstances<M> {
 
         for (i, batch) in batched_instances.into_iter().enumerate() {
             debug!("Batch {}", i);
-            pass.set_bind_group(2, &batch.bind_group, &[]);
-
             pass.set_vertex_buffer(0, batch.vertex_buffer.slice(..));
 
+            match &batch.instance_binding {
+                GpuInstanceBinding::BindGroup(bind_group) => {
+                    pass.set_bind_group(2, bind_group, &[]);
+                }
+                GpuInstanceBinding::VertexBuffer(instance_buffer) => {
+                    pass.set_vertex_buffer(1, instance_buffer.slice(..));
+                }
+            }
+
             if let Some((index_buffer, index_format)) = &batch.index_buffer {
                 pass.set_index_buffer(index_buffer.slice(..), 0, *index_format);
             }
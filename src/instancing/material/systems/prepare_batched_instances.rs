@@ -18,13 +18,16 @@ use bevy::render::render_resource::{
 
 use crate::instancing::{
     indirect::{DrawCall, DrawOffsets, IndirectDraw},
-    instance_slice::InstanceSlice,
+    instance_slice::{
+        live_count::{InstanceSliceLiveCount, InstanceSliceLiveCounts, LiveCountCopy},
+        InstanceSlice, InstanceSliceRange,
+    },
     material::{
         instanced_material_pipeline::InstancedMaterialPipeline,
         material_instanced::MaterialInstanced,
         plugin::{
-            BatchedInstances, GpuIndexBufferData, GpuIndirectBufferData, GpuInstances,
-            InstanceBatchKey, InstanceMeta, RenderMeshes,
+            BatchedInstances, GpuIndexBufferData, GpuIndirectBufferData, GpuInstanceBinding,
+            GpuInstances, InstanceBatchKey, InstanceMeta, RenderMeshes,
         },
     },
     render::instance::{Instance, InstanceUniformLength},
@@ -61,6 +64,8 @@ pub fn system<M: MaterialInstanced>(
         &<M::Instance as Instance>::ExtractedInstance,
     )>,
     query_instance_slice: Query<(Entity, &Handle<M>, &Handle<Mesh>, &InstanceSlice)>,
+    query_live_count: Query<Entity, With<InstanceSliceLiveCount>>,
+    mut live_counts: ResMut<InstanceSliceLiveCounts>,
     mut query_instance_meta: Query<
         (Entity, &mut InstanceMeta<M>),
         (With<ExtractedView>, With<VisibleEntities>),
@@ -102,7 +107,9 @@ pub fn system<M: MaterialInstanced>(
                 .map(|index_data| index_data.buffer().unwrap().clone())
                 .map(|index_buffer| (index_buffer, key.mesh_key.index_format.unwrap()));
 
-            // Calculate mesh instance counts for indirect data
+            let instance_batch = instance_meta.instance_batches.get(&key).unwrap();
+
+            // Calculate CPU instance counts for indirect data
             let mesh_instance_counts = info_span!("Mesh instance counts").in_scope(|| {
                 let mut mesh_instance_counts = mesh_batch
                     .meshes
@@ -110,8 +117,6 @@ pub fn system<M: MaterialInstanced>(
                     .map(|mesh| (mesh, 0))
                     .collect::<BTreeMap<_, _>>();
 
-                let instance_batch = instance_meta.instance_batches.get(&key).unwrap();
-
                 for mesh in query_instance.iter().filter_map(|(entity, _, mesh, _)| {
                     if instance_batch.instances.contains(&entity) {
                         Some(mesh)
@@ -122,42 +127,27 @@ pub fn system<M: MaterialInstanced>(
                     *mesh_instance_counts.get_mut(mesh).unwrap() += 1;
                 }
 
-                for (mesh, instance_slice) in
-                    query_instance_slice
-                        .iter()
-                        .filter_map(|(entity, _, mesh, instance_slice)| {
-                            if instance_batch.instance_slice_ranges.contains_key(&entity) {
-                                Some((mesh, instance_slice))
-                            } else {
-                                None
-                            }
-                        })
-                {
-                    *mesh_instance_counts.get_mut(mesh).unwrap() += instance_slice.instance_count;
-                }
-
                 debug!("Mesh instance counts: {mesh_instance_counts:?}");
                 mesh_instance_counts
             });
 
-            // Calculate instance offsets for indirect data
-            let (mesh_instance_offsets, _) = info_span!("Mesh instance offsets").in_scope(|| {
-                mesh_instance_counts.iter().fold(
-                    (BTreeMap::<&Handle<Mesh>, usize>::new(), 0),
-                    |(mut offsets, mut offset), (mesh, count)| {
-                        offsets.insert(mesh, offset);
-                        offset += count;
-                        (offsets, offset)
-                    },
-                )
-            });
-
             // Calculate vertex offsets for indirect data
-            let (mesh_vertex_offsets, _) = info_span!("Mesh vertex offsets").in_scope(|| {
-                mesh_instance_counts.iter().fold(
-                    (BTreeMap::<&Handle<Mesh>, usize>::new(), 0),
-                    |(mut offsets, mut offset), (mesh, _)| {
-                        offsets.insert(mesh, offset);
+            let mesh_indirects = info_span!("Mesh indirects").in_scope(|| {
+                let mut offset = 0;
+                mesh_batch
+                    .meshes
+                    .iter()
+                    .zip(mesh_batch.indirect_data.iter())
+                    .map(|(mesh, mut indirect)| {
+                        indirect.set_offsets(match indirect {
+                            IndirectDraw::Indexed(_) => DrawOffsets::Indexed {
+                                base_index: offset as u32,
+                                vertex_offset: 0,
+                            },
+                            IndirectDraw::NonIndexed(_) => DrawOffsets::NonIndexed {
+                                base_vertex: offset as u32,
+                            },
+                        });
 
                         let gpu_mesh = render_meshes.get(mesh).unwrap();
 
@@ -168,9 +158,9 @@ pub fn system<M: MaterialInstanced>(
                             }
                         };
 
-                        (offsets, offset)
-                    },
-                )
+                        (mesh, indirect)
+                    })
+                    .collect::<BTreeMap<_, _>>()
             });
 
             // Create bind group
@@ -179,38 +169,59 @@ pub fn system<M: MaterialInstanced>(
             // Build indirect buffer
             let indirect_buffers = view_indirect_data.entry(key.clone()).or_default();
 
+            // Slices occupy the front of the instance buffer, in range order
+            let mut instance_slice_ranges = instance_batch
+                .instance_slice_ranges
+                .iter()
+                .filter(|(_, range)| range.instance_count > 0)
+                .flat_map(|(entity, range)| {
+                    query_instance_slice
+                        .get(*entity)
+                        .ok()
+                        .map(|(_, _, mesh, _)| (*entity, mesh, range))
+                })
+                .collect::<Vec<_>>();
+            instance_slice_ranges.sort_unstable_by_key(|(_, _, range)| range.offset);
+
+            // Slice draws whose instance counts are controlled from the GPU
+            let live_count_draws = instance_slice_ranges
+                .iter()
+                .enumerate()
+                .filter(|(_, (entity, _, _))| query_live_count.contains(*entity))
+                .map(|(i, (entity, _, _))| (i, *entity))
+                .collect::<Vec<_>>();
+
             let mut indirect_buffer_data = info_span!("Create indirect buffer").in_scope(|| {
-                let indirect_data = mesh_batch
-                    .indirect_data
+                let draw = |mesh: &Handle<Mesh>, instance_count: usize, base_instance: usize| {
+                    let mut indirect = *mesh_indirects.get(mesh).unwrap();
+                    indirect.set_instance_count(instance_count as u32);
+                    indirect.set_base_instance(base_instance as u32);
+                    indirect
+                };
+
+                let slice_instance_count = instance_batch
+                    .instance_slice_ranges
+                    .values()
+                    .map(InstanceSliceRange::end)
+                    .max()
+                    .unwrap_or_default() as usize;
+
+                let slice_draws = instance_slice_ranges.iter().map(|(_, mesh, range)| {
+                    draw(mesh, range.instance_count as usize, range.offset as usize)
+                });
+
+                // CPU instances follow, grouped by mesh
+                let instance_draws = mesh_instance_counts
                     .iter()
-                    .zip(
-                        mesh_instance_counts.values().zip(
-                            mesh_vertex_offsets
-                                .values()
-                                .zip(mesh_instance_offsets.values()),
-                        ),
-                    )
-                    .flat_map(
-                        |(mut indirect, (instance_count, (draw_offset, instance_offset)))| {
-                            if *instance_count > 0 {
-                                indirect.set_instance_count(*instance_count as u32);
-                                indirect.set_offsets(match indirect {
-                                    IndirectDraw::Indexed(_) => DrawOffsets::Indexed {
-                                        base_index: *draw_offset as u32,
-                                        vertex_offset: 0,
-                                    },
-                                    IndirectDraw::NonIndexed(_) => DrawOffsets::NonIndexed {
-                                        base_vertex: *draw_offset as u32,
-                                    },
-                                });
-                                indirect.set_base_instance(*instance_offset as u32);
-                                Some(indirect)
-                            } else {
-                                None
-                            }
-                        },
-                    )
-                    .collect::<Vec<_>>();
+                    .scan(slice_instance_count, |offset, (mesh, count)| {
+                        let base_instance = *offset;
+                        *offset += count;
+                        Some((mesh, *count, base_instance))
+                    })
+                    .filter(|(_, count, _)| *count > 0)
+                    .map(|(mesh, count, base_instance)| draw(mesh, count, base_instance));
+
+                let indirect_data = slice_draws.chain(instance_draws).collect::<Vec<_>>();
 
                 debug!("Indirect data: {indirect_data:#?}");
 
@@ -311,6 +322,25 @@ pub fn system<M: MaterialInstanced>(
                     .collect::<Vec<_>>()
             });
 
+            // Copy live counts over their slices' instance counts once compute has run
+            if !matches!(instance_buffer_data, GpuInstances::Uniform { .. }) {
+                let indirect = &indirect_buffer_data[0];
+                for (i, entity) in live_count_draws {
+                    let offset = indirect.indirects[..i]
+                        .iter()
+                        .map(IndirectDraw::size)
+                        .sum::<usize>()
+                        + IndirectDraw::INSTANCE_COUNT_OFFSET;
+
+                    let count = live_counts.get_or_create(&render_device, entity).clone();
+                    live_counts.copies.push(LiveCountCopy {
+                        count,
+                        indirect: indirect.buffer.clone(),
+                        offset: offset as u64,
+                    });
+                }
+            }
+
             let mut batches = vec![];
 
             match instance_buffer_data {
@@ -341,7 +371,7 @@ pub fn system<M: MaterialInstanced>(
                             vertex_buffer: vertex_buffer.clone(),
                             index_buffer: index_buffer.clone(),
                             indirect_buffer: indirect,
-                            bind_group,
+                            instance_binding: GpuInstanceBinding::BindGroup(bind_group),
                         });
                     }
                 }
@@ -353,7 +383,7 @@ pub fn system<M: MaterialInstanced>(
                             .bind_group_layout,
                         entries: &[BindGroupEntry {
                             binding: 0,
-                            resource: buffer.binding().unwrap(),
+                            resource: buffer.buffer().unwrap().as_entire_binding(),
                         }],
                     });
 
@@ -361,7 +391,43 @@ pub fn system<M: MaterialInstanced>(
                         vertex_buffer,
                         index_buffer,
                         indirect_buffer: indirect_buffer_data.remove(0),
-                        bind_group,
+                        instance_binding: GpuInstanceBinding::BindGroup(bind_group),
+                    });
+                }
+                GpuInstances::Vertex { buffer } => {
+                    batches.push(BatchedInstances {
+                        vertex_buffer,
+                        index_buffer,
+                        indirect_buffer: indirect_buffer_data.remove(0),
+                        instance_binding: GpuInstanceBinding::VertexBuffer(
+                            buffer.buffer().unwrap().clone(),
+                        ),
+                    });
+                }
+                GpuInstances::StructOfArrays { streams } => {
+                    let entries = streams
+                        .iter()
+                        .enumerate()
+                        .map(|(i, buffer)| BindGroupEntry {
+                            binding: i as u32,
+                            resource: buffer.buffer().unwrap().as_entire_binding(),
+                        })
+                        .collect::<Vec<_>>();
+
+                    let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
+                        label: Some("instance stream bind group"),
+                        layout: instanced_material_pipeline
+                            .instance_stream_layout
+                            .as_ref()
+                            .unwrap(),
+                        entries: &entries,
+                    });
+
+                    batches.push(BatchedInstances {
+                        vertex_buffer,
+                        index_buffer,
+                        indirect_buffer: indirect_buffer_data.remove(0),
+                        instance_binding: GpuInstanceBinding::BindGroup(bind_group),
                     });
                 }
             }
@@ -6,6 +6,7 @@ use bevy::{
         With,
     },
     render::{
+        render_resource::ShaderSize,
         renderer::{RenderDevice, RenderQueue},
         view::{ExtractedView, VisibleEntities},
     },
@@ -13,7 +14,10 @@ use bevy::{
 };
 
 use crate::instancing::{
-    instance_slice::{InstanceSlice, InstanceSliceRange},
+    instance_slice::{
+        immediate::ExtractedImmediateInstances, instance_slice_alignment, instance_slice_ranges,
+        InstanceSlice, InstanceSliceRange,
+    },
     material::{
         material_instanced::MaterialInstanced,
         plugin::{
@@ -22,7 +26,11 @@ use crate::instancing::{
         },
         systems::prepare_mesh_batches::MeshBatch,
     },
-    render::instance::Instance,
+    mesh_instance::quantized_mesh_instance::InstanceOrigin,
+    render::{
+        instance::Instance,
+        instanced_mesh_pipeline::{InstanceBufferMode, InstancedMeshPipeline},
+    },
 };
 
 use super::prepare_mesh_batches::MeshBatches;
@@ -40,21 +48,48 @@ impl<M: MaterialInstanced> Default for ViewInstanceData<M> {
     }
 }
 
+/// Slice ranges each batch's instance buffer was last written with,
+/// used to decide which slices can keep their GPU contents
+#[derive(Deref, DerefMut, Resource)]
+pub struct ViewInstanceSliceRanges<M: MaterialInstanced> {
+    pub slice_ranges:
+        BTreeMap<Entity, BTreeMap<InstanceBatchKey<M>, BTreeMap<Entity, InstanceSliceRange>>>,
+}
+
+impl<M: MaterialInstanced> Default for ViewInstanceSliceRanges<M> {
+    fn default() -> Self {
+        Self {
+            slice_ranges: default(),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn system<M: MaterialInstanced>(
+    instanced_mesh_pipeline: Res<InstancedMeshPipeline>,
     render_device: Res<RenderDevice>,
     render_queue: Res<RenderQueue>,
     render_meshes: Res<RenderMeshes>,
     render_materials: Res<RenderMaterials<M>>,
     mesh_batches: Res<MeshBatches>,
     mut view_instance_data: ResMut<ViewInstanceData<M>>,
+    mut view_slice_ranges: ResMut<ViewInstanceSliceRanges<M>>,
     mut query_views: Query<(Entity, &ExtractedView, &mut InstanceMeta<M>), With<VisibleEntities>>,
     query_instance: Query<(
         Entity,
         &Handle<M>,
         &Handle<Mesh>,
         &<M::Instance as Instance>::ExtractedInstance,
+        Option<&InstanceOrigin>,
+    )>,
+    query_instance_slice: Query<(
+        Entity,
+        &Handle<M>,
+        &Handle<Mesh>,
+        &InstanceSlice,
+        Option<&InstanceOrigin>,
     )>,
-    query_instance_slice: Query<(Entity, &Handle<M>, &Handle<Mesh>, &InstanceSlice)>,
+    query_immediate: Query<(), With<ExtractedImmediateInstances<M::Instance>>>,
 ) {
     debug!("{}", std::any::type_name::<M>());
 
@@ -81,7 +116,7 @@ pub fn system<M: MaterialInstanced>(
                 )>,
             >::new();
 
-            for (entity, material_handle, mesh_handle, instance) in instance_meta
+            for (entity, material_handle, mesh_handle, instance, origin) in instance_meta
                 .instances
                 .iter()
                 .flat_map(|entity| query_instance.get(*entity))
@@ -127,6 +162,7 @@ pub fn system<M: MaterialInstanced>(
                 let key = InstanceBatchKey {
                     mesh_key,
                     material_key,
+                    origin: origin.map(InstanceOrigin::batch_key).unwrap_or_default(),
                 };
 
                 keyed_instances.entry(key).or_default().push((
@@ -150,7 +186,7 @@ pub fn system<M: MaterialInstanced>(
             let mut keyed_instance_slices =
                 BTreeMap::<InstanceBatchKey<M>, Vec<(Entity, &Handle<M>, &InstanceSlice)>>::new();
 
-            for (entity, material_handle, mesh_handle, instance_slice) in instance_meta
+            for (entity, material_handle, mesh_handle, instance_slice, origin) in instance_meta
                 .instance_slices
                 .iter()
                 .flat_map(|entity| query_instance_slice.get(*entity))
@@ -179,6 +215,7 @@ pub fn system<M: MaterialInstanced>(
                 let key = InstanceBatchKey {
                     mesh_key,
                     material_key,
+                    origin: origin.map(InstanceOrigin::batch_key).unwrap_or_default(),
                 };
 
                 keyed_instance_slices.entry(key).or_default().push((
@@ -188,6 +225,13 @@ pub fn system<M: MaterialInstanced>(
                 ));
             }
 
+            for instance_slices in keyed_instance_slices.values_mut() {
+                // Transient immediate slices go last so they can't displace persistent ones
+                instance_slices.sort_unstable_by_key(|(entity, _, _)| {
+                    (query_immediate.contains(*entity), *entity)
+                });
+            }
+
             keyed_instance_slices
         });
 
@@ -201,82 +245,65 @@ pub fn system<M: MaterialInstanced>(
         }
 
         // Create instance buffer data
-        let gpu_instances =
-            || GpuInstances::new(render_device.get_supported_read_only_binding_type(1));
+        let gpu_instances = || {
+            GpuInstances::new(
+                instanced_mesh_pipeline.instance_buffer_mode,
+                instanced_mesh_pipeline.instance_buffer_binding_type,
+            )
+        };
 
         let mut instance_buffer_data =
             BTreeMap::<InstanceBatchKey<M>, Vec<<M::Instance as Instance>::PreparedInstance>>::new(
             );
 
-        let span = bevy::prelude::info_span!("Populate instances");
-        span.in_scope(|| {
-            debug!("Populating instances");
-            // Populate instances
-            for (key, instances) in keyed_instances.iter() {
-                debug!("{key:#?}");
-                // Collect instance data
-                let data = instances
+        // Slice offsets must be bindable as storage buffer offsets
+        let instance_slice_alignment = instance_slice_alignment(
+            match instanced_mesh_pipeline.instance_buffer_mode {
+                InstanceBufferMode::StructOfArrays => <M::Instance as Instance>::streams()
                     .iter()
-                    .map(|((mesh_handle, _), (_, _, instance))| {
-                        let MeshBatch { meshes, .. } = mesh_batches.get(&key.mesh_key).unwrap();
-
-                        <M::Instance as Instance>::prepare_instance(
-                            instance,
-                            meshes.iter().position(|mesh| mesh == *mesh_handle).unwrap() as u32,
-                        )
-                    })
-                    .collect::<Vec<_>>();
-
-                instance_buffer_data
-                    .entry(key.clone())
-                    .or_default()
-                    .extend(data);
-            }
-        });
+                    .map(|stream| stream.stride)
+                    .collect(),
+                _ => vec![<M::Instance as Instance>::PreparedInstance::SHADER_SIZE.get()],
+            },
+            render_device.limits().min_storage_buffer_offset_alignment as u64,
+        ) as usize;
 
         let span = bevy::prelude::info_span!("Create instance slice ranges");
         let mut keyed_instance_slice_ranges = span.in_scope(|| {
             debug!("Creating instance slice ranges");
             // Create instance slice ranges
+            //
+            // Slices are laid out first, in entity order,
+            // so their offsets don't move when CPU instance counts change
             keyed_instance_slices
                 .iter()
                 .map(|(key, instance_slices)| {
-                    let instance_buffer_data_len =
-                        instance_buffer_data.entry(key.clone()).or_default().len();
-
-                    // Collect CPU instance slice data
-                    let mut offset = instance_buffer_data_len;
-                    let mut instance_slice_ranges = BTreeMap::<Entity, InstanceSliceRange>::new();
-                    for (entity, _, instance_slice) in instance_slices {
-                        debug!("Generating InstanceSliceRange for {entity:?}");
-                        // Generate instance slice range
-                        instance_slice_ranges.insert(
-                            *entity,
-                            InstanceSliceRange {
-                                offset: offset as u64,
-                                instance_count: instance_slice.instance_count as u64,
-                            },
-                        );
-
-                        offset += instance_slice.instance_count;
-                    }
+                    let instance_slice_ranges = instance_slice_ranges(
+                        instance_slices.iter().map(|(entity, _, instance_slice)| {
+                            (*entity, instance_slice.instance_count)
+                        }),
+                        instance_slice_alignment,
+                    );
 
                     debug!("Instance slice ranges: {instance_slice_ranges:?}");
 
                     (key.clone(), instance_slice_ranges)
                 })
+                // Batches of empty slices have nothing to allocate or draw
+                .filter(|(_, instance_slice_ranges)| !instance_slice_ranges.is_empty())
                 .collect::<BTreeMap<_, _>>()
         });
 
         let span = bevy::prelude::info_span!("Populate instance slices");
         span.in_scope(|| {
-            // Populate instance slices
-            for (key, instance_slices) in keyed_instance_slices.iter() {
+            // Populate instance slices, including alignment padding
+            for (key, instance_slice_ranges) in keyed_instance_slice_ranges.iter() {
                 // Collect instance data
-                let instance_count: usize = instance_slices
-                    .iter()
-                    .map(|(_, _, instance_slice)| instance_slice.instance_count)
-                    .sum();
+                let instance_count = instance_slice_ranges
+                    .values()
+                    .map(InstanceSliceRange::end)
+                    .max()
+                    .unwrap_or_default() as usize;
 
                 instance_buffer_data
                     .entry(key.clone())
@@ -285,17 +312,81 @@ pub fn system<M: MaterialInstanced>(
             }
         });
 
+        let span = bevy::prelude::info_span!("Populate instances");
+        span.in_scope(|| {
+            debug!("Populating instances");
+            // Populate instances
+            for (key, instances) in keyed_instances.iter() {
+                debug!("{key:#?}");
+                // Collect instance data
+                let data = instances
+                    .iter()
+                    .map(|((mesh_handle, _), (_, _, instance))| {
+                        let MeshBatch { meshes, .. } = mesh_batches.get(&key.mesh_key).unwrap();
+
+                        <M::Instance as Instance>::prepare_instance(
+                            instance,
+                            meshes.iter().position(|mesh| mesh == *mesh_handle).unwrap() as u32,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                instance_buffer_data
+                    .entry(key.clone())
+                    .or_default()
+                    .extend(data);
+            }
+        });
+
         let view_instance_data = view_instance_data.entry(view_entity).or_default();
+        let view_slice_ranges = view_slice_ranges.entry(view_entity).or_default();
+        let mut keyed_reset_instance_slices =
+            BTreeMap::<InstanceBatchKey<M>, BTreeSet<Entity>>::new();
         for (key, instance_buffer_data) in instance_buffer_data {
             debug!(
                 "Instance batch {key:#?} count: {}",
                 instance_buffer_data.len()
             );
 
-            let entry = view_instance_data.entry(key).or_insert_with(gpu_instances);
+            let instance_slice_ranges = keyed_instance_slice_ranges
+                .get(&key)
+                .cloned()
+                .unwrap_or_default();
+
+            // Slices that occupy the same range as last frame keep their GPU contents
+            let previous_slice_ranges = view_slice_ranges.remove(&key).unwrap_or_default();
+            let (preserved, reset): (Vec<_>, Vec<_>) =
+                instance_slice_ranges.iter().partition(|(entity, range)| {
+                    previous_slice_ranges.get(entity).is_some_and(|previous| {
+                        previous.offset == range.offset
+                            && previous.instance_count == range.instance_count
+                    })
+                });
+
+            let preserve = preserved
+                .iter()
+                .map(|(_, range)| {
+                    range.offset as usize..(range.offset + range.instance_count) as usize
+                })
+                .collect::<Vec<_>>();
+
+            let entry = view_instance_data
+                .entry(key.clone())
+                .or_insert_with(gpu_instances);
 
             entry.set(instance_buffer_data);
-            entry.write_buffer(&render_device, &render_queue);
+            let reallocated = entry.write_buffer(&render_device, &render_queue, &preserve);
+
+            keyed_reset_instance_slices.insert(
+                key.clone(),
+                if reallocated {
+                    instance_slice_ranges.keys().copied().collect()
+                } else {
+                    reset.into_iter().map(|(entity, _)| *entity).collect()
+                },
+            );
+
+            view_slice_ranges.insert(key, instance_slice_ranges);
         }
 
         let span = bevy::prelude::info_span!("Write instance batches");
@@ -317,11 +408,15 @@ pub fn system<M: MaterialInstanced>(
                     let instance_slice_ranges =
                         keyed_instance_slice_ranges.remove(&key).unwrap_or_default();
 
+                    let reset_instance_slices =
+                        keyed_reset_instance_slices.remove(&key).unwrap_or_default();
+
                     (
                         key.clone(),
                         InstanceBatch::<M> {
                             instances,
                             instance_slice_ranges,
+                            reset_instance_slices,
                             _phantom: default(),
                         },
                     )
@@ -332,6 +427,7 @@ pub fn system<M: MaterialInstanced>(
 
 pub fn prune_instance_data<M: MaterialInstanced>(
     mut view_instance_data: ResMut<ViewInstanceData<M>>,
+    mut view_slice_ranges: ResMut<ViewInstanceSliceRanges<M>>,
     query_instance_meta: Query<
         (Entity, &mut InstanceMeta<M>),
         (With<ExtractedView>, With<VisibleEntities>),
@@ -345,6 +441,7 @@ pub fn prune_instance_data<M: MaterialInstanced>(
         {
             info!("View {entity:?} has no instance meta, pruning instance data");
             view_instance_data.remove(&entity);
+            view_slice_ranges.remove(&entity);
         }
     }
 }
@@ -1,10 +1,12 @@
+use std::collections::BTreeMap;
+
 use bevy::{
     prelude::{debug, Commands, Entity, Query, Res, With},
     render::view::{ExtractedView, VisibleEntities},
 };
 
 use crate::instancing::{
-    instance_slice::InstanceSliceTarget,
+    instance_slice::{InstanceSliceTarget, ViewInstanceSliceTarget, ViewInstanceSliceTargets},
     material::{
         material_instanced::MaterialInstanced,
         plugin::{GpuInstances, InstanceMeta},
@@ -18,6 +20,8 @@ pub fn system<M: MaterialInstanced>(
     query_views: Query<(Entity, &InstanceMeta<M>), (With<ExtractedView>, With<VisibleEntities>)>,
     mut commands: Commands,
 ) {
+    let mut view_targets = BTreeMap::<Entity, ViewInstanceSliceTargets>::new();
+
     for (view_entity, instance_meta) in query_views.iter() {
         debug!("\tView {view_entity:?}");
         let view_instance_data =
@@ -29,25 +33,43 @@ pub fn system<M: MaterialInstanced>(
 
         for key in instance_meta.instance_batches.keys() {
             let instance_buffer_data = view_instance_data.get(key).unwrap();
+            let instance_batch = instance_meta.instance_batches.get(key).unwrap();
 
-            for (entity, slice_range) in instance_meta
-                .instance_batches
-                .get(&key)
-                .unwrap()
-                .instance_slice_ranges
-                .iter()
-            {
-                commands.entity(*entity).insert((
-                    *slice_range,
-                    InstanceSliceTarget {
-                        buffer: if let GpuInstances::Storage { buffer } = &instance_buffer_data {
-                            buffer.buffer().unwrap().clone()
-                        } else {
+            for (entity, slice_range) in instance_batch.instance_slice_ranges.iter() {
+                let slice_target = InstanceSliceTarget {
+                    buffers: match &instance_buffer_data {
+                        GpuInstances::Storage { buffer } | GpuInstances::Vertex { buffer } => {
+                            vec![buffer.buffer().unwrap().clone()]
+                        }
+                        GpuInstances::StructOfArrays { streams } => streams
+                            .iter()
+                            .map(|buffer| buffer.buffer().unwrap().clone())
+                            .collect(),
+                        GpuInstances::Uniform { .. } => {
                             panic!("InstanceSlice cannot be used with non-storage buffers")
-                        },
+                        }
                     },
-                ));
+                    reset: instance_batch.reset_instance_slices.contains(entity),
+                };
+
+                view_targets
+                    .entry(*entity)
+                    .or_default()
+                    .0
+                    .push(ViewInstanceSliceTarget {
+                        view: view_entity,
+                        range: *slice_range,
+                        target: slice_target.clone(),
+                    });
+
+                commands
+                    .entity(*entity)
+                    .insert((*slice_range, slice_target));
             }
         }
     }
+
+    for (entity, view_targets) in view_targets {
+        commands.entity(entity).insert(view_targets);
+    }
 }
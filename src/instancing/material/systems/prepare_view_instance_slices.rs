@@ -4,13 +4,21 @@ use bevy::{
 };
 
 use crate::instancing::{
-    instance_slice::InstanceSlice,
+    instance_slice::{immediate::ExtractedImmediateInstances, InstanceSlice},
     material::{material_instanced::MaterialInstanced, plugin::InstanceMeta},
 };
 
+#[allow(clippy::type_complexity)]
 pub fn system<M: MaterialInstanced>(
     mut query_views: Query<(Entity, &VisibleEntities, &mut InstanceMeta<M>), With<ExtractedView>>,
     query_instance_slice: Query<Entity, (With<Handle<M>>, With<InstanceSlice>)>,
+    query_immediate: Query<
+        Entity,
+        (
+            With<Handle<M>>,
+            With<ExtractedImmediateInstances<M::Instance>>,
+        ),
+    >,
 ) {
     debug!("{}", std::any::type_name::<M>());
 
@@ -24,6 +32,8 @@ pub fn system<M: MaterialInstanced>(
             .iter()
             .copied()
             .filter(|entity| query_instance_slice.get(*entity).is_ok())
+            // Immediate draws only exist in the render world, so are visible to every view
+            .chain(query_immediate.iter())
             .collect::<Vec<_>>();
 
         debug!("Instance slices: {instance_slices:#?}");
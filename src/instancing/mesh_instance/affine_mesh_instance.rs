@@ -0,0 +1,95 @@
+use bevy::{
+    ecs::{query::ROQueryItem, system::lifetimeless::Read},
+    math::{Affine3A, Mat4, Vec4},
+    prelude::{Component, ComputedVisibility, GlobalTransform, Handle, Mesh},
+    render::render_resource::{ShaderType, VertexAttribute},
+};
+
+use crate::prelude::{
+    Instance, InstanceStream, InstanceStreams, InstanceVertexAttributes, InstanceWgsl,
+};
+
+/// Mesh instance uploaded as a 3x4 affine matrix.
+///
+/// At 64 bytes against [`MeshInstance`](crate::prelude::MeshInstance)'s 144,
+/// with the normal matrix derived in the shader via `affine_instance_normal_matrix`.
+#[derive(Debug, Default, Clone, PartialEq, Component)]
+pub struct AffineMeshInstance {
+    pub mesh: Handle<Mesh>,
+    pub transform: Affine3A,
+}
+
+/// GPU-friendly data for a single affine mesh instance
+#[derive(Debug, Default, Copy, Clone, PartialEq, ShaderType)]
+pub struct GpuAffineMeshInstance {
+    /// Rows of the affine matrix
+    pub transform: [Vec4; 3],
+    pub mesh: u32,
+}
+
+impl Instance for AffineMeshInstance {
+    type ExtractedInstance = Self;
+    type PreparedInstance = GpuAffineMeshInstance;
+
+    type Query = (
+        Read<Handle<Mesh>>,
+        Read<GlobalTransform>,
+        Read<ComputedVisibility>,
+    );
+
+    fn extract_instance(
+        (mesh, transform, visibility): ROQueryItem<Self::Query>,
+    ) -> Self::ExtractedInstance {
+        let transform = if visibility.is_visible() {
+            transform.affine()
+        } else {
+            Affine3A::ZERO
+        };
+
+        AffineMeshInstance {
+            mesh: mesh.clone_weak(),
+            transform,
+        }
+    }
+
+    fn prepare_instance(instance: &Self::ExtractedInstance, mesh: u32) -> Self::PreparedInstance {
+        let rows = Mat4::from(instance.transform).transpose();
+
+        GpuAffineMeshInstance {
+            transform: [rows.x_axis, rows.y_axis, rows.z_axis],
+            mesh,
+        }
+    }
+
+    fn transform(instance: &Self::ExtractedInstance) -> Mat4 {
+        Mat4::from(instance.transform)
+    }
+
+    fn vertex_attributes(shader_location: u32) -> Vec<VertexAttribute> {
+        InstanceVertexAttributes::new(shader_location)
+            .field::<Vec4>()
+            .field::<Vec4>()
+            .field::<Vec4>()
+            .field::<u32>()
+            .build()
+    }
+
+    fn streams() -> Vec<InstanceStream> {
+        InstanceStreams::new()
+            .payload::<[Vec4; 3]>()
+            .field::<u32>()
+            .build()
+    }
+}
+
+impl InstanceWgsl for AffineMeshInstance {
+    const IMPORT_PATH: &'static str = "indirect_instancing::affine_instance_struct";
+    const STRUCT_NAME: &'static str = "AffineInstanceData";
+    const ARRAY_NAME: &'static str = "AffineInstances";
+    const SOA_ACCESSOR: &'static str = "load_affine_instance";
+    const VERTEX_ACCESSOR: &'static str = "affine_instance_from_vertex";
+
+    fn vertex_structs() -> Vec<&'static str> {
+        vec!["AffineInstanceVertex"]
+    }
+}
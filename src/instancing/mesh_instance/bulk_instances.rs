@@ -0,0 +1,388 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use bevy::{
+    math::{Mat3A, Mat4, Vec3A},
+    prelude::{
+        default, App, AssetEvent, Assets, Bundle, ChangeTrackers, Commands, Component,
+        ComputedVisibility, CoreStage, Entity, EventReader, GlobalTransform, Handle,
+        IntoSystemDescriptor, Mesh, Plugin, Query, Res, Transform, Visibility,
+    },
+    render::{primitives::Aabb, view::VisibilitySystems},
+    transform::TransformSystem,
+    utils::HashSet,
+};
+
+use crate::prelude::{
+    DataMeshInstance, GpuDataMeshInstance, GpuMeshInstance, Instance, InstancePayload,
+    InstancePayloadSource, InstanceSlice, InstanceSliceData, MaterialInstanced, MeshInstance,
+};
+
+/// Instance types that can be built from a transform and a per-instance payload
+/// without an entity of their own
+pub trait BulkInstance: Instance + 'static + Send + Sync {
+    type Data: 'static + Debug + Default + Clone + Send + Sync;
+
+    fn prepare_bulk_instance(transform: Mat4, data: &Self::Data) -> Self::PreparedInstance;
+}
+
+impl BulkInstance for MeshInstance {
+    type Data = ();
+
+    fn prepare_bulk_instance(transform: Mat4, _: &Self::Data) -> Self::PreparedInstance {
+        GpuMeshInstance {
+            mesh: 0,
+            transform,
+            inverse_transpose_model: transform.inverse().transpose(),
+        }
+    }
+}
+
+impl<T, C> BulkInstance for DataMeshInstance<T, C>
+where
+    T: InstancePayload,
+    C: InstancePayloadSource<T>,
+{
+    type Data = T;
+
+    fn prepare_bulk_instance(transform: Mat4, data: &Self::Data) -> Self::PreparedInstance {
+        GpuDataMeshInstance {
+            base: MeshInstance::prepare_bulk_instance(transform, &()),
+            data: *data,
+        }
+    }
+}
+
+/// Many instances of the entity's mesh, drawn from a single entity.
+///
+/// Transforms are relative to the entity, and `data[i]` supplies the payload of instance `i`,
+/// falling back to the default payload where `data` is shorter than `transforms`.
+///
+/// Kept in sync with an [`InstanceSlice`] and [`InstanceSliceData`] on the same entity,
+/// along with an [`Aabb`] enclosing every instance so the entity can be frustum culled.
+/// Any change re-uploads all instances; use [`InstanceSliceData`] directly
+/// for sparse updates.
+#[derive(Component)]
+pub struct BulkInstances<I: BulkInstance> {
+    pub transforms: Vec<Transform>,
+    pub data: Vec<I::Data>,
+}
+
+impl<I: BulkInstance> Default for BulkInstances<I> {
+    fn default() -> Self {
+        Self {
+            transforms: default(),
+            data: default(),
+        }
+    }
+}
+
+impl<I: BulkInstance> BulkInstances<I> {
+    pub fn new(transforms: Vec<Transform>, data: Vec<I::Data>) -> Self {
+        Self { transforms, data }
+    }
+
+    pub fn from_transforms(transforms: Vec<Transform>) -> Self {
+        Self::new(transforms, default())
+    }
+
+    pub fn len(&self) -> usize {
+        self.transforms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    pub fn push(&mut self, transform: Transform, data: I::Data) {
+        self.data.resize(self.transforms.len(), default());
+        self.transforms.push(transform);
+        self.data.push(data);
+    }
+}
+
+/// Components to draw many instances from a single entity
+#[derive(Bundle)]
+pub struct BulkInstancesBundle<M: MaterialInstanced>
+where
+    M::Instance: BulkInstance,
+{
+    pub material: Handle<M>,
+    pub mesh: Handle<Mesh>,
+    pub instances: BulkInstances<M::Instance>,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub computed_visibility: ComputedVisibility,
+}
+
+impl<M: MaterialInstanced> Default for BulkInstancesBundle<M>
+where
+    M::Instance: BulkInstance,
+{
+    fn default() -> Self {
+        Self {
+            material: default(),
+            mesh: default(),
+            instances: default(),
+            transform: default(),
+            global_transform: default(),
+            visibility: default(),
+            computed_visibility: default(),
+        }
+    }
+}
+
+/// Draws [`BulkInstances`] of type `I`.
+///
+/// Requires [`IndirectRenderingPlugin`](crate::prelude::IndirectRenderingPlugin).
+pub struct BulkInstancesPlugin<I: BulkInstance>(PhantomData<I>);
+
+impl<I: BulkInstance> Default for BulkInstancesPlugin<I> {
+    fn default() -> Self {
+        Self(default())
+    }
+}
+
+impl<I: BulkInstance> Plugin for BulkInstancesPlugin<I> {
+    fn build(&self, app: &mut App) {
+        // Runs after bevy's mesh bounds so that its Aabb gets replaced,
+        // and before culling so that changes take effect this frame
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            update_bulk_instances::<I>
+                .after(TransformSystem::TransformPropagate)
+                .after(VisibilitySystems::CalculateBounds)
+                .before(VisibilitySystems::CheckVisibility),
+        );
+    }
+}
+
+/// Rebuild the slice contents and bounds of changed [`BulkInstances`]
+#[allow(clippy::type_complexity)]
+pub fn update_bulk_instances<I: BulkInstance>(
+    meshes: Res<Assets<Mesh>>,
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    mut query_bulk_instances: Query<(
+        Entity,
+        &BulkInstances<I>,
+        ChangeTrackers<BulkInstances<I>>,
+        &Handle<Mesh>,
+        &GlobalTransform,
+        ChangeTrackers<GlobalTransform>,
+        Option<&mut InstanceSlice>,
+        Option<&mut InstanceSliceData<I>>,
+        Option<&mut Aabb>,
+    )>,
+    mut commands: Commands,
+) {
+    let updated_meshes = mesh_events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => Some(handle),
+            AssetEvent::Removed { .. } => None,
+        })
+        .collect::<HashSet<_>>();
+
+    for (
+        entity,
+        instances,
+        instances_tracker,
+        mesh,
+        global_transform,
+        transform_tracker,
+        slice,
+        slice_data,
+        aabb,
+    ) in query_bulk_instances.iter_mut()
+    {
+        if instances_tracker.is_changed() || transform_tracker.is_changed() || slice_data.is_none()
+        {
+            let global_transform = global_transform.compute_matrix();
+            let prepared = instances
+                .transforms
+                .iter()
+                .enumerate()
+                .map(|(i, transform)| {
+                    I::prepare_bulk_instance(
+                        global_transform * transform.compute_matrix(),
+                        &instances.data.get(i).cloned().unwrap_or_default(),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            match slice_data {
+                Some(mut slice_data) => *slice_data.instances_mut() = prepared,
+                None => {
+                    commands
+                        .entity(entity)
+                        .insert(InstanceSliceData::<I>::new(prepared));
+                }
+            }
+
+            match slice {
+                Some(mut slice) => {
+                    if slice.instance_count != instances.len() {
+                        slice.instance_count = instances.len();
+                    }
+                }
+                None => {
+                    commands.entity(entity).insert(InstanceSlice {
+                        instance_count: instances.len(),
+                    });
+                }
+            }
+        }
+
+        if !(instances_tracker.is_changed() || aabb.is_none() || updated_meshes.contains(&mesh)) {
+            continue;
+        }
+
+        // Retried every frame until the mesh has loaded
+        let mesh_aabb = match meshes.get(mesh).and_then(Mesh::compute_aabb) {
+            Some(mesh_aabb) => mesh_aabb,
+            None => continue,
+        };
+
+        let bounds = bulk_instance_bounds(&mesh_aabb, &instances.transforms);
+        match aabb {
+            Some(mut aabb) => *aabb = bounds,
+            None => {
+                commands.entity(entity).insert(bounds);
+            }
+        }
+    }
+}
+
+/// Union of `mesh_aabb` placed at each of `transforms`
+fn bulk_instance_bounds(mesh_aabb: &Aabb, transforms: &[Transform]) -> Aabb {
+    transforms
+        .iter()
+        .map(|transform| {
+            let affine = transform.compute_affine();
+            let center = affine.transform_point3a(mesh_aabb.center);
+            let half_extents = Mat3A::from_cols(
+                affine.matrix3.x_axis.abs(),
+                affine.matrix3.y_axis.abs(),
+                affine.matrix3.z_axis.abs(),
+            ) * mesh_aabb.half_extents;
+
+            (center - half_extents, center + half_extents)
+        })
+        .reduce(|(min_a, max_a), (min_b, max_b)| (min_a.min(min_b), max_a.max(max_b)))
+        .map(|(min, max): (Vec3A, Vec3A)| Aabb::from_min_max(min.into(), max.into()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_4;
+
+    use bevy::math::{Quat, Vec3, Vec4};
+
+    use super::*;
+    use crate::prelude::ColorMeshInstance;
+
+    fn assert_bounds(aabb: &Aabb, min: Vec3, max: Vec3) {
+        assert!(
+            Vec3::from(aabb.min()).abs_diff_eq(min, 1e-5)
+                && Vec3::from(aabb.max()).abs_diff_eq(max, 1e-5),
+            "{aabb:?} != {min}..{max}"
+        );
+    }
+
+    fn unit_cube() -> Aabb {
+        Aabb::from_min_max(Vec3::splat(-0.5), Vec3::splat(0.5))
+    }
+
+    #[test]
+    fn no_instances_have_empty_bounds() {
+        let bounds = bulk_instance_bounds(&unit_cube(), &[]);
+        assert_bounds(&bounds, Vec3::ZERO, Vec3::ZERO);
+    }
+
+    #[test]
+    fn bounds_follow_translation_and_scale() {
+        let bounds = bulk_instance_bounds(
+            &unit_cube(),
+            &[Transform::from_xyz(10.0, 0.0, 0.0).with_scale(Vec3::new(2.0, 1.0, 4.0))],
+        );
+        assert_bounds(
+            &bounds,
+            Vec3::new(9.0, -0.5, -2.0),
+            Vec3::new(11.0, 0.5, 2.0),
+        );
+    }
+
+    #[test]
+    fn bounds_enclose_rotated_instances() {
+        let bounds = bulk_instance_bounds(
+            &unit_cube(),
+            &[Transform::from_rotation(Quat::from_rotation_z(FRAC_PI_4))],
+        );
+
+        let extent = 0.5 * 2.0f32.sqrt();
+        assert_bounds(
+            &bounds,
+            Vec3::new(-extent, -extent, -0.5),
+            Vec3::new(extent, extent, 0.5),
+        );
+    }
+
+    #[test]
+    fn bounds_move_off_center_meshes_with_their_instance() {
+        let mesh_aabb = Aabb::from_min_max(Vec3::new(1.0, 0.0, 0.0), Vec3::new(3.0, 1.0, 1.0));
+        let bounds = bulk_instance_bounds(
+            &mesh_aabb,
+            &[Transform::from_rotation(Quat::from_rotation_y(
+                std::f32::consts::PI,
+            ))],
+        );
+        assert_bounds(
+            &bounds,
+            Vec3::new(-3.0, 0.0, -1.0),
+            Vec3::new(-1.0, 1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn bounds_cover_every_instance() {
+        let bounds = bulk_instance_bounds(
+            &unit_cube(),
+            &[
+                Transform::from_xyz(-4.0, 0.0, 0.0),
+                Transform::from_xyz(2.0, 3.0, 0.0).with_scale(Vec3::splat(2.0)),
+            ],
+        );
+        assert_bounds(
+            &bounds,
+            Vec3::new(-4.5, -0.5, -1.0),
+            Vec3::new(3.0, 4.0, 1.0),
+        );
+    }
+
+    #[test]
+    fn push_pads_missing_data_with_defaults() {
+        let mut instances = BulkInstances::<ColorMeshInstance>::from_transforms(vec![
+            Transform::IDENTITY,
+            Transform::from_xyz(1.0, 0.0, 0.0),
+        ]);
+        assert!(instances.data.is_empty());
+
+        instances.push(Transform::from_xyz(2.0, 0.0, 0.0), Vec4::ONE);
+
+        assert_eq!(instances.len(), 3);
+        assert_eq!(instances.data, vec![Vec4::ZERO, Vec4::ZERO, Vec4::ONE]);
+    }
+
+    #[test]
+    fn push_truncates_surplus_data() {
+        let mut instances = BulkInstances::<ColorMeshInstance>::new(
+            vec![Transform::IDENTITY],
+            vec![Vec4::X, Vec4::Y],
+        );
+
+        instances.push(Transform::IDENTITY, Vec4::Z);
+
+        assert_eq!(instances.data, vec![Vec4::X, Vec4::Z]);
+    }
+}
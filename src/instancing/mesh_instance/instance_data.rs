@@ -0,0 +1,178 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use bevy::{
+    ecs::{query::ROQueryItem, system::lifetimeless::Read},
+    math::Mat4,
+    prelude::{Component, Deref, DerefMut},
+    render::render_resource::{
+        encase::private::{CreateFrom, ReadFrom, WriteInto},
+        ShaderSize, ShaderType, VertexAttribute,
+    },
+};
+use bytemuck::Pod;
+
+use crate::prelude::{
+    GpuMeshInstance, Instance, InstanceStream, InstanceStreams, InstanceVertexAttributes,
+    MeshInstance,
+};
+
+/// Types that can be uploaded as a per-instance payload via [`DataMeshInstance`]
+pub trait InstancePayload:
+    'static
+    + Debug
+    + Default
+    + Pod
+    + Send
+    + Sync
+    + ShaderType
+    + ShaderSize
+    + WriteInto
+    + ReadFrom
+    + CreateFrom
+{
+}
+
+impl<T> InstancePayload for T where
+    T: 'static
+        + Debug
+        + Default
+        + Pod
+        + Send
+        + Sync
+        + ShaderType
+        + ShaderSize
+        + WriteInto
+        + ReadFrom
+        + CreateFrom
+{
+}
+
+/// Components that supply the payload of a [`DataMeshInstance`]
+pub trait InstancePayloadSource<T>: Component {
+    fn payload(&self) -> T;
+}
+
+/// Arbitrary per-instance payload, such as UV offsets or an animation phase
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deref, DerefMut, Component)]
+pub struct InstanceData<T: InstancePayload>(pub T);
+
+impl<T: InstancePayload> From<T> for InstanceData<T> {
+    fn from(data: T) -> Self {
+        InstanceData(data)
+    }
+}
+
+impl<T: InstancePayload> InstancePayloadSource<T> for InstanceData<T> {
+    fn payload(&self) -> T {
+        self.0
+    }
+}
+
+/// Mesh instance carrying a payload of type `T`, read from the component `C`
+///
+/// On the GPU this is laid out as [`GpuMeshInstance`] followed by `T`, so a shader can declare:
+///
+/// ```wgsl
+/// struct MyInstanceData {
+///     base: InstanceData,
+///     data: MyPayload,
+/// };
+/// ```
+///
+/// When bound as a vertex buffer, the payload follows the base attributes
+/// as `vec4<f32>` chunks; see [`InstanceVertexAttributes::payload`].
+#[derive(Component)]
+pub struct DataMeshInstance<T: InstancePayload, C: InstancePayloadSource<T> = InstanceData<T>> {
+    pub base: MeshInstance,
+    pub data: T,
+    _phantom: PhantomData<C>,
+}
+
+impl<T: InstancePayload, C: InstancePayloadSource<T>> DataMeshInstance<T, C> {
+    pub fn new(base: MeshInstance, data: T) -> Self {
+        DataMeshInstance {
+            base,
+            data,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+// Implemented by hand so that `C` needs no bounds beyond being a component
+impl<T: InstancePayload, C: InstancePayloadSource<T>> Debug for DataMeshInstance<T, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DataMeshInstance")
+            .field("base", &self.base)
+            .field("data", &self.data)
+            .finish()
+    }
+}
+
+impl<T: InstancePayload, C: InstancePayloadSource<T>> Clone for DataMeshInstance<T, C> {
+    fn clone(&self) -> Self {
+        DataMeshInstance::new(self.base.clone(), self.data)
+    }
+}
+
+impl<T, C> PartialEq for DataMeshInstance<T, C>
+where
+    T: InstancePayload + PartialEq,
+    C: InstancePayloadSource<T>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.base == other.base && self.data == other.data
+    }
+}
+
+impl<T: InstancePayload, C: InstancePayloadSource<T>> Default for DataMeshInstance<T, C> {
+    fn default() -> Self {
+        DataMeshInstance::new(MeshInstance::default(), T::default())
+    }
+}
+
+/// GPU-friendly data for a mesh instance with a payload
+#[derive(Debug, Default, Copy, Clone, PartialEq, ShaderType)]
+pub struct GpuDataMeshInstance<T: InstancePayload> {
+    pub base: GpuMeshInstance,
+    pub data: T,
+}
+
+impl<T, C> Instance for DataMeshInstance<T, C>
+where
+    T: InstancePayload,
+    C: InstancePayloadSource<T>,
+{
+    type ExtractedInstance = Self;
+    type PreparedInstance = GpuDataMeshInstance<T>;
+
+    type Query = (<MeshInstance as Instance>::Query, Read<C>);
+
+    fn extract_instance((base, data): ROQueryItem<Self::Query>) -> Self::ExtractedInstance {
+        DataMeshInstance::new(MeshInstance::extract_instance(base), data.payload())
+    }
+
+    fn prepare_instance(instance: &Self::ExtractedInstance, mesh: u32) -> Self::PreparedInstance {
+        GpuDataMeshInstance {
+            base: MeshInstance::prepare_instance(&instance.base, mesh),
+            data: instance.data,
+        }
+    }
+
+    fn transform(instance: &Self::ExtractedInstance) -> Mat4 {
+        instance.base.transform
+    }
+
+    fn vertex_attributes(shader_location: u32) -> Vec<VertexAttribute> {
+        InstanceVertexAttributes::new(shader_location)
+            .base::<MeshInstance>()
+            .payload::<T>()
+            .build()
+    }
+
+    fn streams() -> Vec<InstanceStream> {
+        InstanceStreams::new()
+            .base::<MeshInstance>()
+            .payload::<T>()
+            .build()
+    }
+}
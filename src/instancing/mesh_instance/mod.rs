@@ -1,14 +1,26 @@
+pub mod affine_mesh_instance;
+pub mod bulk_instances;
+pub mod instance_data;
 pub mod mesh_instance_bundle;
+pub mod quantized_mesh_instance;
+pub mod spline_instances;
+pub mod trs_mesh_instance;
 
-use crate::prelude::Instance;
+use crate::prelude::{
+    Instance, InstanceSlice, InstanceStream, InstanceStreams, InstanceVertexAttributes,
+    InstanceWgsl,
+};
 use bevy::{
     ecs::{query::ROQueryItem, system::lifetimeless::Read},
     math::Mat4,
     prelude::{
         default, Commands, Component, ComputedVisibility, Entity, GlobalTransform, Handle, Mesh,
-        Query,
+        Query, Without,
+    },
+    render::{
+        render_resource::{ShaderType, VertexAttribute},
+        Extract,
     },
-    render::{render_resource::ShaderType, Extract},
 };
 
 use super::material::material_instanced::MaterialInstanced;
@@ -96,14 +108,49 @@ impl Instance for MeshInstance {
     fn transform(instance: &Self::ExtractedInstance) -> Mat4 {
         instance.transform
     }
+
+    fn vertex_attributes(shader_location: u32) -> Vec<VertexAttribute> {
+        InstanceVertexAttributes::new(shader_location)
+            .field::<u32>()
+            .field::<Mat4>()
+            .field::<Mat4>()
+            .build()
+    }
+
+    fn streams() -> Vec<InstanceStream> {
+        InstanceStreams::new()
+            .field::<u32>()
+            .field::<Mat4>()
+            .field::<Mat4>()
+            .build()
+    }
+}
+
+impl InstanceWgsl for MeshInstance {
+    const IMPORT_PATH: &'static str = "indirect_instancing::instance_struct";
+    const STRUCT_NAME: &'static str = "InstanceData";
+    const ARRAY_NAME: &'static str = "Instances";
+    const SOA_ACCESSOR: &'static str = "load_instance";
+    const VERTEX_ACCESSOR: &'static str = "instance_from_vertex";
+
+    fn vertex_structs() -> Vec<&'static str> {
+        vec!["InstanceVertex"]
+    }
 }
 
 /// Tag type for material-independent identification of instances
 #[derive(Debug, Default, Copy, Clone, Component)]
 pub struct ExtractedInstance;
 
+/// Extract CPU-side instances.
+///
+/// Entities carrying an [`InstanceSlice`], such as those driven by
+/// [`BulkInstances`](crate::prelude::BulkInstances), draw through their slice instead.
+#[allow(clippy::type_complexity)]
 pub fn extract_mesh_instances<M: MaterialInstanced>(
-    query_mesh_instance: Extract<Query<(Entity, <M::Instance as Instance>::Query)>>,
+    query_mesh_instance: Extract<
+        Query<(Entity, <M::Instance as Instance>::Query), Without<InstanceSlice>>,
+    >,
     mut commands: Commands,
 ) {
     for (entity, item) in query_mesh_instance.iter() {
@@ -0,0 +1,240 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use bevy::{
+    ecs::{
+        query::{QueryItem, ROQueryItem},
+        system::lifetimeless::Read,
+    },
+    math::{Mat4, Quat, UVec2, Vec3, Vec4, Vec4Swizzles},
+    prelude::{
+        warn, Color, Component, ComputedVisibility, Deref, DerefMut, GlobalTransform, Handle, Mesh,
+    },
+    render::{
+        extract_component::ExtractComponent,
+        render_resource::{ShaderType, VertexAttribute},
+    },
+};
+
+use crate::prelude::{
+    pack_half2, pack_snorm16x2, pack_unorm8x4, Instance, InstanceColor, InstanceStream,
+    InstanceStreams, InstanceVertexAttributes, InstanceWgsl,
+};
+
+/// Origin that a [`QuantizedMeshInstance`]'s half-precision translation is relative to.
+///
+/// Instances are only batched with others sharing their origin.
+/// The material exposes the origin to its shader for `quantized_instance_transform`,
+/// so instances sharing a material should share an origin.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deref, DerefMut, Component)]
+pub struct InstanceOrigin(pub Vec3);
+
+impl InstanceOrigin {
+    /// Exact representation of the origin, for use in batch keys
+    pub fn batch_key(&self) -> [u32; 3] {
+        self.0.to_array().map(f32::to_bits)
+    }
+}
+
+impl ExtractComponent for InstanceOrigin {
+    type Query = Read<Self>;
+
+    type Filter = ();
+
+    fn extract_component(item: QueryItem<Self::Query>) -> Self {
+        *item
+    }
+}
+
+/// Whether non-uniform scale has been reported, to warn only once
+static NON_UNIFORM_SCALE_WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Mesh instance quantized to 32 bytes, for dense scenes that don't need full precision.
+///
+/// Translation and uniform scale are stored as halves, rotation as a snorm16 quaternion
+/// renormalized by the shader, and the optional [`InstanceColor`] as unorm8.
+///
+/// Only uniform scale is supported: non-uniform scale takes the largest axis,
+/// with a warning the first time it happens.
+#[derive(Debug, Clone, PartialEq, Component)]
+pub struct QuantizedMeshInstance {
+    pub mesh: Handle<Mesh>,
+    pub origin: Vec3,
+    /// Translation relative to `origin`
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: f32,
+    pub color: Color,
+}
+
+impl Default for QuantizedMeshInstance {
+    fn default() -> Self {
+        Self {
+            mesh: Default::default(),
+            origin: Vec3::ZERO,
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: 1.0,
+            color: Color::WHITE,
+        }
+    }
+}
+
+/// GPU-friendly data for a single quantized mesh instance
+#[derive(Debug, Default, Copy, Clone, PartialEq, ShaderType)]
+pub struct GpuQuantizedMeshInstance {
+    /// Translation and uniform scale as four halves
+    pub translation_scale: UVec2,
+    /// Rotation quaternion as four snorm16
+    pub rotation: UVec2,
+    pub mesh: u32,
+    /// Linear RGBA as unorm8x4, padded to keep the 16-byte stride uniform arrays require
+    #[size(12)]
+    pub color: u32,
+}
+
+impl Instance for QuantizedMeshInstance {
+    type ExtractedInstance = Self;
+    type PreparedInstance = GpuQuantizedMeshInstance;
+
+    type Query = (
+        Read<Handle<Mesh>>,
+        Read<GlobalTransform>,
+        Read<ComputedVisibility>,
+        Option<Read<InstanceOrigin>>,
+        Option<Read<InstanceColor>>,
+    );
+
+    fn extract_instance(
+        (mesh, transform, visibility, origin, color): ROQueryItem<Self::Query>,
+    ) -> Self::ExtractedInstance {
+        let (scale, rotation, translation) = transform.to_scale_rotation_translation();
+        let origin = origin.map(|origin| **origin).unwrap_or_default();
+
+        if !scale.abs_diff_eq(Vec3::splat(scale.x), scale.max_element() * 1e-4)
+            && !NON_UNIFORM_SCALE_WARNED.swap(true, Ordering::Relaxed)
+        {
+            warn!(
+                "QuantizedMeshInstance only supports uniform scale, using the largest axis of {scale}"
+            );
+        }
+
+        QuantizedMeshInstance {
+            mesh: mesh.clone_weak(),
+            origin,
+            translation: translation - origin,
+            rotation,
+            scale: if visibility.is_visible() {
+                scale.max_element()
+            } else {
+                0.0
+            },
+            color: color.map(|color| **color).unwrap_or(Color::WHITE),
+        }
+    }
+
+    fn prepare_instance(instance: &Self::ExtractedInstance, mesh: u32) -> Self::PreparedInstance {
+        let translation_scale = instance.translation.extend(instance.scale);
+        // Quantization leaves it slightly off unit length; decoders renormalize
+        let rotation = Vec4::from(instance.rotation.normalize());
+
+        GpuQuantizedMeshInstance {
+            translation_scale: UVec2::new(
+                pack_half2(translation_scale.xy()),
+                pack_half2(translation_scale.zw()),
+            ),
+            rotation: UVec2::new(pack_snorm16x2(rotation.xy()), pack_snorm16x2(rotation.zw())),
+            mesh,
+            color: pack_unorm8x4(Vec4::from(instance.color.as_linear_rgba_f32())),
+        }
+    }
+
+    fn transform(instance: &Self::ExtractedInstance) -> Mat4 {
+        Mat4::from_scale_rotation_translation(
+            Vec3::splat(instance.scale),
+            instance.rotation,
+            instance.origin + instance.translation,
+        )
+    }
+
+    fn vertex_attributes(shader_location: u32) -> Vec<VertexAttribute> {
+        InstanceVertexAttributes::new(shader_location)
+            .field::<UVec2>()
+            .field::<UVec2>()
+            .field::<u32>()
+            .field::<u32>()
+            .build()
+    }
+
+    fn streams() -> Vec<InstanceStream> {
+        InstanceStreams::new()
+            .field::<UVec2>()
+            .field::<UVec2>()
+            .field::<u32>()
+            .field::<u32>()
+            .build()
+    }
+}
+
+impl InstanceWgsl for QuantizedMeshInstance {
+    const IMPORT_PATH: &'static str = "indirect_instancing::quantized_instance_struct";
+    const STRUCT_NAME: &'static str = "QuantizedInstanceData";
+    const ARRAY_NAME: &'static str = "QuantizedInstances";
+    const SOA_ACCESSOR: &'static str = "load_quantized_instance";
+    const VERTEX_ACCESSOR: &'static str = "quantized_instance_from_vertex";
+
+    fn vertex_structs() -> Vec<&'static str> {
+        vec!["QuantizedInstanceVertex"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::{EulerRot, Vec2};
+
+    use super::*;
+
+    /// Matches WGSL's `unpack2x16snorm`
+    fn unpack_snorm16x2(packed: u32) -> Vec2 {
+        let snorm = |bits: u32| (bits as u16 as i16 as f32 / 32767.0).max(-1.0);
+        Vec2::new(snorm(packed), snorm(packed >> 16))
+    }
+
+    #[test]
+    fn rotation_survives_quantization() {
+        for i in 0..1000 {
+            let t = i as f32 * 0.377;
+            let rotation = Quat::from_euler(EulerRot::YXZ, t, t * 1.3, t * 0.7);
+
+            let instance = QuantizedMeshInstance {
+                rotation,
+                ..Default::default()
+            };
+            let prepared = QuantizedMeshInstance::prepare_instance(&instance, 0);
+
+            let (xy, zw) = (
+                unpack_snorm16x2(prepared.rotation.x),
+                unpack_snorm16x2(prepared.rotation.y),
+            );
+            let decoded = Quat::from_xyzw(xy.x, xy.y, zw.x, zw.y).normalize();
+
+            assert!(decoded.is_normalized());
+            assert!(
+                decoded.angle_between(rotation) < 1e-3,
+                "{rotation} decoded as {decoded}"
+            );
+        }
+    }
+
+    #[test]
+    fn origins_have_distinct_batch_keys() {
+        assert_eq!(
+            InstanceOrigin(Vec3::new(1.0, 2.0, 3.0)).batch_key(),
+            InstanceOrigin(Vec3::new(1.0, 2.0, 3.0)).batch_key()
+        );
+        assert_ne!(
+            InstanceOrigin(Vec3::new(1.0, 2.0, 3.0)).batch_key(),
+            InstanceOrigin(Vec3::new(1.0, 2.0, 3.5)).batch_key()
+        );
+        assert_eq!(InstanceOrigin::default().batch_key(), [0; 3]);
+    }
+}
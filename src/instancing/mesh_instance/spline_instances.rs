@@ -0,0 +1,496 @@
+use std::{f32::consts::PI, marker::PhantomData};
+
+use bevy::{
+    ecs::reflect::ReflectComponent,
+    math::{Quat, Vec3},
+    prelude::{
+        default, App, Bundle, Changed, Component, CoreStage, IntoSystemDescriptor, Plugin, Query,
+        Transform,
+    },
+    reflect::{FromReflect, Reflect},
+};
+
+use crate::prelude::{
+    layout_hash, update_bulk_instances, BulkInstance, BulkInstances, BulkInstancesBundle,
+    MaterialInstanced,
+};
+
+/// Samples taken along each curve segment to measure distances
+const SAMPLES_PER_SEGMENT: usize = 32;
+
+/// Curve instances are distributed along, in the entity's local space
+#[derive(Debug, Clone, PartialEq, Reflect, FromReflect)]
+pub enum SplineCurve {
+    /// Cubic Bezier segments sharing their end points,
+    /// i.e. `[start, control, control, end, control, control, end, ...]`
+    Bezier(Vec<Vec3>),
+    /// Uniform Catmull-Rom spline passing through every point
+    CatmullRom(Vec<Vec3>),
+}
+
+impl Default for SplineCurve {
+    fn default() -> Self {
+        SplineCurve::CatmullRom(vec![])
+    }
+}
+
+impl SplineCurve {
+    /// Bezier control points of each segment
+    pub fn segments(&self) -> Vec<[Vec3; 4]> {
+        match self {
+            SplineCurve::Bezier(points) => points
+                .windows(4)
+                .step_by(3)
+                .map(|segment| [segment[0], segment[1], segment[2], segment[3]])
+                .collect(),
+            SplineCurve::CatmullRom(points) => (0..points.len().saturating_sub(1))
+                .map(|i| {
+                    // Clamp neighbours at either end of the curve
+                    let p0 = points[i.saturating_sub(1)];
+                    let p1 = points[i];
+                    let p2 = points[i + 1];
+                    let p3 = points[(i + 2).min(points.len() - 1)];
+                    [p1, p1 + (p2 - p0) / 6.0, p2 - (p3 - p1) / 6.0, p2]
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Position along a cubic Bezier segment at `t`
+pub fn cubic_position([p0, p1, p2, p3]: [Vec3; 4], t: f32) -> Vec3 {
+    let s = 1.0 - t;
+    p0 * (s * s * s) + p1 * (3.0 * s * s * t) + p2 * (3.0 * s * t * t) + p3 * (t * t * t)
+}
+
+/// Derivative of a cubic Bezier segment at `t`
+pub fn cubic_tangent([p0, p1, p2, p3]: [Vec3; 4], t: f32) -> Vec3 {
+    let s = 1.0 - t;
+    (p1 - p0) * (3.0 * s * s) + (p2 - p1) * (6.0 * s * t) + (p3 - p2) * (3.0 * t * t)
+}
+
+/// How far apart instances are along a [`SplineCurve`]
+#[derive(Debug, Copy, Clone, PartialEq, Reflect, FromReflect)]
+pub enum SplineSpacing {
+    /// An instance every `distance` along the curve, starting from its first point
+    Distance(f32),
+    /// `count` instances spread evenly from the first point to the last
+    Count(u32),
+}
+
+impl Default for SplineSpacing {
+    fn default() -> Self {
+        SplineSpacing::Distance(1.0)
+    }
+}
+
+/// Distributes the [`BulkInstances`] of its entity along a spline,
+/// regenerating them whenever it changes.
+///
+/// Randomization is seeded, so the same settings always produce the same instances.
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub struct SplineInstances {
+    pub curve: SplineCurve,
+    pub spacing: SplineSpacing,
+    /// Turn each instance's forward (-Z) axis along the curve, keeping +Y up
+    pub align_to_tangent: bool,
+    /// Applied to each instance before it is placed, i.e. to correct a mesh's orientation
+    pub instance_transform: Transform,
+    /// Maximum random offset along each of the instance's placed axes
+    pub position_jitter: Vec3,
+    /// Maximum random rotation about the instance's placed +Y, in radians
+    pub rotation_jitter: f32,
+    /// Uniform scale is picked at random between `min_scale` and `max_scale`
+    pub min_scale: f32,
+    pub max_scale: f32,
+    pub seed: u32,
+}
+
+impl Default for SplineInstances {
+    fn default() -> Self {
+        Self {
+            curve: default(),
+            spacing: default(),
+            align_to_tangent: true,
+            instance_transform: default(),
+            position_jitter: Vec3::ZERO,
+            rotation_jitter: 0.0,
+            min_scale: 1.0,
+            max_scale: 1.0,
+            seed: 0,
+        }
+    }
+}
+
+impl SplineInstances {
+    pub fn new(curve: SplineCurve, spacing: SplineSpacing) -> Self {
+        Self {
+            curve,
+            spacing,
+            ..default()
+        }
+    }
+
+    /// Local transform of every instance along the curve
+    pub fn transforms(&self) -> Vec<Transform> {
+        let segments = self.curve.segments();
+        if segments.is_empty() {
+            return vec![];
+        }
+
+        // Cumulative distance at each sample, used to place instances by distance
+        let mut samples = vec![(0, 0.0, 0.0)];
+        let mut length = 0.0;
+        for (i, segment) in segments.iter().enumerate() {
+            let mut previous = cubic_position(*segment, 0.0);
+            for sample in 1..=SAMPLES_PER_SEGMENT {
+                let t = sample as f32 / SAMPLES_PER_SEGMENT as f32;
+                let position = cubic_position(*segment, t);
+                length += previous.distance(position);
+                samples.push((i, t, length));
+                previous = position;
+            }
+        }
+
+        let distances = match self.spacing {
+            SplineSpacing::Distance(spacing) if spacing > 0.0 => {
+                let count = (length / spacing).floor() as usize + 1;
+                (0..count).map(|i| i as f32 * spacing).collect::<Vec<_>>()
+            }
+            SplineSpacing::Distance(_) => vec![],
+            SplineSpacing::Count(count) => (0..count)
+                .map(|i| length * i as f32 / count.saturating_sub(1).max(1) as f32)
+                .collect(),
+        };
+
+        distances
+            .into_iter()
+            .enumerate()
+            .map(|(index, distance)| {
+                let (segment, t) = sample_at_distance(&samples, distance);
+                self.place_instance(
+                    index as u32,
+                    cubic_position(segments[segment], t),
+                    cubic_tangent(segments[segment], t),
+                )
+            })
+            .collect()
+    }
+
+    /// Place, orient and randomize instance `index`
+    fn place_instance(&self, index: u32, position: Vec3, tangent: Vec3) -> Transform {
+        let random = |offset: u32| {
+            layout_hash(
+                index
+                    .wrapping_mul(8)
+                    .wrapping_add(self.seed.wrapping_mul(2654435761))
+                    .wrapping_add(offset),
+            )
+        };
+
+        let mut rotation = Quat::IDENTITY;
+        if self.align_to_tangent && tangent.length_squared() > f32::EPSILON {
+            // Fall back to a horizontal up axis for vertical tangents
+            let up = if tangent.normalize().cross(Vec3::Y).length_squared() > 1e-6 {
+                Vec3::Y
+            } else {
+                Vec3::Z
+            };
+            rotation = Transform::IDENTITY.looking_at(tangent, up).rotation;
+        }
+
+        rotation *= Quat::from_rotation_y((random(0) * 2.0 - 1.0) * self.rotation_jitter.min(PI));
+
+        let jitter =
+            (Vec3::new(random(1), random(2), random(3)) * 2.0 - 1.0) * self.position_jitter;
+        let scale = self.min_scale + (self.max_scale - self.min_scale) * random(4);
+
+        Transform {
+            translation: position + rotation * jitter,
+            rotation,
+            scale: Vec3::splat(scale),
+        }
+        .mul_transform(self.instance_transform)
+    }
+}
+
+/// Segment and parameter at `distance` along the curve, interpolated between samples
+fn sample_at_distance(samples: &[(usize, f32, f32)], distance: f32) -> (usize, f32) {
+    let next = samples
+        .partition_point(|(_, _, sample_distance)| *sample_distance < distance)
+        .clamp(1, samples.len() - 1);
+
+    let (_, previous_t, previous_distance) = samples[next - 1];
+    let (segment, next_t, next_distance) = samples[next];
+
+    // The sample before a segment's first is the previous segment's end
+    let previous_t = if next_t > previous_t { previous_t } else { 0.0 };
+
+    let f = if next_distance > previous_distance {
+        ((distance - previous_distance) / (next_distance - previous_distance)).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+
+    (segment, previous_t + (next_t - previous_t) * f)
+}
+
+/// Components to draw instances along a spline from a single entity
+#[derive(Bundle)]
+pub struct SplineInstancesBundle<M: MaterialInstanced>
+where
+    M::Instance: BulkInstance,
+{
+    #[bundle]
+    pub bulk_instances_bundle: BulkInstancesBundle<M>,
+    pub spline: SplineInstances,
+}
+
+impl<M: MaterialInstanced> Default for SplineInstancesBundle<M>
+where
+    M::Instance: BulkInstance,
+{
+    fn default() -> Self {
+        Self {
+            bulk_instances_bundle: default(),
+            spline: default(),
+        }
+    }
+}
+
+/// Places [`BulkInstances`] of type `I` along their entity's [`SplineInstances`].
+///
+/// Requires [`BulkInstancesPlugin`](crate::prelude::BulkInstancesPlugin) for `I`, which isn't added implicitly
+/// so that it can be shared with other users of [`BulkInstances`].
+pub struct SplineInstancesPlugin<I: BulkInstance>(PhantomData<I>);
+
+impl<I: BulkInstance> Default for SplineInstancesPlugin<I> {
+    fn default() -> Self {
+        Self(default())
+    }
+}
+
+impl<I: BulkInstance> Plugin for SplineInstancesPlugin<I> {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SplineInstances>();
+
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            update_spline_instances::<I>.before(update_bulk_instances::<I>),
+        );
+    }
+}
+
+/// Regenerate the transforms of changed [`SplineInstances`]
+pub fn update_spline_instances<I: BulkInstance>(
+    mut query_spline_instances: Query<
+        (&SplineInstances, &mut BulkInstances<I>),
+        Changed<SplineInstances>,
+    >,
+) {
+    for (spline, mut instances) in query_spline_instances.iter_mut() {
+        instances.transforms = spline.transforms();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Straight Bezier along `direction`, with control points spaced evenly
+    fn line(direction: Vec3, length: f32) -> SplineCurve {
+        SplineCurve::Bezier(vec![
+            Vec3::ZERO,
+            direction * length / 3.0,
+            direction * length * 2.0 / 3.0,
+            direction * length,
+        ])
+    }
+
+    fn translations(spline: &SplineInstances) -> Vec<Vec3> {
+        spline
+            .transforms()
+            .into_iter()
+            .map(|transform| transform.translation)
+            .collect()
+    }
+
+    #[test]
+    fn instances_span_the_curve_end_to_end() {
+        let spline = SplineInstances::new(line(Vec3::X, 10.0), SplineSpacing::Count(5));
+
+        let translations = translations(&spline);
+        assert_eq!(translations.len(), 5);
+        assert!(translations[0].abs_diff_eq(Vec3::ZERO, 1e-4));
+        assert!(translations[4].abs_diff_eq(Vec3::X * 10.0, 1e-4));
+    }
+
+    #[test]
+    fn instances_are_spaced_by_arc_length() {
+        // Control points bunched at the start, so the curve speeds up along its parameter
+        let curve = SplineCurve::Bezier(vec![
+            Vec3::ZERO,
+            Vec3::X * 0.1,
+            Vec3::X * 0.2,
+            Vec3::X * 10.5,
+        ]);
+        let spline = SplineInstances::new(curve, SplineSpacing::Distance(1.0));
+
+        let translations = translations(&spline);
+        assert_eq!(translations.len(), 11);
+        for (i, translation) in translations.iter().enumerate() {
+            assert!(
+                (translation.x - i as f32).abs() < 0.05,
+                "instance {i} at {translation}"
+            );
+        }
+    }
+
+    #[test]
+    fn distance_spacing_starts_at_the_first_point() {
+        let spline = SplineInstances::new(line(Vec3::X, 10.0), SplineSpacing::Distance(3.0));
+
+        let translations = translations(&spline);
+        assert_eq!(translations.len(), 4);
+        for (translation, x) in translations.iter().zip([0.0, 3.0, 6.0, 9.0]) {
+            assert!(translation.abs_diff_eq(Vec3::X * x, 1e-3));
+        }
+
+        let spline = SplineInstances::new(line(Vec3::X, 10.0), SplineSpacing::Distance(0.0));
+        assert!(spline.transforms().is_empty());
+    }
+
+    #[test]
+    fn count_spacing_spreads_instances_evenly() {
+        let spline = SplineInstances::new(line(Vec3::X, 10.0), SplineSpacing::Count(5));
+        for (translation, x) in translations(&spline).iter().zip([0.0, 2.5, 5.0, 7.5, 10.0]) {
+            assert!(translation.abs_diff_eq(Vec3::X * x, 1e-3));
+        }
+
+        let spline = SplineInstances::new(line(Vec3::X, 10.0), SplineSpacing::Count(1));
+        assert_eq!(translations(&spline), vec![Vec3::ZERO]);
+
+        let spline = SplineInstances::new(line(Vec3::X, 10.0), SplineSpacing::Count(0));
+        assert!(spline.transforms().is_empty());
+    }
+
+    #[test]
+    fn empty_curves_have_no_instances() {
+        let spline = SplineInstances::new(
+            SplineCurve::CatmullRom(vec![Vec3::ONE]),
+            SplineSpacing::Count(3),
+        );
+        assert!(spline.transforms().is_empty());
+    }
+
+    #[test]
+    fn instances_face_along_the_tangent() {
+        for direction in [Vec3::X, Vec3::NEG_Z, Vec3::Y] {
+            let spline = SplineInstances::new(line(direction, 4.0), SplineSpacing::Count(3));
+
+            for transform in spline.transforms() {
+                assert!(
+                    transform.forward().abs_diff_eq(direction, 1e-4),
+                    "{:?} facing {} along {direction}",
+                    transform.translation,
+                    transform.forward()
+                );
+            }
+        }
+
+        let spline = SplineInstances {
+            align_to_tangent: false,
+            ..SplineInstances::new(line(Vec3::X, 4.0), SplineSpacing::Count(3))
+        };
+        for transform in spline.transforms() {
+            assert_eq!(transform.rotation, Quat::IDENTITY);
+        }
+    }
+
+    #[test]
+    fn jitter_is_deterministic_per_seed() {
+        let spline = SplineInstances {
+            position_jitter: Vec3::splat(0.5),
+            rotation_jitter: 1.0,
+            min_scale: 0.5,
+            max_scale: 2.0,
+            seed: 7,
+            ..SplineInstances::new(line(Vec3::X, 10.0), SplineSpacing::Count(8))
+        };
+
+        let transforms = spline.transforms();
+        assert_eq!(transforms, spline.transforms());
+
+        let reseeded = SplineInstances {
+            seed: 8,
+            ..spline.clone()
+        };
+        assert_ne!(transforms, reseeded.transforms());
+
+        let unjittered = SplineInstances::new(line(Vec3::X, 10.0), SplineSpacing::Count(8));
+        for (transform, base) in transforms.iter().zip(unjittered.transforms()) {
+            let offset = transform.translation - base.translation;
+            assert!(offset.length() <= Vec3::splat(0.5).length() + 1e-4);
+            assert!((0.5..=2.0).contains(&transform.scale.x));
+            assert_eq!(transform.scale, Vec3::splat(transform.scale.x));
+        }
+    }
+
+    #[test]
+    fn catmull_rom_segments_pass_through_every_point() {
+        let points = vec![Vec3::ZERO, Vec3::X, Vec3::new(2.0, 1.0, 0.0)];
+        let segments = SplineCurve::CatmullRom(points.clone()).segments();
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0][0], points[0]);
+        assert_eq!(segments[0][3], points[1]);
+        assert_eq!(segments[1][0], points[1]);
+        assert_eq!(segments[1][3], points[2]);
+
+        // Interior tangents are shared between segments, so the curve is smooth
+        let outgoing = segments[1][1] - segments[1][0];
+        let incoming = segments[0][3] - segments[0][2];
+        assert!(outgoing.abs_diff_eq(incoming, 1e-6));
+        assert!(outgoing.abs_diff_eq((points[2] - points[0]) / 6.0, 1e-6));
+
+        // End points are clamped, pointing at their only neighbour
+        assert!((segments[0][1] - segments[0][0]).abs_diff_eq((points[1] - points[0]) / 6.0, 1e-6));
+    }
+
+    #[test]
+    fn bezier_segments_share_end_points() {
+        let points = (0..7).map(|i| Vec3::X * i as f32).collect::<Vec<_>>();
+        let segments = SplineCurve::Bezier(points.clone()).segments();
+
+        assert_eq!(
+            segments,
+            vec![
+                [points[0], points[1], points[2], points[3]],
+                [points[3], points[4], points[5], points[6]],
+            ]
+        );
+    }
+
+    #[test]
+    fn sampling_crosses_segment_boundaries() {
+        // Two segments of length 1, sampled at their midpoints and ends
+        let samples = [
+            (0, 0.0, 0.0),
+            (0, 0.5, 0.5),
+            (0, 1.0, 1.0),
+            (1, 0.5, 1.5),
+            (1, 1.0, 2.0),
+        ];
+
+        assert_eq!(sample_at_distance(&samples, 0.0), (0, 0.0));
+        assert_eq!(sample_at_distance(&samples, 0.75), (0, 0.75));
+        assert_eq!(sample_at_distance(&samples, 1.0), (0, 1.0));
+        assert_eq!(sample_at_distance(&samples, 1.25), (1, 0.25));
+        assert_eq!(sample_at_distance(&samples, 2.0), (1, 1.0));
+
+        // Distances past either end clamp to the curve
+        assert_eq!(sample_at_distance(&samples, -1.0), (0, 0.0));
+        assert_eq!(sample_at_distance(&samples, 3.0), (1, 1.0));
+    }
+}
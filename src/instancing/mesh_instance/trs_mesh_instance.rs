@@ -0,0 +1,119 @@
+use bevy::{
+    ecs::{query::ROQueryItem, system::lifetimeless::Read},
+    math::{Mat4, Quat, Vec3, Vec4},
+    prelude::{Component, ComputedVisibility, GlobalTransform, Handle, Mesh},
+    render::render_resource::{ShaderType, VertexAttribute},
+};
+
+use crate::prelude::{
+    Instance, InstanceStream, InstanceStreams, InstanceVertexAttributes, InstanceWgsl,
+};
+
+/// Mesh instance uploaded as translation, rotation and scale.
+///
+/// At 48 bytes against [`MeshInstance`](crate::prelude::MeshInstance)'s 144,
+/// with the matrices rebuilt in the shader via `trs_instance_transform`
+/// and `trs_instance_normal_matrix`. Cannot represent shear.
+#[derive(Debug, Clone, PartialEq, Component)]
+pub struct TrsMeshInstance {
+    pub mesh: Handle<Mesh>,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for TrsMeshInstance {
+    fn default() -> Self {
+        Self {
+            mesh: Default::default(),
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+        }
+    }
+}
+
+/// GPU-friendly data for a single translation-rotation-scale mesh instance
+#[derive(Debug, Default, Copy, Clone, PartialEq, ShaderType)]
+pub struct GpuTrsMeshInstance {
+    pub translation: Vec3,
+    pub mesh: u32,
+    /// Rotation quaternion as `xyzw`
+    pub rotation: Vec4,
+    pub scale: Vec3,
+}
+
+impl Instance for TrsMeshInstance {
+    type ExtractedInstance = Self;
+    type PreparedInstance = GpuTrsMeshInstance;
+
+    type Query = (
+        Read<Handle<Mesh>>,
+        Read<GlobalTransform>,
+        Read<ComputedVisibility>,
+    );
+
+    fn extract_instance(
+        (mesh, transform, visibility): ROQueryItem<Self::Query>,
+    ) -> Self::ExtractedInstance {
+        let (scale, rotation, translation) = transform.to_scale_rotation_translation();
+
+        TrsMeshInstance {
+            mesh: mesh.clone_weak(),
+            translation,
+            rotation,
+            scale: if visibility.is_visible() {
+                scale
+            } else {
+                Vec3::ZERO
+            },
+        }
+    }
+
+    fn prepare_instance(instance: &Self::ExtractedInstance, mesh: u32) -> Self::PreparedInstance {
+        GpuTrsMeshInstance {
+            translation: instance.translation,
+            mesh,
+            rotation: Vec4::from(instance.rotation),
+            scale: instance.scale,
+        }
+    }
+
+    fn transform(instance: &Self::ExtractedInstance) -> Mat4 {
+        Mat4::from_scale_rotation_translation(
+            instance.scale,
+            instance.rotation,
+            instance.translation,
+        )
+    }
+
+    fn vertex_attributes(shader_location: u32) -> Vec<VertexAttribute> {
+        InstanceVertexAttributes::new(shader_location)
+            .field::<Vec3>()
+            .field::<u32>()
+            .field::<Vec4>()
+            .field::<Vec3>()
+            .build()
+    }
+
+    fn streams() -> Vec<InstanceStream> {
+        InstanceStreams::new()
+            .field::<Vec3>()
+            .field::<u32>()
+            .field::<Vec4>()
+            .field::<Vec3>()
+            .build()
+    }
+}
+
+impl InstanceWgsl for TrsMeshInstance {
+    const IMPORT_PATH: &'static str = "indirect_instancing::trs_instance_struct";
+    const STRUCT_NAME: &'static str = "TrsInstanceData";
+    const ARRAY_NAME: &'static str = "TrsInstances";
+    const SOA_ACCESSOR: &'static str = "load_trs_instance";
+    const VERTEX_ACCESSOR: &'static str = "trs_instance_from_vertex";
+
+    fn vertex_structs() -> Vec<&'static str> {
+        vec!["TrsInstanceVertex"]
+    }
+}
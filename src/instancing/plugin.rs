@@ -3,14 +3,22 @@ use bevy::{
     prelude::{App, HandleUntyped, IntoSystemDescriptor, Plugin, Shader},
     reflect::TypeUuid,
     render::{
-        extract_component::ExtractComponentPlugin, render_asset::PrepareAssetLabel, RenderApp,
-        RenderStage,
+        extract_component::ExtractComponentPlugin,
+        render_asset::PrepareAssetLabel,
+        render_graph::{EmptyNode, RenderGraph},
+        RenderApp, RenderStage,
     },
 };
 
 use crate::{
     instancing::material::systems::prepare_mesh_batches::{self, MeshBatches},
-    prelude::{InstanceSlice, InstancedMeshPipeline},
+    prelude::{
+        cleanup_live_counts, cleanup_readback_copies, InstanceBufferMode, InstanceComputeBackend,
+        InstanceComputeRunMode, InstanceComputeStage, InstanceOrigin, InstanceSlice,
+        InstanceSliceLiveCount, InstanceSliceLiveCountNode, InstanceSliceLiveCounts,
+        InstanceSliceReadbackCopies, InstanceSliceReadbackNode, InstancedMeshPipeline,
+        INSTANCE_SLICE_LIVE_COUNT_NODE, INSTANCE_SLICE_READBACK_NODE,
+    },
 };
 
 pub const INSTANCED_MESH_SHADER_HANDLE: HandleUntyped =
@@ -19,6 +27,18 @@ pub const INSTANCED_MESH_SHADER_HANDLE: HandleUntyped =
 pub const INSTANCE_STRUCT_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 14563515845427599203);
 
+pub const AFFINE_INSTANCE_STRUCT_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 3370842156934871241);
+
+pub const TRS_INSTANCE_STRUCT_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 16021532990431746807);
+
+pub const QUANTIZE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 11469620841350298331);
+
+pub const QUANTIZED_INSTANCE_STRUCT_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 6021488173362614019);
+
 pub const INDIRECT_STRUCT_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 7281773422344927676);
 
@@ -42,6 +62,34 @@ impl Plugin for IndirectRenderingPlugin {
             Shader::from_wgsl
         );
 
+        load_internal_asset!(
+            app,
+            AFFINE_INSTANCE_STRUCT_HANDLE,
+            "render/shaders/affine_instance_struct.wgsl",
+            Shader::from_wgsl
+        );
+
+        load_internal_asset!(
+            app,
+            TRS_INSTANCE_STRUCT_HANDLE,
+            "render/shaders/trs_instance_struct.wgsl",
+            Shader::from_wgsl
+        );
+
+        load_internal_asset!(
+            app,
+            QUANTIZE_HANDLE,
+            "render/shaders/quantize.wgsl",
+            Shader::from_wgsl
+        );
+
+        load_internal_asset!(
+            app,
+            QUANTIZED_INSTANCE_STRUCT_HANDLE,
+            "render/shaders/quantized_instance_struct.wgsl",
+            Shader::from_wgsl
+        );
+
         load_internal_asset!(
             app,
             INDIRECT_STRUCT_HANDLE,
@@ -49,16 +97,78 @@ impl Plugin for IndirectRenderingPlugin {
             Shader::from_wgsl
         );
 
-        app.register_type::<InstanceSlice>();
+        let instance_buffer_mode = app
+            .world
+            .get_resource::<InstanceBufferMode>()
+            .copied()
+            .unwrap_or_default();
 
-        app.add_plugin(ExtractComponentPlugin::<InstanceSlice>::default());
+        app.insert_resource(instance_buffer_mode);
+
+        let instance_compute_backend = app
+            .world
+            .get_resource::<InstanceComputeBackend>()
+            .copied()
+            .unwrap_or_default();
+
+        app.insert_resource(instance_compute_backend);
+
+        app.register_type::<InstanceSlice>()
+            .register_type::<InstanceSliceLiveCount>()
+            .register_type::<InstanceComputeRunMode>();
+
+        app.add_plugin(ExtractComponentPlugin::<InstanceSlice>::default())
+            .add_plugin(ExtractComponentPlugin::<InstanceSliceLiveCount>::default())
+            .add_plugin(ExtractComponentPlugin::<InstanceComputeRunMode>::default())
+            .add_plugin(ExtractComponentPlugin::<InstanceOrigin>::default());
 
         app.sub_app_mut(RenderApp)
+            .insert_resource(instance_buffer_mode)
+            .insert_resource(instance_compute_backend)
             .init_resource::<InstancedMeshPipeline>()
             .init_resource::<MeshBatches>()
+            .init_resource::<InstanceSliceLiveCounts>()
+            .init_resource::<InstanceSliceReadbackCopies>()
             .add_system_to_stage(
                 RenderStage::Prepare,
                 prepare_mesh_batches::system.after(PrepareAssetLabel::AssetPrepare),
-            );
+            )
+            .add_system_to_stage(RenderStage::Cleanup, cleanup_live_counts)
+            .add_system_to_stage(RenderStage::Cleanup, cleanup_readback_copies);
+
+        let mut render_graph = app
+            .sub_app_mut(RenderApp)
+            .world
+            .resource_mut::<RenderGraph>();
+
+        // Instance compute stages run in sequence, followed by live counts
+        for stage in InstanceComputeStage::ALL {
+            render_graph.add_node(stage.label(), EmptyNode);
+            if let Some(previous) = stage.previous() {
+                render_graph
+                    .add_node_edge(previous.label(), stage.label())
+                    .unwrap();
+            }
+        }
+
+        render_graph.add_node(INSTANCE_SLICE_LIVE_COUNT_NODE, InstanceSliceLiveCountNode);
+        render_graph
+            .add_node_edge(
+                InstanceComputeStage::Cull.label(),
+                INSTANCE_SLICE_LIVE_COUNT_NODE,
+            )
+            .unwrap();
+        render_graph
+            .add_node_edge(
+                INSTANCE_SLICE_LIVE_COUNT_NODE,
+                bevy::render::main_graph::node::CAMERA_DRIVER,
+            )
+            .unwrap();
+
+        // Read back after the live count node, and therefore after all instance compute
+        render_graph.add_node(INSTANCE_SLICE_READBACK_NODE, InstanceSliceReadbackNode);
+        render_graph
+            .add_node_edge(INSTANCE_SLICE_LIVE_COUNT_NODE, INSTANCE_SLICE_READBACK_NODE)
+            .unwrap();
     }
 }
@@ -2,14 +2,16 @@ use std::num::NonZeroU64;
 
 use bevy::{
     ecs::query::{ROQueryItem, ReadOnlyWorldQuery},
-    math::Mat4,
-    prelude::Component,
+    math::{IVec2, IVec3, IVec4, Mat3, Mat4, UVec2, UVec3, UVec4, Vec2, Vec3, Vec4},
+    prelude::{Component, HandleUntyped, Shader},
     render::render_resource::{
         encase::private::{ShaderType, WriteInto},
-        ShaderSize,
+        ShaderSize, VertexAttribute, VertexFormat,
     },
 };
 
+pub use bevy_instancing_derive::Instance;
+
 pub trait Instance {
     type ExtractedInstance: std::fmt::Debug + Component;
     type PreparedInstance: std::fmt::Debug
@@ -26,6 +28,175 @@ pub trait Instance {
     fn prepare_instance(instance: &Self::ExtractedInstance, mesh: u32) -> Self::PreparedInstance;
 
     fn transform(instance: &Self::ExtractedInstance) -> Mat4;
+
+    /// WGSL import declaring [`Self::PreparedInstance`], registered by `InstancedMaterialPlugin`.
+    /// Generated by `#[derive(Instance)]`; hand-written instances load their own.
+    fn shader() -> Option<(HandleUntyped, Shader)> {
+        None
+    }
+
+    /// Per-instance vertex attributes describing [`Self::PreparedInstance`],
+    /// used when instances are bound via `InstanceBufferMode::Vertex`.
+    fn vertex_attributes(_shader_location: u32) -> Vec<VertexAttribute> {
+        vec![]
+    }
+
+    /// Per-field streams of [`Self::PreparedInstance`],
+    /// used when instances are stored via `InstanceBufferMode::StructOfArrays`.
+    fn streams() -> Vec<InstanceStream> {
+        vec![]
+    }
+}
+
+/// A single field of a prepared instance, stored in its own buffer
+/// under `InstanceBufferMode::StructOfArrays`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct InstanceStream {
+    /// Offset of the field within the prepared instance
+    pub offset: u64,
+    /// Size of the field
+    pub size: u64,
+    /// WGSL array stride of the field
+    pub stride: u64,
+}
+
+/// Builds [`Instance::streams`] by walking a prepared instance's fields in order,
+/// placing each at its WGSL-aligned offset
+#[derive(Debug, Default, Clone)]
+pub struct InstanceStreams {
+    offset: u64,
+    streams: Vec<InstanceStream>,
+}
+
+impl InstanceStreams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn align(&mut self, align: u64) {
+        self.offset = self.offset.next_multiple_of(align);
+    }
+
+    /// Nested base instance, flattened into its own streams.
+    /// Assumed to be 16-byte aligned, as any struct containing a matrix is
+    pub fn base<I: Instance>(mut self) -> Self {
+        self.align(16);
+        for stream in I::streams() {
+            self.streams.push(InstanceStream {
+                offset: self.offset + stream.offset,
+                ..stream
+            });
+        }
+        self.offset += I::PreparedInstance::SHADER_SIZE.get();
+        self
+    }
+
+    /// Field with a known WGSL type
+    pub fn field<T: InstanceFieldWgsl + ShaderSize>(mut self) -> Self {
+        self.align(T::ALIGN);
+        self.push::<T>();
+        self
+    }
+
+    /// Opaque 16-byte aligned value, stored as a single stream
+    pub fn payload<T: ShaderSize>(mut self) -> Self {
+        self.align(16);
+        self.push::<T>();
+        self
+    }
+
+    fn push<T: ShaderSize>(&mut self) {
+        let size = T::SHADER_SIZE.get();
+        self.streams.push(InstanceStream {
+            offset: self.offset,
+            size,
+            // A single-element runtime array is exactly one stride long
+            stride: <Vec<T> as ShaderType>::min_size().get(),
+        });
+        self.offset += size;
+    }
+
+    pub fn build(self) -> Vec<InstanceStream> {
+        self.streams
+    }
+}
+
+/// First shader location used by per-instance vertex attributes,
+/// following the position, normal, uv, tangent and color mesh attributes
+pub const INSTANCE_VERTEX_LOCATION: u32 = 5;
+
+/// Builds [`Instance::vertex_attributes`] by walking a prepared instance's fields in order,
+/// placing each at its WGSL-aligned offset and assigning consecutive shader locations
+#[derive(Debug, Clone)]
+pub struct InstanceVertexAttributes {
+    offset: u64,
+    shader_location: u32,
+    attributes: Vec<VertexAttribute>,
+}
+
+impl InstanceVertexAttributes {
+    pub fn new(shader_location: u32) -> Self {
+        InstanceVertexAttributes {
+            offset: 0,
+            shader_location,
+            attributes: vec![],
+        }
+    }
+
+    fn align(&mut self, align: u64) {
+        self.offset = self.offset.next_multiple_of(align);
+    }
+
+    fn push(&mut self, format: VertexFormat, offset: u64) {
+        self.attributes.push(VertexAttribute {
+            format,
+            offset,
+            shader_location: self.shader_location,
+        });
+        self.shader_location += 1;
+    }
+
+    /// Nested base instance, assumed to be 16-byte aligned as any struct containing a matrix is
+    pub fn base<I: Instance>(mut self) -> Self {
+        self.align(16);
+        for attribute in I::vertex_attributes(self.shader_location) {
+            self.push(attribute.format, self.offset + attribute.offset);
+        }
+        self.offset += I::PreparedInstance::SHADER_SIZE.get();
+        self
+    }
+
+    /// Field with a known WGSL type; matrices take one location per column
+    pub fn field<T: InstanceFieldWgsl + ShaderSize>(mut self) -> Self {
+        self.align(T::ALIGN);
+        for (i, format) in T::VERTEX_FORMATS.iter().enumerate() {
+            self.push(*format, self.offset + i as u64 * 16);
+        }
+        self.offset += T::SHADER_SIZE.get();
+        self
+    }
+
+    /// Opaque payload, exposed as `vec4<f32>` chunks followed by a narrower remainder.
+    /// Shaders `bitcast` any integer components.
+    pub fn payload<T: ShaderSize>(mut self) -> Self {
+        self.align(16);
+        let size = T::SHADER_SIZE.get();
+        for chunk in (0..size).step_by(16) {
+            let format = match (size - chunk).min(16) {
+                4 => VertexFormat::Float32,
+                8 => VertexFormat::Float32x2,
+                12 => VertexFormat::Float32x3,
+                _ => VertexFormat::Float32x4,
+            };
+            self.push(format, self.offset + chunk);
+        }
+        self.offset += size;
+        self
+    }
+
+    pub fn build(self) -> Vec<VertexAttribute> {
+        self.attributes
+    }
 }
 
 pub trait InstanceUniformLength: Instance {
@@ -39,3 +210,329 @@ where
     const UNIFORM_BUFFER_LENGTH: NonZeroU64 =
         unsafe { NonZeroU64::new_unchecked(16384 / T::PreparedInstance::SHADER_SIZE.get()) };
 }
+
+/// Names of the WGSL declarations matching an [`Instance`]'s prepared data
+pub trait InstanceWgsl: Instance {
+    /// `#define_import_path` of the module declaring the instance structs
+    const IMPORT_PATH: &'static str;
+    /// Per-instance struct
+    const STRUCT_NAME: &'static str;
+    /// Array wrapper bound at `@group(2) @binding(0)`
+    const ARRAY_NAME: &'static str;
+    /// Function loading an instance from its streams under `SOA_INSTANCES`
+    const SOA_ACCESSOR: &'static str;
+    /// Function building an instance from its vertex inputs under `VERTEX_INSTANCES`
+    const VERTEX_ACCESSOR: &'static str;
+
+    /// Per-instance vertex input structs taken by [`Self::VERTEX_ACCESSOR`], in order
+    fn vertex_structs() -> Vec<&'static str>;
+}
+
+/// Rust types usable as `#[derive(Instance)]` fields, alongside their WGSL equivalent
+pub trait InstanceFieldWgsl {
+    const WGSL_TYPE: &'static str;
+    /// WGSL alignment in bytes
+    const ALIGN: u64;
+    /// Vertex format of each column, laid out at a 16-byte stride
+    const VERTEX_FORMATS: &'static [VertexFormat];
+}
+
+macro_rules! impl_instance_field_wgsl {
+    ($($ty:ty => $wgsl:literal, $align:literal, [$($format:ident),*]),* $(,)?) => {
+        $(
+            impl InstanceFieldWgsl for $ty {
+                const WGSL_TYPE: &'static str = $wgsl;
+                const ALIGN: u64 = $align;
+                const VERTEX_FORMATS: &'static [VertexFormat] = &[$(VertexFormat::$format),*];
+            }
+        )*
+    };
+}
+
+impl_instance_field_wgsl!(
+    f32 => "f32", 4, [Float32],
+    u32 => "u32", 4, [Uint32],
+    i32 => "i32", 4, [Sint32],
+    Vec2 => "vec2<f32>", 8, [Float32x2],
+    Vec3 => "vec3<f32>", 16, [Float32x3],
+    Vec4 => "vec4<f32>", 16, [Float32x4],
+    UVec2 => "vec2<u32>", 8, [Uint32x2],
+    UVec3 => "vec3<u32>", 16, [Uint32x3],
+    UVec4 => "vec4<u32>", 16, [Uint32x4],
+    IVec2 => "vec2<i32>", 8, [Sint32x2],
+    IVec3 => "vec3<i32>", 16, [Sint32x3],
+    IVec4 => "vec4<i32>", 16, [Sint32x4],
+    Mat3 => "mat3x3<f32>", 16, [Float32x3, Float32x3, Float32x3],
+    Mat4 => "mat4x4<f32>", 16, [Float32x4, Float32x4, Float32x4, Float32x4],
+);
+
+/// WGSL type read from a vertex attribute of the given format
+fn vertex_format_wgsl(format: VertexFormat) -> &'static str {
+    match format {
+        VertexFormat::Float32 => "f32",
+        VertexFormat::Float32x2 => "vec2<f32>",
+        VertexFormat::Float32x3 => "vec3<f32>",
+        VertexFormat::Float32x4 => "vec4<f32>",
+        VertexFormat::Uint32 => "u32",
+        VertexFormat::Uint32x2 => "vec2<u32>",
+        VertexFormat::Uint32x3 => "vec3<u32>",
+        VertexFormat::Uint32x4 => "vec4<u32>",
+        VertexFormat::Sint32 => "i32",
+        VertexFormat::Sint32x2 => "vec2<i32>",
+        VertexFormat::Sint32x3 => "vec3<i32>",
+        VertexFormat::Sint32x4 => "vec4<i32>",
+        _ => panic!("Unsupported instance vertex format {format:?}"),
+    }
+}
+
+/// Generates the WGSL import for an [`InstanceWgsl`] type `I` extending the base instance `B`,
+/// as used by `#[derive(Instance)]`.
+///
+/// Field layout follows WGSL's natural alignment rules, which `encase` mirrors on the Rust side.
+/// `fields` holds the name, WGSL type and per-column vertex formats of each field,
+/// starting with the base field named `base_name`.
+/// `stream_prefix` prefixes the storage bindings declared for the remaining fields.
+///
+/// Under `VERTEX_INSTANCES`, [`InstanceWgsl::VERTEX_ACCESSOR`] takes the vertex structs of `B`
+/// followed by one for the remaining fields, whose locations follow those of `B`.
+pub fn instance_struct_wgsl<I: InstanceWgsl, B: InstanceWgsl>(
+    base_name: &str,
+    fields: &[(&str, &str, &[VertexFormat])],
+    stream_prefix: &str,
+) -> String {
+    let base_streams = B::streams().len();
+    let base_vertex_structs = B::vertex_structs();
+
+    let mut binding = 0;
+    let mut streams = String::new();
+    let mut loads = String::new();
+
+    let mut location =
+        INSTANCE_VERTEX_LOCATION + B::vertex_attributes(INSTANCE_VERTEX_LOCATION).len() as u32;
+    let mut vertex_fields = String::new();
+    let mut vertex_loads = String::new();
+
+    for (name, ty, formats) in fields {
+        if *name == base_name {
+            binding += base_streams;
+            loads.push_str(&format!(
+                "    instance.{name} = {}(index);\n",
+                B::SOA_ACCESSOR
+            ));
+
+            let base_args = (0..base_vertex_structs.len())
+                .map(|i| format!("base_{i}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            vertex_loads.push_str(&format!(
+                "    instance.{name} = {}({base_args});\n",
+                B::VERTEX_ACCESSOR
+            ));
+            continue;
+        }
+
+        streams.push_str(&format!(
+            "@group(2) @binding({binding})\nvar<storage> {stream_prefix}_{name}: array<{ty}>;\n"
+        ));
+        loads.push_str(&format!(
+            "    instance.{name} = {stream_prefix}_{name}[index];\n"
+        ));
+        binding += 1;
+
+        // Matrices take one location per column
+        if let [format] = formats {
+            vertex_fields.push_str(&format!(
+                "    @location({location}) {name}: {},\n",
+                vertex_format_wgsl(*format)
+            ));
+            vertex_loads.push_str(&format!("    instance.{name} = in.{name};\n"));
+            location += 1;
+        } else {
+            let mut columns = vec![];
+            for (i, format) in formats.iter().enumerate() {
+                vertex_fields.push_str(&format!(
+                    "    @location({location}) {name}_{i}: {},\n",
+                    vertex_format_wgsl(*format)
+                ));
+                columns.push(format!("in.{name}_{i}"));
+                location += 1;
+            }
+            vertex_loads.push_str(&format!(
+                "    instance.{name} = {ty}({});\n",
+                columns.join(", ")
+            ));
+        }
+    }
+
+    let mut vertex_args = base_vertex_structs
+        .iter()
+        .enumerate()
+        .map(|(i, base_struct)| format!("base_{i}: {base_struct}"))
+        .collect::<Vec<_>>();
+
+    // WGSL structs cannot be empty, so only declare one if there are fields past the base
+    let vertex_struct = if vertex_fields.is_empty() {
+        String::new()
+    } else {
+        let vertex_struct = I::vertex_structs()
+            .last()
+            .copied()
+            .expect("Instance declares no vertex struct");
+        vertex_args.push(format!("in: {vertex_struct}"));
+        format!("struct {vertex_struct} {{\n{vertex_fields}}};\n")
+    };
+
+    let fields = fields
+        .iter()
+        .map(|(name, ty, _)| format!("    {name}: {ty},\n"))
+        .collect::<String>();
+
+    format!(
+        "#import {base_import}
+#define_import_path {import_path}
+
+struct {struct_name} {{
+{fields}}};
+
+#ifdef NO_STORAGE_BUFFERS_SUPPORT
+struct {array_name} {{
+    instances: array<{struct_name}, {uniform_length}>,
+}};
+#else
+struct {array_name} {{
+    instances: array<{struct_name}>,
+}};
+#endif
+
+#ifdef VERTEX_INSTANCES
+{vertex_struct}
+fn {vertex_accessor}({vertex_args}) -> {struct_name} {{
+    var instance: {struct_name};
+{vertex_loads}    return instance;
+}}
+#endif
+
+#ifdef SOA_INSTANCES
+{streams}
+fn {accessor}(index: u32) -> {struct_name} {{
+    var instance: {struct_name};
+{loads}    return instance;
+}}
+#endif
+",
+        base_import = B::IMPORT_PATH,
+        import_path = I::IMPORT_PATH,
+        struct_name = I::STRUCT_NAME,
+        array_name = I::ARRAY_NAME,
+        accessor = I::SOA_ACCESSOR,
+        vertex_accessor = I::VERTEX_ACCESSOR,
+        vertex_args = vertex_args.join(", "),
+        uniform_length = I::UNIFORM_BUFFER_LENGTH,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::{ColorMeshInstance, MeshInstance};
+
+    use super::*;
+
+    fn color_instance_wgsl(fields: &[(&str, &str, &[VertexFormat])]) -> String {
+        instance_struct_wgsl::<ColorMeshInstance, MeshInstance>("base", fields, "color_instance")
+    }
+
+    #[test]
+    fn struct_follows_field_order() {
+        let wgsl = color_instance_wgsl(&[
+            ("base", "InstanceData", &[]),
+            ("color", Vec4::WGSL_TYPE, Vec4::VERTEX_FORMATS),
+            ("offset", Mat4::WGSL_TYPE, Mat4::VERTEX_FORMATS),
+        ]);
+
+        assert!(wgsl.starts_with(
+            "#import indirect_instancing::instance_struct\n\
+             #define_import_path indirect_instancing::color_instance_struct\n"
+        ));
+        assert!(wgsl.contains(
+            "struct ColorInstanceData {\n    \
+                 base: InstanceData,\n    \
+                 color: vec4<f32>,\n    \
+                 offset: mat4x4<f32>,\n\
+             };"
+        ));
+        assert!(wgsl.contains(&format!(
+            "instances: array<ColorInstanceData, {}>,",
+            ColorMeshInstance::UNIFORM_BUFFER_LENGTH
+        )));
+        assert!(wgsl.contains("instances: array<ColorInstanceData>,"));
+    }
+
+    #[test]
+    fn streams_follow_the_base_bindings() {
+        let wgsl = color_instance_wgsl(&[
+            ("base", "InstanceData", &[]),
+            ("color", Vec4::WGSL_TYPE, Vec4::VERTEX_FORMATS),
+            ("phase", f32::WGSL_TYPE, f32::VERTEX_FORMATS),
+        ]);
+
+        // The base occupies bindings 0..3 with its mesh index and two matrices
+        assert!(wgsl.contains(
+            "@group(2) @binding(3)\nvar<storage> color_instance_color: array<vec4<f32>>;\n\
+             @group(2) @binding(4)\nvar<storage> color_instance_phase: array<f32>;\n"
+        ));
+        assert!(wgsl.contains(
+            "fn load_color_instance(index: u32) -> ColorInstanceData {\n    \
+                 var instance: ColorInstanceData;\n    \
+                 instance.base = load_instance(index);\n    \
+                 instance.color = color_instance_color[index];\n    \
+                 instance.phase = color_instance_phase[index];\n    \
+                 return instance;\n\
+             }"
+        ));
+    }
+
+    #[test]
+    fn vertex_locations_follow_the_base_attributes() {
+        let wgsl = color_instance_wgsl(&[
+            ("base", "InstanceData", &[]),
+            ("color", Vec4::WGSL_TYPE, Vec4::VERTEX_FORMATS),
+            ("offset", Mat4::WGSL_TYPE, Mat4::VERTEX_FORMATS),
+        ]);
+
+        // The base occupies locations 5..14 with its mesh index and two matrices
+        assert_eq!(
+            MeshInstance::vertex_attributes(INSTANCE_VERTEX_LOCATION).len(),
+            9
+        );
+        assert!(wgsl.contains(
+            "struct ColorInstanceVertex {\n    \
+                 @location(14) color: vec4<f32>,\n    \
+                 @location(15) offset_0: vec4<f32>,\n    \
+                 @location(16) offset_1: vec4<f32>,\n    \
+                 @location(17) offset_2: vec4<f32>,\n    \
+                 @location(18) offset_3: vec4<f32>,\n\
+             };"
+        ));
+        assert!(wgsl.contains(
+            "fn color_instance_from_vertex(base_0: InstanceVertex, in: ColorInstanceVertex) \
+             -> ColorInstanceData {\n    \
+                 var instance: ColorInstanceData;\n    \
+                 instance.base = instance_from_vertex(base_0);\n    \
+                 instance.color = in.color;\n    \
+                 instance.offset = mat4x4<f32>(in.offset_0, in.offset_1, in.offset_2, in.offset_3);\n    \
+                 return instance;\n\
+             }"
+        ));
+    }
+
+    #[test]
+    fn base_only_instances_declare_no_vertex_struct() {
+        let wgsl = color_instance_wgsl(&[("base", "InstanceData", &[])]);
+
+        assert!(!wgsl.contains("struct ColorInstanceVertex"));
+        assert!(wgsl.contains(
+            "fn color_instance_from_vertex(base_0: InstanceVertex) -> ColorInstanceData {"
+        ));
+        assert!(!wgsl.contains("var<storage>"));
+    }
+}
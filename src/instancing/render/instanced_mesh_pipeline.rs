@@ -1,6 +1,6 @@
 use bevy::{
     pbr::{MeshPipeline, MeshPipelineKey},
-    prelude::{FromWorld, Shader, World, Resource},
+    prelude::{warn, FromWorld, Resource, Shader, World},
     render::{
         mesh::MeshVertexBufferLayout,
         render_resource::{
@@ -12,12 +12,35 @@ use bevy::{
     },
 };
 
-use crate::prelude::INSTANCED_MESH_SHADER_HANDLE;
+use crate::prelude::{InstanceComputeBackend, INSTANCED_MESH_SHADER_HANDLE};
+
+/// How prepared instance data is fed to the vertex shader.
+///
+/// Insert before adding `IndirectRenderingPlugin` to override the default.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Resource)]
+pub enum InstanceBufferMode {
+    /// Storage or uniform buffer at `@group(2) @binding(0)`, indexed by `instance_index`.
+    /// Uniform buffers are split into 16 KB chunks where storage buffers are unsupported,
+    /// and can't hold [`InstanceSlice`](crate::prelude::InstanceSlice)s.
+    #[default]
+    Binding,
+    /// Second vertex buffer stepped per instance, with attributes from [`Instance::vertex_attributes`].
+    /// Enables the `VERTEX_INSTANCES` shader def.
+    ///
+    /// [`Instance::vertex_attributes`]: crate::prelude::Instance::vertex_attributes
+    Vertex,
+    /// One storage buffer per field at `@group(2)`, with streams from [`Instance::streams`].
+    /// Enables the `SOA_INSTANCES` shader def, and falls back to `Binding` without storage buffers.
+    ///
+    /// [`Instance::streams`]: crate::prelude::Instance::streams
+    StructOfArrays,
+}
 
 /// Pipeline for rendering instanced meshes
 #[derive(Clone, Resource)]
 pub struct InstancedMeshPipeline {
     pub mesh_pipeline: MeshPipeline,
+    pub instance_buffer_mode: InstanceBufferMode,
     pub instance_buffer_binding_type: BufferBindingType,
     pub bind_group_layout: BindGroupLayout,
 }
@@ -30,8 +53,43 @@ impl FromWorld for InstancedMeshPipeline {
 
         let render_device = world.get_resource::<RenderDevice>().unwrap();
 
+        let instance_buffer_mode = world
+            .get_resource::<InstanceBufferMode>()
+            .map(|mode| *mode)
+            .unwrap_or_default();
+
         let instance_buffer_binding_type = render_device.get_supported_read_only_binding_type(1);
 
+        let instance_compute_backend = world
+            .get_resource::<InstanceComputeBackend>()
+            .map(|backend| *backend)
+            .unwrap_or_default();
+
+        let instance_buffer_mode = match (
+            instance_buffer_mode,
+            instance_buffer_binding_type,
+            instance_compute_backend,
+        ) {
+            // Instance slices are written in place, which chunked uniform buffers can't support
+            (
+                InstanceBufferMode::Binding | InstanceBufferMode::StructOfArrays,
+                BufferBindingType::Uniform,
+                InstanceComputeBackend::Cpu,
+            ) => {
+                warn!(
+                    "Storage buffers are unsupported, falling back to InstanceBufferMode::Vertex for CPU instance compute"
+                );
+                InstanceBufferMode::Vertex
+            }
+            (InstanceBufferMode::StructOfArrays, BufferBindingType::Uniform, _) => {
+                warn!(
+                    "Storage buffers are unsupported, falling back to InstanceBufferMode::Binding"
+                );
+                InstanceBufferMode::Binding
+            }
+            (instance_buffer_mode, _, _) => instance_buffer_mode,
+        };
+
         let bind_group_layout =
             render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
                 label: Some("instanced mesh bind group"),
@@ -49,6 +107,7 @@ impl FromWorld for InstancedMeshPipeline {
 
         InstancedMeshPipeline {
             mesh_pipeline: mesh_pipeline.clone(),
+            instance_buffer_mode,
             instance_buffer_binding_type,
             bind_group_layout,
         }
@@ -84,10 +143,29 @@ impl SpecializedMeshPipeline for InstancedMeshPipeline {
                 .push(String::from("NO_STORAGE_BUFFERS_SUPPORT"));
         }
 
-        descriptor.layout = Some(vec![
-            self.mesh_pipeline.view_layout.clone(),
-            self.bind_group_layout.clone(),
-        ]);
+        descriptor.layout = Some(match self.instance_buffer_mode {
+            InstanceBufferMode::Binding => vec![
+                self.mesh_pipeline.view_layout.clone(),
+                self.bind_group_layout.clone(),
+            ],
+            // Instances arrive through a vertex buffer or stream layout
+            // pushed by InstancedMaterialPipeline, which knows the instance type
+            InstanceBufferMode::Vertex | InstanceBufferMode::StructOfArrays => {
+                vec![self.mesh_pipeline.view_layout.clone()]
+            }
+        });
+
+        match self.instance_buffer_mode {
+            InstanceBufferMode::Binding => (),
+            InstanceBufferMode::Vertex => descriptor
+                .vertex
+                .shader_defs
+                .push(String::from("VERTEX_INSTANCES")),
+            InstanceBufferMode::StructOfArrays => descriptor
+                .vertex
+                .shader_defs
+                .push(String::from("SOA_INSTANCES")),
+        }
 
         descriptor.vertex.shader = INSTANCED_MESH_SHADER_HANDLE.typed::<Shader>();
 
@@ -1,3 +1,4 @@
 pub mod instance;
 pub mod instanced_mesh_pipeline;
-
+pub mod persistent_buffer;
+pub mod quantize;
@@ -0,0 +1,100 @@
+use std::ops::Range;
+
+use bevy::render::{
+    render_resource::{Buffer, BufferDescriptor, BufferUsages},
+    renderer::{RenderDevice, RenderQueue},
+};
+
+/// Growable GPU byte buffer that can be partially rewritten,
+/// leaving regions written on the GPU (i.e. by compute) intact between uploads.
+///
+/// Unlike [`BufferVec`](bevy::render::render_resource::BufferVec),
+/// the GPU allocation is only replaced when it is too small.
+pub struct PersistentBuffer {
+    usage: BufferUsages,
+    values: Vec<u8>,
+    buffer: Option<Buffer>,
+    capacity: usize,
+}
+
+impl PersistentBuffer {
+    pub fn new(usage: BufferUsages) -> Self {
+        PersistentBuffer {
+            usage,
+            values: vec![],
+            buffer: None,
+            capacity: 0,
+        }
+    }
+
+    pub fn buffer(&self) -> Option<&Buffer> {
+        self.buffer.as_ref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        self.values.extend_from_slice(bytes);
+    }
+
+    /// Ensure the GPU buffer can hold the current contents,
+    /// returning `true` if a new buffer had to be created
+    pub fn reserve(&mut self, render_device: &RenderDevice) -> bool {
+        if self.buffer.is_some() && self.values.len() <= self.capacity {
+            return false;
+        }
+
+        self.capacity = self.values.len().next_power_of_two();
+        self.buffer = Some(render_device.create_buffer(&BufferDescriptor {
+            label: Some("persistent buffer"),
+            size: self.capacity as u64,
+            usage: BufferUsages::COPY_DST | self.usage,
+            mapped_at_creation: false,
+        }));
+
+        true
+    }
+
+    /// Upload the contents to the GPU, skipping any byte ranges in `preserve`.
+    ///
+    /// `preserve` must be sorted and non-overlapping.
+    /// Returns `true` if the buffer was reallocated, in which case everything is uploaded.
+    pub fn write_buffer(
+        &mut self,
+        render_device: &RenderDevice,
+        render_queue: &RenderQueue,
+        preserve: &[Range<usize>],
+    ) -> bool {
+        if self.values.is_empty() {
+            return false;
+        }
+
+        let reallocated = self.reserve(render_device);
+        let buffer = self.buffer.as_ref().unwrap();
+
+        let mut offset = 0;
+        let preserve = if reallocated { &[] } else { preserve };
+        for range in preserve
+            .iter()
+            .cloned()
+            .chain(std::iter::once(self.values.len()..self.values.len()))
+        {
+            if range.start > offset {
+                render_queue.write_buffer(buffer, offset as u64, &self.values[offset..range.start]);
+            }
+            offset = offset.max(range.end);
+        }
+
+        reallocated
+    }
+}
@@ -0,0 +1,167 @@
+//! CPU-side encoders matching the decoders in the `indirect_instancing::quantize` WGSL import
+
+use bevy::math::{Vec2, Vec4};
+
+/// Converts to IEEE 754 half precision, rounding to nearest
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+
+    // Infinity and NaN
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x0200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+
+    // Overflow to infinity
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    // Subnormal or zero
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+
+        let mantissa = mantissa | 0x0080_0000;
+        let shift = (14 - exponent) as u32;
+        let round = (mantissa >> (shift - 1)) & 1;
+        return sign | ((mantissa >> shift) + round) as u16;
+    }
+
+    // A rounding carry out of the mantissa correctly increments the exponent
+    let half = sign | ((exponent as u16) << 10) | (mantissa >> 13) as u16;
+    half + ((mantissa >> 12) & 1) as u16
+}
+
+/// Packs two halves into a `u32`, as read by WGSL's `unpack2x16float`
+pub fn pack_half2(value: Vec2) -> u32 {
+    f32_to_f16(value.x) as u32 | (f32_to_f16(value.y) as u32) << 16
+}
+
+/// Packs two `[-1, 1]` values into a `u32`, as read by WGSL's `unpack2x16snorm`
+pub fn pack_snorm16x2(value: Vec2) -> u32 {
+    let snorm = |v: f32| (v.clamp(-1.0, 1.0) * 32767.0).round() as i16 as u16 as u32;
+    snorm(value.x) | snorm(value.y) << 16
+}
+
+/// Packs four `[0, 1]` values into a `u32`, as read by WGSL's `unpack4x8unorm`
+pub fn pack_unorm8x4(value: Vec4) -> u32 {
+    let unorm = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u32;
+    unorm(value.x) | unorm(value.y) << 8 | unorm(value.z) << 16 | unorm(value.w) << 24
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference decoder, matching WGSL's `unpack2x16float`
+    fn f16_to_f32(half: u16) -> f32 {
+        let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+        let exponent = ((half >> 10) & 0x1f) as i32;
+        let mantissa = (half & 0x03ff) as f32;
+
+        match exponent {
+            0 => sign * mantissa * 2f32.powi(-24),
+            0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+            0x1f => f32::NAN,
+            _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+        }
+    }
+
+    #[test]
+    fn f16_normals() {
+        assert_eq!(f32_to_f16(1.0), 0x3c00);
+        assert_eq!(f32_to_f16(-2.0), 0xc000);
+        assert_eq!(f32_to_f16(0.5), 0x3800);
+        assert_eq!(f32_to_f16(0.1), 0x2e66);
+        assert_eq!(f32_to_f16(65504.0), 0x7bff);
+        assert_eq!(f32_to_f16(2f32.powi(-14)), 0x0400);
+    }
+
+    #[test]
+    fn f16_zero() {
+        assert_eq!(f32_to_f16(0.0), 0x0000);
+        assert_eq!(f32_to_f16(-0.0), 0x8000);
+    }
+
+    #[test]
+    fn f16_round_trips_every_half() {
+        for half in 0..=u16::MAX {
+            let value = f16_to_f32(half);
+            if value.is_nan() {
+                continue;
+            }
+
+            assert_eq!(f32_to_f16(value), half, "{half:#06x} ({value})");
+        }
+    }
+
+    #[test]
+    fn f16_rounds_to_nearest() {
+        // Just above and below the midpoint between 1.0 and the next half
+        let ulp = 2f32.powi(-10);
+        assert_eq!(f32_to_f16(1.0 + ulp * 0.49), 0x3c00);
+        assert_eq!(f32_to_f16(1.0 + ulp * 0.51), 0x3c01);
+
+        // Rounding carries from the mantissa into the exponent
+        assert_eq!(f32_to_f16(2.0 - ulp * 0.1), 0x4000);
+
+        for i in 0..10000 {
+            let value = (i as f32 * 0.731).sin() * 1000.0;
+            let half = f16_to_f32(f32_to_f16(value));
+            let error = (half - value).abs();
+            assert!(error <= value.abs() * 2f32.powi(-11), "{value} -> {half}");
+        }
+    }
+
+    #[test]
+    fn f16_subnormals() {
+        let min = 2f32.powi(-24);
+        assert_eq!(f32_to_f16(min), 0x0001);
+        assert_eq!(f32_to_f16(-min), 0x8001);
+        assert_eq!(f32_to_f16(min * 1023.0), 0x03ff);
+        assert_eq!(f32_to_f16(min * 2.6), 0x0003);
+
+        // Largest subnormal rounding up to the smallest normal
+        assert_eq!(f32_to_f16(min * 1023.9), 0x0400);
+
+        // Too small to represent
+        assert_eq!(f32_to_f16(min * 0.4), 0x0000);
+        assert_eq!(f32_to_f16(-min * 0.4), 0x8000);
+        assert_eq!(f32_to_f16(f32::MIN_POSITIVE), 0x0000);
+    }
+
+    #[test]
+    fn f16_overflow() {
+        assert_eq!(f32_to_f16(65520.0), 0x7c00);
+        assert_eq!(f32_to_f16(1.0e6), 0x7c00);
+        assert_eq!(f32_to_f16(-1.0e6), 0xfc00);
+        assert_eq!(f32_to_f16(f32::MAX), 0x7c00);
+        assert_eq!(f32_to_f16(f32::INFINITY), 0x7c00);
+        assert_eq!(f32_to_f16(f32::NEG_INFINITY), 0xfc00);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+    }
+
+    #[test]
+    fn pack_half2_order() {
+        assert_eq!(pack_half2(Vec2::new(1.0, -2.0)), 0xc000_3c00);
+    }
+
+    #[test]
+    fn pack_snorm16x2_clamps() {
+        assert_eq!(pack_snorm16x2(Vec2::new(1.0, -1.0)), 0x8001_7fff);
+        assert_eq!(pack_snorm16x2(Vec2::new(2.0, -2.0)), 0x8001_7fff);
+        assert_eq!(pack_snorm16x2(Vec2::new(0.0, 0.5)), 0x4000_0000);
+    }
+
+    #[test]
+    fn pack_unorm8x4_clamps() {
+        assert_eq!(pack_unorm8x4(Vec4::new(0.0, 0.5, 1.0, 2.0)), 0xffff_8000);
+        assert_eq!(pack_unorm8x4(Vec4::new(-1.0, 0.0, 0.0, 1.0)), 0xff00_0000);
+    }
+}
@@ -0,0 +1,68 @@
+#define_import_path indirect_instancing::affine_instance_struct
+
+struct AffineInstanceData {
+    transform: array<vec4<f32>, 3>,
+    mesh: u32,
+};
+
+#ifdef NO_STORAGE_BUFFERS_SUPPORT
+struct AffineInstances {
+    instances: array<AffineInstanceData, 256>,
+};
+#else
+struct AffineInstances {
+    instances: array<AffineInstanceData>,
+};
+#endif
+
+fn affine_instance_transform(instance: AffineInstanceData) -> mat4x4<f32> {
+    let rows = instance.transform;
+    return transpose(mat4x4<f32>(
+        rows[0],
+        rows[1],
+        rows[2],
+        vec4<f32>(0.0, 0.0, 0.0, 1.0),
+    ));
+}
+
+// Inverse transpose of the upper 3x3, built from the cofactors of its columns
+fn affine_instance_normal_matrix(instance: AffineInstanceData) -> mat3x3<f32> {
+    let transform = affine_instance_transform(instance);
+    let x = transform[0].xyz;
+    let y = transform[1].xyz;
+    let z = transform[2].xyz;
+    let det = dot(x, cross(y, z));
+    return mat3x3<f32>(cross(y, z), cross(z, x), cross(x, y)) * (1.0 / det);
+}
+
+#ifdef VERTEX_INSTANCES
+struct AffineInstanceVertex {
+    @location(5) transform_0: vec4<f32>,
+    @location(6) transform_1: vec4<f32>,
+    @location(7) transform_2: vec4<f32>,
+    @location(8) mesh: u32,
+};
+
+fn affine_instance_from_vertex(in: AffineInstanceVertex) -> AffineInstanceData {
+    var instance: AffineInstanceData;
+    instance.transform[0] = in.transform_0;
+    instance.transform[1] = in.transform_1;
+    instance.transform[2] = in.transform_2;
+    instance.mesh = in.mesh;
+    return instance;
+}
+#endif
+
+#ifdef SOA_INSTANCES
+@group(2) @binding(0)
+var<storage> affine_instance_transform_rows: array<array<vec4<f32>, 3>>;
+@group(2) @binding(1)
+var<storage> affine_instance_mesh: array<u32>;
+
+fn load_affine_instance(index: u32) -> AffineInstanceData {
+    var instance: AffineInstanceData;
+    instance.transform = affine_instance_transform_rows[index];
+    instance.mesh = affine_instance_mesh[index];
+    return instance;
+}
+#endif
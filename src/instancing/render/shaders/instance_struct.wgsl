@@ -19,3 +19,52 @@ struct Instances {
 };
 #endif
 
+
+#ifdef VERTEX_INSTANCES
+struct InstanceVertex {
+    @location(5) mesh: u32,
+    @location(6) transform_0: vec4<f32>,
+    @location(7) transform_1: vec4<f32>,
+    @location(8) transform_2: vec4<f32>,
+    @location(9) transform_3: vec4<f32>,
+    @location(10) inverse_transpose_model_0: vec4<f32>,
+    @location(11) inverse_transpose_model_1: vec4<f32>,
+    @location(12) inverse_transpose_model_2: vec4<f32>,
+    @location(13) inverse_transpose_model_3: vec4<f32>,
+};
+
+fn instance_from_vertex(in: InstanceVertex) -> InstanceData {
+    var instance: InstanceData;
+    instance.mesh = in.mesh;
+    instance.transform = mat4x4<f32>(
+        in.transform_0,
+        in.transform_1,
+        in.transform_2,
+        in.transform_3,
+    );
+    instance.inverse_transpose_model = mat4x4<f32>(
+        in.inverse_transpose_model_0,
+        in.inverse_transpose_model_1,
+        in.inverse_transpose_model_2,
+        in.inverse_transpose_model_3,
+    );
+    return instance;
+}
+#endif
+
+#ifdef SOA_INSTANCES
+@group(2) @binding(0)
+var<storage> instance_mesh: array<u32>;
+@group(2) @binding(1)
+var<storage> instance_transform: array<mat4x4<f32>>;
+@group(2) @binding(2)
+var<storage> instance_inverse_transpose_model: array<mat4x4<f32>>;
+
+fn load_instance(index: u32) -> InstanceData {
+    var instance: InstanceData;
+    instance.mesh = instance_mesh[index];
+    instance.transform = instance_transform[index];
+    instance.inverse_transpose_model = instance_inverse_transpose_model[index];
+    return instance;
+}
+#endif
@@ -1,6 +1,8 @@
 #import bevy_pbr::mesh_view_bindings
 #import indirect_instancing::instance_struct
 
+#ifndef VERTEX_INSTANCES
+#ifndef SOA_INSTANCES
 #ifdef NO_STORAGE_BUFFERS_SUPPORT
 @group(2)
 @binding(0)
@@ -10,6 +12,8 @@ var<uniform> instances: Instances;
 @binding(0)
 var<storage> instances: Instances;
 #endif
+#endif
+#endif
 
 struct Vertex {
     @builtin(instance_index) instance: u32,
@@ -26,8 +30,17 @@ struct VertexOutput {
 };
 
 @vertex
+#ifdef VERTEX_INSTANCES
+fn vertex(in: Vertex, instance_in: InstanceVertex) -> VertexOutput {
+    let instance = instance_from_vertex(instance_in);
+#else
 fn vertex(in: Vertex) -> VertexOutput {
+#ifdef SOA_INSTANCES
+    let instance = load_instance(in.instance);
+#else
     let instance = instances.instances[in.instance];
+#endif
+#endif
 
     var out: VertexOutput;
     out.world_position = instance.transform * vec4<f32>(in.vertex, 1.0);
@@ -0,0 +1,34 @@
+#define_import_path indirect_instancing::quantize
+
+fn unpack_half4(packed: vec2<u32>) -> vec4<f32> {
+    return vec4<f32>(unpack2x16float(packed.x), unpack2x16float(packed.y));
+}
+
+fn unpack_snorm16x4(packed: vec2<u32>) -> vec4<f32> {
+    return vec4<f32>(unpack2x16snorm(packed.x), unpack2x16snorm(packed.y));
+}
+
+fn unpack_unorm8x4(packed: u32) -> vec4<f32> {
+    return unpack4x8unorm(packed);
+}
+
+// Rotation matrix from an xyzw unit quaternion
+fn quat_to_mat3(q: vec4<f32>) -> mat3x3<f32> {
+    let q2 = q.xyz + q.xyz;
+
+    let xx = q.x * q2.x;
+    let xy = q.x * q2.y;
+    let xz = q.x * q2.z;
+    let yy = q.y * q2.y;
+    let yz = q.y * q2.z;
+    let zz = q.z * q2.z;
+    let wx = q.w * q2.x;
+    let wy = q.w * q2.y;
+    let wz = q.w * q2.z;
+
+    return mat3x3<f32>(
+        vec3<f32>(1.0 - (yy + zz), xy + wz, xz - wy),
+        vec3<f32>(xy - wz, 1.0 - (xx + zz), yz + wx),
+        vec3<f32>(xz + wy, yz - wx, 1.0 - (xx + yy)),
+    );
+}
@@ -0,0 +1,87 @@
+#import indirect_instancing::quantize
+#define_import_path indirect_instancing::quantized_instance_struct
+
+struct QuantizedInstanceData {
+    translation_scale: vec2<u32>,
+    rotation: vec2<u32>,
+    mesh: u32,
+    @size(12)
+    color: u32,
+};
+
+#ifdef NO_STORAGE_BUFFERS_SUPPORT
+struct QuantizedInstances {
+    instances: array<QuantizedInstanceData, 512>,
+};
+#else
+struct QuantizedInstances {
+    instances: array<QuantizedInstanceData>,
+};
+#endif
+
+fn quantized_instance_scale(instance: QuantizedInstanceData) -> f32 {
+    return unpack_half4(instance.translation_scale).w;
+}
+
+fn quantized_instance_rotation(instance: QuantizedInstanceData) -> mat3x3<f32> {
+    return quat_to_mat3(normalize(unpack_snorm16x4(instance.rotation)));
+}
+
+fn quantized_instance_color(instance: QuantizedInstanceData) -> vec4<f32> {
+    return unpack_unorm8x4(instance.color);
+}
+
+// origin must match the InstanceOrigin the instances were extracted with
+fn quantized_instance_transform(instance: QuantizedInstanceData, origin: vec3<f32>) -> mat4x4<f32> {
+    let translation_scale = unpack_half4(instance.translation_scale);
+    let rotation = quantized_instance_rotation(instance) * translation_scale.w;
+    return mat4x4<f32>(
+        vec4<f32>(rotation[0], 0.0),
+        vec4<f32>(rotation[1], 0.0),
+        vec4<f32>(rotation[2], 0.0),
+        vec4<f32>(origin + translation_scale.xyz, 1.0),
+    );
+}
+
+// Uniform scale leaves the rotation as the normal matrix, up to normalization
+fn quantized_instance_normal_matrix(instance: QuantizedInstanceData) -> mat3x3<f32> {
+    return quantized_instance_rotation(instance);
+}
+
+#ifdef VERTEX_INSTANCES
+struct QuantizedInstanceVertex {
+    @location(5) translation_scale: vec2<u32>,
+    @location(6) rotation: vec2<u32>,
+    @location(7) mesh: u32,
+    @location(8) color: u32,
+};
+
+fn quantized_instance_from_vertex(in: QuantizedInstanceVertex) -> QuantizedInstanceData {
+    var instance: QuantizedInstanceData;
+    instance.translation_scale = in.translation_scale;
+    instance.rotation = in.rotation;
+    instance.mesh = in.mesh;
+    instance.color = in.color;
+    return instance;
+}
+#endif
+
+#ifdef SOA_INSTANCES
+@group(2) @binding(0)
+var<storage> quantized_instance_translation_scale: array<vec2<u32>>;
+@group(2) @binding(1)
+var<storage> quantized_instance_rotation_packed: array<vec2<u32>>;
+@group(2) @binding(2)
+var<storage> quantized_instance_mesh: array<u32>;
+@group(2) @binding(3)
+var<storage> quantized_instance_color_packed: array<u32>;
+
+fn load_quantized_instance(index: u32) -> QuantizedInstanceData {
+    var instance: QuantizedInstanceData;
+    instance.translation_scale = quantized_instance_translation_scale[index];
+    instance.rotation = quantized_instance_rotation_packed[index];
+    instance.mesh = quantized_instance_mesh[index];
+    instance.color = quantized_instance_color_packed[index];
+    return instance;
+}
+#endif
@@ -0,0 +1,85 @@
+#import indirect_instancing::quantize
+#define_import_path indirect_instancing::trs_instance_struct
+
+struct TrsInstanceData {
+    translation: vec3<f32>,
+    mesh: u32,
+    rotation: vec4<f32>,
+    scale: vec3<f32>,
+};
+
+#ifdef NO_STORAGE_BUFFERS_SUPPORT
+struct TrsInstances {
+    instances: array<TrsInstanceData, 341>,
+};
+#else
+struct TrsInstances {
+    instances: array<TrsInstanceData>,
+};
+#endif
+
+fn trs_instance_rotation(instance: TrsInstanceData) -> mat3x3<f32> {
+    return quat_to_mat3(instance.rotation);
+}
+
+fn trs_instance_transform(instance: TrsInstanceData) -> mat4x4<f32> {
+    let rotation = trs_instance_rotation(instance);
+    let scale = instance.scale;
+    return mat4x4<f32>(
+        vec4<f32>(rotation[0] * scale.x, 0.0),
+        vec4<f32>(rotation[1] * scale.y, 0.0),
+        vec4<f32>(rotation[2] * scale.z, 0.0),
+        vec4<f32>(instance.translation, 1.0),
+    );
+}
+
+// Inverse transpose of rotation * scale is rotation * inverse scale.
+// Collapsed axes get a zero inverse rather than infinity, so normals stay finite
+fn trs_instance_normal_matrix(instance: TrsInstanceData) -> mat3x3<f32> {
+    let rotation = trs_instance_rotation(instance);
+    let scale = instance.scale;
+    let inverse_scale = select(1.0 / scale, vec3<f32>(0.0), scale == vec3<f32>(0.0));
+    return mat3x3<f32>(
+        rotation[0] * inverse_scale.x,
+        rotation[1] * inverse_scale.y,
+        rotation[2] * inverse_scale.z,
+    );
+}
+
+#ifdef VERTEX_INSTANCES
+struct TrsInstanceVertex {
+    @location(5) translation: vec3<f32>,
+    @location(6) mesh: u32,
+    @location(7) rotation: vec4<f32>,
+    @location(8) scale: vec3<f32>,
+};
+
+fn trs_instance_from_vertex(in: TrsInstanceVertex) -> TrsInstanceData {
+    var instance: TrsInstanceData;
+    instance.translation = in.translation;
+    instance.mesh = in.mesh;
+    instance.rotation = in.rotation;
+    instance.scale = in.scale;
+    return instance;
+}
+#endif
+
+#ifdef SOA_INSTANCES
+@group(2) @binding(0)
+var<storage> trs_instance_translation: array<vec3<f32>>;
+@group(2) @binding(1)
+var<storage> trs_instance_mesh: array<u32>;
+@group(2) @binding(2)
+var<storage> trs_instance_rotation_quat: array<vec4<f32>>;
+@group(2) @binding(3)
+var<storage> trs_instance_scale: array<vec3<f32>>;
+
+fn load_trs_instance(index: u32) -> TrsInstanceData {
+    var instance: TrsInstanceData;
+    instance.translation = trs_instance_translation[index];
+    instance.mesh = trs_instance_mesh[index];
+    instance.rotation = trs_instance_rotation_quat[index];
+    instance.scale = trs_instance_scale[index];
+    return instance;
+}
+#endif
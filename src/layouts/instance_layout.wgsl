@@ -0,0 +1,206 @@
+#import indirect_instancing::instance_struct
+#import indirect_instancing::color_instance_struct
+
+struct InstanceLayoutUniform {
+    transform: mat4x4<f32>,
+    inverse_transform: mat4x4<f32>,
+    instance_transform: mat4x4<f32>,
+    inverse_instance_transform: mat4x4<f32>,
+    color: vec4<f32>,
+    params: vec4<f32>,
+    counts: vec3<u32>,
+    shape: u32,
+    seed: u32,
+    point_count: u32,
+    points: array<vec4<f32>, 64>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> in_uniform: InstanceLayoutUniform;
+
+#ifdef SOA_INSTANCES
+@group(1)
+@binding(0)
+var<storage, read_write> out_transforms: array<mat4x4<f32>>;
+
+@group(1)
+@binding(1)
+var<storage, read_write> out_inverse_transpose_models: array<mat4x4<f32>>;
+
+#ifdef LAYOUT_COLOR_INSTANCE
+@group(1)
+@binding(2)
+var<storage, read_write> out_colors: array<vec4<f32>>;
+#endif
+#else
+#ifdef LAYOUT_COLOR_INSTANCE
+@group(1)
+@binding(0)
+var<storage, read_write> out_instances: ColorInstances;
+#else
+@group(1)
+@binding(0)
+var<storage, read_write> out_instances: Instances;
+#endif
+#endif
+
+let SHAPE_GRID: u32 = 0u;
+let SHAPE_RING: u32 = 1u;
+let SHAPE_SPIRAL: u32 = 2u;
+let SHAPE_RANDOM_BOX: u32 = 3u;
+let SHAPE_RANDOM_SPHERE: u32 = 4u;
+let SHAPE_RANDOM_DISK: u32 = 5u;
+let SHAPE_POLYLINE: u32 = 6u;
+
+let TAU: f32 = 6.28318530718;
+
+fn hash(n: u32) -> f32 {
+    var x = n * 747796405u + 2891336453u;
+    x = ((x >> ((x >> 28u) + 4u)) ^ x) * 277803737u;
+    x = (x >> 22u) ^ x;
+    return f32(x) / 4294967295.0;
+}
+
+fn random(index: u32, offset: u32) -> f32 {
+    return hash(index * 4u + in_uniform.seed * 2654435761u + offset);
+}
+
+fn instance_count() -> u32 {
+#ifdef SOA_INSTANCES
+    return arrayLength(&out_transforms);
+#else
+    return arrayLength(&out_instances.instances);
+#endif
+}
+
+// Point a fraction `t` of the way along the polyline by distance
+fn polyline_position(t: f32) -> vec3<f32> {
+    let point_count = in_uniform.point_count;
+    if (point_count == 0u) {
+        return vec3<f32>(0.0);
+    }
+
+    var total_length = 0.0;
+    for (var i = 1u; i < point_count; i = i + 1u) {
+        total_length += distance(in_uniform.points[i - 1u].xyz, in_uniform.points[i].xyz);
+    }
+
+    var remaining = total_length * t;
+    var start = in_uniform.points[0].xyz;
+    for (var i = 1u; i < point_count; i = i + 1u) {
+        let end = in_uniform.points[i].xyz;
+        let segment_length = distance(start, end);
+        if (remaining <= segment_length || i == point_count - 1u) {
+            var f = 0.0;
+            if (segment_length > 0.0) {
+                f = clamp(remaining / segment_length, 0.0, 1.0);
+            }
+            return mix(start, end, f);
+        }
+
+        remaining -= segment_length;
+        start = end;
+    }
+
+    return start;
+}
+
+// Position of an instance, with `w` set to zero if it is hidden.
+// Mirrors `InstanceLayoutShape::position`.
+fn layout_position(index: u32, count: u32) -> vec4<f32> {
+    let params = in_uniform.params;
+    let t = f32(index) / f32(max(count, 2u) - 1u);
+
+    if (in_uniform.shape == SHAPE_GRID) {
+        let counts = in_uniform.counts;
+        let cell = vec3<u32>(
+            index % counts.x,
+            (index / counts.x) % counts.y,
+            index / (counts.x * counts.y),
+        );
+        if (cell.z >= counts.z) {
+            return vec4<f32>(0.0);
+        }
+
+        let offset = vec3<f32>(counts - vec3<u32>(1u)) * 0.5;
+        return vec4<f32>((vec3<f32>(cell) - offset) * params.xyz, 1.0);
+    } else if (in_uniform.shape == SHAPE_RING) {
+        let angle = TAU * f32(index) / f32(max(count, 1u));
+        return vec4<f32>(vec3<f32>(cos(angle), 0.0, sin(angle)) * params.x, 1.0);
+    } else if (in_uniform.shape == SHAPE_SPIRAL) {
+        let angle = t * params.z * TAU;
+        let radius = params.x + (params.y - params.x) * t;
+        return vec4<f32>(cos(angle) * radius, t * params.w, sin(angle) * radius, 1.0);
+    } else if (in_uniform.shape == SHAPE_RANDOM_BOX) {
+        let r = vec3<f32>(random(index, 0u), random(index, 1u), random(index, 2u));
+        return vec4<f32>((r * 2.0 - 1.0) * params.xyz, 1.0);
+    } else if (in_uniform.shape == SHAPE_RANDOM_SPHERE) {
+        let z = random(index, 0u) * 2.0 - 1.0;
+        let phi = random(index, 1u) * TAU;
+        let r = sqrt(max(1.0 - z * z, 0.0));
+        let radius = params.x * pow(random(index, 2u), 1.0 / 3.0);
+        return vec4<f32>(vec3<f32>(r * cos(phi), r * sin(phi), z) * radius, 1.0);
+    } else if (in_uniform.shape == SHAPE_RANDOM_DISK) {
+        let radius = params.x * sqrt(random(index, 0u));
+        let angle = random(index, 1u) * TAU;
+        return vec4<f32>(vec3<f32>(cos(angle), 0.0, sin(angle)) * radius, 1.0);
+    } else if (in_uniform.shape == SHAPE_POLYLINE) {
+        return vec4<f32>(polyline_position(t), 1.0);
+    }
+
+    return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+}
+
+fn translation(p: vec3<f32>) -> mat4x4<f32> {
+    return mat4x4<f32>(
+        vec4<f32>(1.0, 0.0, 0.0, 0.0),
+        vec4<f32>(0.0, 1.0, 0.0, 0.0),
+        vec4<f32>(0.0, 0.0, 1.0, 0.0),
+        vec4<f32>(p, 1.0),
+    );
+}
+
+@compute
+@workgroup_size(64)
+fn instances(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let max_instance = instance_count();
+
+    let instance_idx = invocation_id.x;
+    if (instance_idx >= max_instance) {
+        return;
+    }
+
+    let position = layout_position(instance_idx, max_instance);
+
+    // Hidden instances collapse to a point
+    var transform = mat4x4<f32>(vec4<f32>(0.0), vec4<f32>(0.0), vec4<f32>(0.0), vec4<f32>(0.0));
+    var inverse_transpose_model = transform;
+    if (position.w > 0.0) {
+        transform = in_uniform.transform
+            * translation(position.xyz)
+            * in_uniform.instance_transform;
+        inverse_transpose_model = transpose(
+            in_uniform.inverse_instance_transform
+                * translation(-position.xyz)
+                * in_uniform.inverse_transform
+        );
+    }
+
+#ifdef SOA_INSTANCES
+    out_transforms[instance_idx] = transform;
+    out_inverse_transpose_models[instance_idx] = inverse_transpose_model;
+#ifdef LAYOUT_COLOR_INSTANCE
+    out_colors[instance_idx] = in_uniform.color;
+#endif
+#else
+#ifdef LAYOUT_COLOR_INSTANCE
+    out_instances.instances[instance_idx].base.transform = transform;
+    out_instances.instances[instance_idx].base.inverse_transpose_model = inverse_transpose_model;
+    out_instances.instances[instance_idx].color = in_uniform.color;
+#else
+    out_instances.instances[instance_idx].transform = transform;
+    out_instances.instances[instance_idx].inverse_transpose_model = inverse_transpose_model;
+#endif
+#endif
+}
@@ -0,0 +1,334 @@
+pub mod plugin;
+pub mod shape;
+
+use std::marker::PhantomData;
+
+use bevy::{
+    ecs::system::lifetimeless::Read,
+    math::{Mat4, UVec3, Vec4},
+    prelude::{default, Color, Component, Transform},
+    render::{
+        extract_component::ExtractComponent,
+        render_resource::{AsBindGroup, ComputePipelineDescriptor, ShaderRef, ShaderType},
+    },
+};
+
+use crate::prelude::{
+    BulkInstance, ColorMeshInstance, Instance, InstanceColor, InstanceCompute,
+    InstanceComputePipeline, InstanceLayoutShape, MeshInstance, INSTANCE_LAYOUT_SHADER_HANDLE,
+    MAX_LAYOUT_POINTS,
+};
+
+/// Instance types the built-in layout shader can write
+pub trait LayoutInstance: BulkInstance {
+    /// Shader def selecting this type's instance struct in `instance_layout.wgsl`
+    const SHADER_DEF: &'static str;
+
+    /// Per-instance data written alongside each transform
+    fn layout_data(color: Color) -> Self::Data;
+}
+
+impl LayoutInstance for MeshInstance {
+    const SHADER_DEF: &'static str = "LAYOUT_MESH_INSTANCE";
+
+    fn layout_data(_: Color) -> Self::Data {}
+}
+
+impl LayoutInstance for ColorMeshInstance {
+    const SHADER_DEF: &'static str = "LAYOUT_COLOR_INSTANCE";
+
+    fn layout_data(color: Color) -> Self::Data {
+        InstanceColor(color).into()
+    }
+}
+
+/// Layout parameters, laid out to match `InstanceLayoutUniform` in `instance_layout.wgsl`
+#[derive(Debug, Copy, Clone, ShaderType)]
+pub struct InstanceLayoutUniform {
+    pub transform: Mat4,
+    pub inverse_transform: Mat4,
+    pub instance_transform: Mat4,
+    pub inverse_instance_transform: Mat4,
+    pub color: Vec4,
+    /// Shape parameters, see [`InstanceLayoutShape::shader_params`]
+    pub params: Vec4,
+    pub counts: UVec3,
+    pub shape: u32,
+    pub seed: u32,
+    pub point_count: u32,
+    pub points: [Vec4; MAX_LAYOUT_POINTS],
+}
+
+/// [`InstanceCompute`] placing the instances of its slice in a procedural [`InstanceLayoutShape`],
+/// configured entirely from Rust.
+///
+/// Layouts only depend on their settings and the slice's instance count,
+/// so pairing them with [`InstanceComputeRunMode::OnChange`](crate::prelude::InstanceComputeRunMode::OnChange)
+/// avoids recomputing them every frame.
+///
+/// Requires [`InstanceLayoutPlugin`](crate::prelude::InstanceLayoutPlugin) for `I`.
+#[derive(Debug, Component, AsBindGroup)]
+#[uniform(0, InstanceLayoutUniform)]
+pub struct InstanceLayout<I: LayoutInstance> {
+    pub shape: InstanceLayoutShape,
+    /// World-space placement of the whole layout
+    pub transform: Transform,
+    /// Applied to each instance before it is placed, i.e. to rotate or scale every instance
+    pub instance_transform: Transform,
+    /// Written to instances that carry a color
+    pub color: Color,
+    marker: PhantomData<I>,
+}
+
+// Implemented by hand so that `I` needn't be `Clone`
+impl<I: LayoutInstance> Clone for InstanceLayout<I> {
+    fn clone(&self) -> Self {
+        Self {
+            shape: self.shape.clone(),
+            transform: self.transform,
+            instance_transform: self.instance_transform,
+            color: self.color,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<I: LayoutInstance> Default for InstanceLayout<I> {
+    fn default() -> Self {
+        Self {
+            shape: default(),
+            transform: default(),
+            instance_transform: default(),
+            color: Color::WHITE,
+            marker: default(),
+        }
+    }
+}
+
+impl<I: LayoutInstance> InstanceLayout<I> {
+    pub fn new(shape: InstanceLayoutShape) -> Self {
+        Self { shape, ..default() }
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn with_instance_transform(mut self, instance_transform: Transform) -> Self {
+        self.instance_transform = instance_transform;
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// World-space transform of instance `index` out of `count`,
+    /// or a zero matrix if it is hidden
+    pub fn instance_matrix(&self, index: u32, count: u32) -> Mat4 {
+        match self.shape.position(index, count) {
+            Some(position) => {
+                self.transform.compute_matrix()
+                    * Mat4::from_translation(position)
+                    * self.instance_transform.compute_matrix()
+            }
+            None => Mat4::ZERO,
+        }
+    }
+}
+
+impl<I: LayoutInstance> From<&InstanceLayout<I>> for InstanceLayoutUniform {
+    fn from(layout: &InstanceLayout<I>) -> Self {
+        let transform = layout.transform.compute_matrix();
+        let instance_transform = layout.instance_transform.compute_matrix();
+        let (shape, params, counts, seed) = layout.shape.shader_params();
+
+        let mut points = [Vec4::ZERO; MAX_LAYOUT_POINTS];
+        for (point, source) in points.iter_mut().zip(layout.shape.points()) {
+            *point = source.extend(1.0);
+        }
+
+        InstanceLayoutUniform {
+            transform,
+            inverse_transform: transform.inverse(),
+            instance_transform,
+            inverse_instance_transform: instance_transform.inverse(),
+            color: layout.color.into(),
+            params,
+            counts,
+            shape,
+            seed,
+            point_count: layout.shape.points().len() as u32,
+            points,
+        }
+    }
+}
+
+impl<I: LayoutInstance> From<&InstanceLayout<I>> for () {
+    fn from(_: &InstanceLayout<I>) -> Self {}
+}
+
+impl<I: LayoutInstance> ExtractComponent for InstanceLayout<I> {
+    type Query = Read<Self>;
+
+    type Filter = ();
+
+    fn extract_component(item: bevy::ecs::query::QueryItem<Self::Query>) -> Self {
+        item.clone()
+    }
+}
+
+impl<I: LayoutInstance> InstanceCompute for InstanceLayout<I> {
+    type Instance = I;
+
+    fn shader() -> ShaderRef {
+        INSTANCE_LAYOUT_SHADER_HANDLE.typed().into()
+    }
+
+    /// Every stream but the mesh index, which the layout leaves untouched
+    fn streams() -> Vec<usize> {
+        (1..<I as Instance>::streams().len()).collect()
+    }
+
+    fn compute_cpu(&self, instances: &mut [<I as Instance>::PreparedInstance]) -> bool {
+        let count = instances.len() as u32;
+        let data = I::layout_data(self.color);
+
+        for (index, instance) in instances.iter_mut().enumerate() {
+            *instance = I::prepare_bulk_instance(self.instance_matrix(index as u32, count), &data);
+        }
+
+        true
+    }
+
+    fn specialize(
+        _: &InstanceComputePipeline<Self>,
+        descriptor: &mut ComputePipelineDescriptor,
+        _: Self::Data,
+    ) {
+        descriptor.shader_defs.push(I::SHADER_DEF.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::{UVec3, Vec3};
+
+    use super::*;
+    use crate::prelude::{GpuColorMeshInstance, GpuMeshInstance};
+
+    fn compute(layout: &InstanceLayout<MeshInstance>, count: usize) -> Vec<GpuMeshInstance> {
+        let mut instances = vec![GpuMeshInstance::default(); count];
+        assert!(layout.compute_cpu(&mut instances));
+        instances
+    }
+
+    fn assert_translations(instances: &[GpuMeshInstance], expected: &[Vec3]) {
+        assert_eq!(instances.len(), expected.len());
+        for (instance, expected) in instances.iter().zip(expected) {
+            let translation = instance.transform.w_axis.truncate();
+            assert!(
+                translation.abs_diff_eq(*expected, 1e-5),
+                "{translation} != {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn grid_hides_instances_past_the_last_cell() {
+        let layout = InstanceLayout::<MeshInstance>::new(InstanceLayoutShape::grid_3d(
+            UVec3::new(2, 2, 2),
+            Vec3::splat(2.0),
+        ));
+        let instances = compute(&layout, 9);
+
+        assert_translations(
+            &instances[..8],
+            &[
+                Vec3::new(-1.0, -1.0, -1.0),
+                Vec3::new(1.0, -1.0, -1.0),
+                Vec3::new(-1.0, 1.0, -1.0),
+                Vec3::new(1.0, 1.0, -1.0),
+                Vec3::new(-1.0, -1.0, 1.0),
+                Vec3::new(1.0, -1.0, 1.0),
+                Vec3::new(-1.0, 1.0, 1.0),
+                Vec3::new(1.0, 1.0, 1.0),
+            ],
+        );
+        assert_eq!(instances[8].transform, Mat4::ZERO);
+    }
+
+    #[test]
+    fn ring_spaces_instances_evenly() {
+        let layout = InstanceLayout::<MeshInstance>::new(InstanceLayoutShape::Ring { radius: 2.0 });
+
+        assert_translations(
+            &compute(&layout, 4),
+            &[
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 2.0),
+                Vec3::new(-2.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, -2.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn polyline_spaces_instances_by_distance() {
+        let layout = InstanceLayout::<MeshInstance>::new(InstanceLayoutShape::Polyline {
+            points: vec![
+                Vec3::ZERO,
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(2.0, 0.0, 2.0),
+            ],
+        });
+
+        assert_translations(
+            &compute(&layout, 5),
+            &[
+                Vec3::ZERO,
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(2.0, 0.0, 1.0),
+                Vec3::new(2.0, 0.0, 2.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn transforms_apply_around_each_position() {
+        let layout = InstanceLayout::<MeshInstance>::new(InstanceLayoutShape::grid_1d(2, 2.0))
+            .with_transform(Transform::from_xyz(10.0, 0.0, 0.0))
+            .with_instance_transform(Transform::from_scale(Vec3::splat(3.0)));
+        let instances = compute(&layout, 2);
+
+        assert_translations(
+            &instances,
+            &[Vec3::new(9.0, 0.0, 0.0), Vec3::new(11.0, 0.0, 0.0)],
+        );
+        for instance in &instances {
+            assert!(instance.transform.x_axis.abs_diff_eq(Vec4::X * 3.0, 1e-5));
+            assert!(instance
+                .inverse_transpose_model
+                .abs_diff_eq(instance.transform.inverse().transpose(), 1e-5));
+        }
+    }
+
+    #[test]
+    fn color_instances_take_the_layout_color() {
+        let color = Color::rgba(0.2, 0.4, 0.6, 0.8);
+        let layout =
+            InstanceLayout::<ColorMeshInstance>::new(InstanceLayoutShape::Ring { radius: 1.0 })
+                .with_color(color);
+
+        let mut instances = vec![GpuColorMeshInstance::default(); 3];
+        assert!(layout.compute_cpu(&mut instances));
+
+        for instance in &instances {
+            assert_eq!(instance.color, Vec4::from(InstanceColor(color)));
+            assert_eq!(instance.base.transform.x_axis, Vec4::X);
+        }
+    }
+}
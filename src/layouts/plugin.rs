@@ -0,0 +1,45 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    asset::load_internal_asset,
+    prelude::{default, HandleUntyped, Plugin, Shader},
+    reflect::TypeUuid,
+};
+
+use crate::prelude::{
+    ColorInstancePlugin, InstanceComputePlugin, InstanceLayout, InstanceLayoutShape, LayoutInstance,
+};
+
+pub const INSTANCE_LAYOUT_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 5081546273810941368);
+
+/// Places slices of `I` carrying an [`InstanceLayout`] with the built-in layout shader.
+///
+/// Requires [`IndirectRenderingPlugin`](crate::prelude::IndirectRenderingPlugin).
+pub struct InstanceLayoutPlugin<I: LayoutInstance>(PhantomData<I>);
+
+impl<I: LayoutInstance> Default for InstanceLayoutPlugin<I> {
+    fn default() -> Self {
+        Self(default())
+    }
+}
+
+impl<I: LayoutInstance> Plugin for InstanceLayoutPlugin<I> {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        load_internal_asset!(
+            app,
+            INSTANCE_LAYOUT_SHADER_HANDLE,
+            "instance_layout.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.register_type::<InstanceLayoutShape>();
+
+        // The shader imports the color instance struct whichever type it writes
+        if !app.is_plugin_added::<ColorInstancePlugin>() {
+            app.add_plugin(ColorInstancePlugin);
+        }
+
+        app.add_plugin(InstanceComputePlugin::<InstanceLayout<I>>::default());
+    }
+}
@@ -0,0 +1,208 @@
+use std::f32::consts::TAU;
+
+use bevy::{
+    math::{UVec2, UVec3, Vec2, Vec3, Vec4},
+    reflect::{FromReflect, Reflect},
+};
+
+/// Most points a [`InstanceLayoutShape::Polyline`] can follow, further points are ignored
+pub const MAX_LAYOUT_POINTS: usize = 64;
+
+/// Arrangement of an [`InstanceLayout`](crate::prelude::InstanceLayout)'s instances,
+/// spread across however many instances its slice holds
+#[derive(Debug, Clone, PartialEq, Reflect, FromReflect)]
+pub enum InstanceLayoutShape {
+    /// Rows along X, then Y, then Z, centered on the origin.
+    /// Instances past the last cell are hidden.
+    Grid { counts: UVec3, spacing: Vec3 },
+    /// Evenly spaced around a circle on the XZ plane
+    Ring { radius: f32 },
+    /// Winding outward from `inner_radius` to `outer_radius` on the XZ plane,
+    /// rising by `height` over `turns` revolutions
+    Spiral {
+        inner_radius: f32,
+        outer_radius: f32,
+        turns: f32,
+        height: f32,
+    },
+    /// Uniformly distributed inside an axis-aligned box
+    RandomBox { half_extents: Vec3, seed: u32 },
+    /// Uniformly distributed inside a sphere
+    RandomSphere { radius: f32, seed: u32 },
+    /// Uniformly distributed inside a disk on the XZ plane
+    RandomDisk { radius: f32, seed: u32 },
+    /// Evenly spaced by distance along connected points,
+    /// from the first to the last of up to [`MAX_LAYOUT_POINTS`]
+    Polyline { points: Vec<Vec3> },
+}
+
+impl Default for InstanceLayoutShape {
+    fn default() -> Self {
+        InstanceLayoutShape::Grid {
+            counts: UVec3::ONE,
+            spacing: Vec3::ONE,
+        }
+    }
+}
+
+impl InstanceLayoutShape {
+    /// A line of `count` instances along X
+    pub fn grid_1d(count: u32, spacing: f32) -> Self {
+        InstanceLayoutShape::Grid {
+            counts: UVec3::new(count, 1, 1),
+            spacing: Vec3::new(spacing, 0.0, 0.0),
+        }
+    }
+
+    /// Rows of instances on the XZ plane
+    pub fn grid_2d(counts: UVec2, spacing: Vec2) -> Self {
+        InstanceLayoutShape::Grid {
+            counts: UVec3::new(counts.x, 1, counts.y),
+            spacing: Vec3::new(spacing.x, 0.0, spacing.y),
+        }
+    }
+
+    pub fn grid_3d(counts: UVec3, spacing: Vec3) -> Self {
+        InstanceLayoutShape::Grid { counts, spacing }
+    }
+
+    /// Shape index, parameters, grid counts and seed as read by `instance_layout.wgsl`
+    pub fn shader_params(&self) -> (u32, Vec4, UVec3, u32) {
+        match self {
+            InstanceLayoutShape::Grid { counts, spacing } => {
+                (0, spacing.extend(0.0), counts.max(UVec3::ONE), 0)
+            }
+            InstanceLayoutShape::Ring { radius } => {
+                (1, Vec4::new(*radius, 0.0, 0.0, 0.0), UVec3::ONE, 0)
+            }
+            InstanceLayoutShape::Spiral {
+                inner_radius,
+                outer_radius,
+                turns,
+                height,
+            } => (
+                2,
+                Vec4::new(*inner_radius, *outer_radius, *turns, *height),
+                UVec3::ONE,
+                0,
+            ),
+            InstanceLayoutShape::RandomBox { half_extents, seed } => {
+                (3, half_extents.extend(0.0), UVec3::ONE, *seed)
+            }
+            InstanceLayoutShape::RandomSphere { radius, seed } => {
+                (4, Vec4::new(*radius, 0.0, 0.0, 0.0), UVec3::ONE, *seed)
+            }
+            InstanceLayoutShape::RandomDisk { radius, seed } => {
+                (5, Vec4::new(*radius, 0.0, 0.0, 0.0), UVec3::ONE, *seed)
+            }
+            InstanceLayoutShape::Polyline { .. } => (6, Vec4::ZERO, UVec3::ONE, 0),
+        }
+    }
+
+    /// Points followed by a polyline, truncated to [`MAX_LAYOUT_POINTS`]
+    pub fn points(&self) -> &[Vec3] {
+        match self {
+            InstanceLayoutShape::Polyline { points } => {
+                &points[..points.len().min(MAX_LAYOUT_POINTS)]
+            }
+            _ => &[],
+        }
+    }
+
+    /// Position of instance `index` out of `count`, or `None` if it is hidden.
+    ///
+    /// Mirrors `layout_position` in `instance_layout.wgsl`.
+    pub fn position(&self, index: u32, count: u32) -> Option<Vec3> {
+        let (_, params, counts, seed) = self.shader_params();
+        let t = index as f32 / count.saturating_sub(1).max(1) as f32;
+        let random = |offset: u32| {
+            layout_hash(
+                index
+                    .wrapping_mul(4)
+                    .wrapping_add(seed.wrapping_mul(2654435761))
+                    .wrapping_add(offset),
+            )
+        };
+
+        let position = match self {
+            InstanceLayoutShape::Grid { .. } => {
+                let cell = UVec3::new(
+                    index % counts.x,
+                    (index / counts.x) % counts.y,
+                    index / (counts.x * counts.y),
+                );
+                if cell.z >= counts.z {
+                    return None;
+                }
+
+                (cell.as_vec3() - (counts - UVec3::ONE).as_vec3() * 0.5) * params.truncate()
+            }
+            InstanceLayoutShape::Ring { .. } => {
+                let angle = TAU * index as f32 / count.max(1) as f32;
+                Vec3::new(angle.cos(), 0.0, angle.sin()) * params.x
+            }
+            InstanceLayoutShape::Spiral { .. } => {
+                let angle = t * params.z * TAU;
+                let radius = params.x + (params.y - params.x) * t;
+                Vec3::new(angle.cos() * radius, t * params.w, angle.sin() * radius)
+            }
+            InstanceLayoutShape::RandomBox { .. } => {
+                (Vec3::new(random(0), random(1), random(2)) * 2.0 - 1.0) * params.truncate()
+            }
+            InstanceLayoutShape::RandomSphere { .. } => {
+                let z = random(0) * 2.0 - 1.0;
+                let phi = random(1) * TAU;
+                let r = (1.0 - z * z).max(0.0).sqrt();
+                Vec3::new(r * phi.cos(), r * phi.sin(), z) * params.x * random(2).powf(1.0 / 3.0)
+            }
+            InstanceLayoutShape::RandomDisk { .. } => {
+                let radius = params.x * random(0).sqrt();
+                let angle = random(1) * TAU;
+                Vec3::new(angle.cos(), 0.0, angle.sin()) * radius
+            }
+            InstanceLayoutShape::Polyline { .. } => polyline_position(self.points(), t),
+        };
+
+        Some(position)
+    }
+}
+
+/// Point a fraction `t` of the way along `points` by distance
+fn polyline_position(points: &[Vec3], t: f32) -> Vec3 {
+    let (first, rest) = match points.split_first() {
+        Some(split) => split,
+        None => return Vec3::ZERO,
+    };
+
+    let length = points
+        .windows(2)
+        .map(|segment| segment[0].distance(segment[1]))
+        .sum::<f32>();
+
+    let mut distance = length * t;
+    let mut start = *first;
+    for (i, end) in rest.iter().enumerate() {
+        let segment_length = start.distance(*end);
+        if distance <= segment_length || i == rest.len() - 1 {
+            let f = if segment_length > 0.0 {
+                (distance / segment_length).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            return start.lerp(*end, f);
+        }
+
+        distance -= segment_length;
+        start = *end;
+    }
+
+    start
+}
+
+/// Pseudorandom value in `[0, 1]`, matching `hash` in `instance_layout.wgsl`
+pub fn layout_hash(n: u32) -> f32 {
+    let mut x = n.wrapping_mul(747796405).wrapping_add(2891336453);
+    x = ((x >> ((x >> 28) + 4)) ^ x).wrapping_mul(277803737);
+    x = (x >> 22) ^ x;
+    x as f32 / 4294967295.0
+}
@@ -1,6 +1,10 @@
+extern crate self as bevy_instancing;
+
 pub mod materials;
 pub mod instancing;
 pub mod prelude;
 pub mod colored_mesh_instance;
+pub mod particles;
+pub mod layouts;
 
 //pub mod compute;
@@ -1,6 +1,8 @@
 #import bevy_pbr::mesh_view_bindings
 #import indirect_instancing::color_instance_struct
 
+#ifndef VERTEX_INSTANCES
+#ifndef SOA_INSTANCES
 #ifdef NO_STORAGE_BUFFERS_SUPPORT
 @group(2)
 @binding(0)
@@ -10,6 +12,8 @@ var<uniform> instances: ColorInstances;
 @binding(0)
 var<storage> instances: ColorInstances;
 #endif
+#endif
+#endif
 
 struct VertexInput {
     @builtin(instance_index) instance: u32,
@@ -27,8 +31,21 @@ struct VertexOutput {
 };
 
 @vertex
+#ifdef VERTEX_INSTANCES
+fn vertex(
+    in: VertexInput,
+    base_in: InstanceVertex,
+    instance_in: ColorInstanceVertex,
+) -> VertexOutput {
+    let instance = color_instance_from_vertex(base_in, instance_in);
+#else
 fn vertex(in: VertexInput) -> VertexOutput {
+#ifdef SOA_INSTANCES
+    let instance = load_color_instance(in.instance);
+#else
     let instance = instances.instances[in.instance];
+#endif
+#endif
 
     var out: VertexOutput;
     out.world_position = instance.base.transform * vec4<f32>(in.vertex, 1.0);
@@ -1,3 +1,4 @@
 pub mod basic_material;
 pub mod custom_material;
+pub mod standard_material;
 pub mod texture_material;
@@ -0,0 +1,468 @@
+use bevy::{
+    pbr::StandardMaterial,
+    prelude::{
+        debug, App, Assets, Changed, Commands, Component, CoreStage, Deref, DerefMut, Entity,
+        Handle, Mesh, Or, Plugin, Query, RemovedComponents, Res, ResMut, Resource, Without,
+    },
+    utils::{HashMap, HashSet},
+};
+
+use crate::prelude::{StandardMaterialInstanced, StandardMaterialInstancedPlugin};
+
+/// Opt-in plugin that routes ordinary `Handle<Mesh>` + `Handle<StandardMaterial>` entities
+/// through the instanced renderer once enough of them share a mesh and material
+#[derive(Debug, Copy, Clone)]
+pub struct AutoInstancingPlugin {
+    /// Minimum number of entities sharing a mesh and material before they are instanced
+    pub threshold: usize,
+}
+
+impl Default for AutoInstancingPlugin {
+    fn default() -> Self {
+        Self { threshold: 16 }
+    }
+}
+
+impl Plugin for AutoInstancingPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<StandardMaterialInstancedPlugin>() {
+            app.add_plugin(StandardMaterialInstancedPlugin);
+        }
+
+        app.insert_resource(AutoInstancingThreshold(self.threshold))
+            .init_resource::<AutoInstancedMaterials>()
+            .init_resource::<AutoInstancingGroups>()
+            // Runs after Update so that entities despawned there are seen as removed
+            .add_system_to_stage(CoreStage::PostUpdate, auto_instancing_system);
+    }
+}
+
+/// Runtime-adjustable threshold used by [`AutoInstancingPlugin`]
+#[derive(Debug, Copy, Clone, Deref, DerefMut, Resource)]
+pub struct AutoInstancingThreshold(pub usize);
+
+/// Excludes an entity from automatic instancing
+#[derive(Debug, Default, Copy, Clone, Component)]
+pub struct NoAutoInstancing;
+
+/// Marks an entity that has been moved onto the instanced renderer,
+/// holding the material it will be restored to if it falls back
+#[derive(Debug, Clone, Component)]
+pub struct AutoInstanced {
+    pub material: Handle<StandardMaterial>,
+}
+
+/// Instanced wrappers created on behalf of auto-instanced [`StandardMaterial`]s
+#[derive(Debug, Default, Deref, DerefMut, Resource)]
+pub struct AutoInstancedMaterials(
+    HashMap<Handle<StandardMaterial>, Handle<StandardMaterialInstanced>>,
+);
+
+/// Entities sharing a mesh and material, keyed by weak handles
+type AutoInstancingKey = (Handle<Mesh>, Handle<StandardMaterial>);
+
+#[derive(Debug, Default)]
+struct AutoInstancingGroup {
+    entities: HashSet<Entity>,
+    instanced: bool,
+}
+
+/// Auto-instancing candidates grouped by mesh and material,
+/// updated incrementally as entities change
+#[derive(Debug, Default, Resource)]
+pub struct AutoInstancingGroups {
+    groups: HashMap<AutoInstancingKey, AutoInstancingGroup>,
+    entities: HashMap<Entity, AutoInstancingKey>,
+}
+
+impl AutoInstancingGroups {
+    /// Move `entity` into the group for `key`, or out of any group if `None`,
+    /// marking the groups it left and joined as dirty
+    fn assign(
+        &mut self,
+        entity: Entity,
+        key: Option<AutoInstancingKey>,
+        dirty: &mut HashSet<AutoInstancingKey>,
+    ) {
+        if self.entities.get(&entity) == key.as_ref() {
+            return;
+        }
+
+        if let Some(previous) = self.entities.remove(&entity) {
+            if let Some(group) = self.groups.get_mut(&previous) {
+                group.entities.remove(&entity);
+            }
+            dirty.insert(previous);
+        }
+
+        if let Some(key) = key {
+            self.groups
+                .entry(key.clone())
+                .or_default()
+                .entities
+                .insert(entity);
+            self.entities.insert(entity, key.clone());
+            dirty.insert(key);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn auto_instancing_system(
+    threshold: Res<AutoInstancingThreshold>,
+    mut instanced_materials: ResMut<Assets<StandardMaterialInstanced>>,
+    mut auto_instanced_materials: ResMut<AutoInstancedMaterials>,
+    mut groups: ResMut<AutoInstancingGroups>,
+    query_members: Query<
+        (
+            &Handle<Mesh>,
+            Option<&Handle<StandardMaterial>>,
+            Option<&AutoInstanced>,
+        ),
+        Without<NoAutoInstancing>,
+    >,
+    query_changed: Query<
+        Entity,
+        Or<(
+            Changed<Handle<Mesh>>,
+            Changed<Handle<StandardMaterial>>,
+            Changed<AutoInstanced>,
+        )>,
+    >,
+    query_opted_out: Query<(Entity, Option<&AutoInstanced>), Changed<NoAutoInstancing>>,
+    removed_meshes: RemovedComponents<Handle<Mesh>>,
+    removed_materials: RemovedComponents<Handle<StandardMaterial>>,
+    removed_auto_instanced: RemovedComponents<AutoInstanced>,
+    removed_opt_outs: RemovedComponents<NoAutoInstancing>,
+    mut commands: Commands,
+) {
+    for (entity, auto_instanced) in query_opted_out.iter() {
+        if let Some(auto_instanced) = auto_instanced {
+            debug!("Auto-instancing disabled for {entity:?}, restoring StandardMaterial");
+            commands
+                .entity(entity)
+                .remove::<(AutoInstanced, Handle<StandardMaterialInstanced>)>()
+                .insert(auto_instanced.material.clone());
+        }
+    }
+
+    // Regroup only entities whose mesh, material or opt-out changed
+    let touched = query_changed
+        .iter()
+        .chain(query_opted_out.iter().map(|(entity, _)| entity))
+        .chain(removed_meshes.iter())
+        .chain(removed_materials.iter())
+        .chain(removed_auto_instanced.iter())
+        .chain(removed_opt_outs.iter())
+        .collect::<HashSet<_>>();
+
+    let mut dirty = HashSet::<AutoInstancingKey>::default();
+
+    for entity in touched {
+        let key = query_members
+            .get(entity)
+            .ok()
+            .and_then(|(mesh, standard, auto_instanced)| {
+                let material = standard.or(auto_instanced.map(|auto| &auto.material))?;
+                Some((mesh.clone_weak(), material.clone_weak()))
+            });
+
+        groups.assign(entity, key, &mut dirty);
+    }
+
+    if threshold.is_changed() {
+        dirty.extend(groups.groups.keys().cloned());
+    }
+
+    if dirty.is_empty() {
+        return;
+    }
+
+    for key in dirty.iter() {
+        let group = match groups.groups.get_mut(key) {
+            Some(group) => group,
+            None => continue,
+        };
+
+        let (_, material) = key;
+        group.instanced = group.entities.len() >= **threshold;
+
+        let members = group
+            .entities
+            .iter()
+            .filter_map(|entity| Some((*entity, query_members.get(*entity).ok()?)));
+
+        let mut count = 0;
+        if group.instanced {
+            for (entity, standard) in
+                members.filter_map(|(entity, (_, standard, _))| Some((entity, standard?)))
+            {
+                let instanced_material = auto_instanced_materials
+                    .entry(material.clone_weak())
+                    .or_insert_with(|| {
+                        instanced_materials.add(StandardMaterialInstanced::from(standard.clone()))
+                    })
+                    .clone();
+
+                commands
+                    .entity(entity)
+                    .remove::<Handle<StandardMaterial>>()
+                    .insert((
+                        AutoInstanced {
+                            material: standard.clone(),
+                        },
+                        instanced_material,
+                    ));
+                count += 1;
+            }
+
+            if count > 0 {
+                debug!("Instancing {count} entities with material {material:?}");
+            }
+        } else {
+            for (entity, standard, auto_instanced) in
+                members.filter_map(|(entity, (_, standard, auto_instanced))| {
+                    Some((entity, standard, auto_instanced?))
+                })
+            {
+                let mut entity = commands.entity(entity);
+                entity.remove::<(AutoInstanced, Handle<StandardMaterialInstanced>)>();
+
+                // Keep a StandardMaterial assigned while instanced over the one it replaced
+                if standard.is_none() {
+                    entity.insert(auto_instanced.material.clone());
+                }
+                count += 1;
+            }
+
+            if count > 0 {
+                debug!("Falling back for {count} entities with material {material:?}");
+            }
+        }
+    }
+
+    groups.groups.retain(|_, group| !group.entities.is_empty());
+
+    // Release wrappers that no longer back any instanced group
+    auto_instanced_materials.retain(|material, _| {
+        groups
+            .groups
+            .iter()
+            .any(|((_, group_material), group)| group_material == material && group.instanced)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::{AddAsset, AssetPlugin},
+        prelude::{shape::Cube, Color, MinimalPlugins},
+    };
+
+    use super::*;
+
+    fn app(threshold: usize) -> App {
+        let mut app = App::new();
+
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .add_asset::<StandardMaterialInstanced>()
+            .insert_resource(AutoInstancingThreshold(threshold))
+            .init_resource::<AutoInstancedMaterials>()
+            .init_resource::<AutoInstancingGroups>()
+            .add_system_to_stage(CoreStage::PostUpdate, auto_instancing_system);
+
+        app
+    }
+
+    fn add_mesh(app: &mut App) -> Handle<Mesh> {
+        app.world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Cube::default().into())
+    }
+
+    fn add_material(app: &mut App, color: Color) -> Handle<StandardMaterial> {
+        app.world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(color.into())
+    }
+
+    fn spawn(
+        app: &mut App,
+        mesh: &Handle<Mesh>,
+        material: &Handle<StandardMaterial>,
+        count: usize,
+    ) -> Vec<Entity> {
+        (0..count)
+            .map(|_| app.world.spawn((mesh.clone(), material.clone())).id())
+            .collect()
+    }
+
+    /// The instanced material drawing `entity`, if it has been auto-instanced with `material`
+    fn instanced(
+        app: &App,
+        entity: Entity,
+        material: &Handle<StandardMaterial>,
+    ) -> Option<Handle<StandardMaterialInstanced>> {
+        let entity = app.world.entity(entity);
+        assert!(!entity.contains::<Handle<StandardMaterial>>());
+        assert_eq!(&entity.get::<AutoInstanced>()?.material, material);
+        entity.get::<Handle<StandardMaterialInstanced>>().cloned()
+    }
+
+    fn assert_standard(app: &App, entity: Entity, material: &Handle<StandardMaterial>) {
+        let entity = app.world.entity(entity);
+        assert_eq!(entity.get::<Handle<StandardMaterial>>(), Some(material));
+        assert!(!entity.contains::<AutoInstanced>());
+        assert!(!entity.contains::<Handle<StandardMaterialInstanced>>());
+    }
+
+    #[test]
+    fn groups_crossing_the_threshold_are_instanced() {
+        let mut app = app(3);
+        let mesh = add_mesh(&mut app);
+        let material = add_material(&mut app, Color::RED);
+
+        let mut entities = spawn(&mut app, &mesh, &material, 2);
+        app.update();
+
+        for entity in &entities {
+            assert_standard(&app, *entity, &material);
+        }
+
+        entities.extend(spawn(&mut app, &mesh, &material, 1));
+        app.update();
+
+        let instanced_material = instanced(&app, entities[0], &material).unwrap();
+        for entity in &entities {
+            assert_eq!(
+                instanced(&app, *entity, &material).as_ref(),
+                Some(&instanced_material)
+            );
+        }
+
+        assert_eq!(app.world.resource::<AutoInstancedMaterials>().len(), 1);
+        assert!(app
+            .world
+            .resource::<Assets<StandardMaterialInstanced>>()
+            .contains(&instanced_material));
+    }
+
+    #[test]
+    fn groups_dropping_below_the_threshold_revert() {
+        let mut app = app(3);
+        let mesh = add_mesh(&mut app);
+        let material = add_material(&mut app, Color::RED);
+
+        let entities = spawn(&mut app, &mesh, &material, 3);
+        app.update();
+
+        for entity in &entities {
+            assert!(instanced(&app, *entity, &material).is_some());
+        }
+
+        app.world.despawn(entities[0]);
+        app.update();
+
+        for entity in &entities[1..] {
+            assert_standard(&app, *entity, &material);
+        }
+
+        assert!(app.world.resource::<AutoInstancedMaterials>().is_empty());
+    }
+
+    #[test]
+    fn separate_meshes_are_grouped_separately() {
+        let mut app = app(3);
+        let (mesh_a, mesh_b) = (add_mesh(&mut app), add_mesh(&mut app));
+        let material = add_material(&mut app, Color::RED);
+
+        let group_a = spawn(&mut app, &mesh_a, &material, 2);
+        let group_b = spawn(&mut app, &mesh_b, &material, 2);
+        app.update();
+
+        for entity in group_a.iter().chain(&group_b) {
+            assert_standard(&app, *entity, &material);
+        }
+    }
+
+    #[test]
+    fn changed_materials_regroup() {
+        let mut app = app(3);
+        let mesh = add_mesh(&mut app);
+        let material_a = add_material(&mut app, Color::RED);
+        let material_b = add_material(&mut app, Color::BLUE);
+
+        let group_a = spawn(&mut app, &mesh, &material_a, 3);
+        let group_b = spawn(&mut app, &mesh, &material_b, 2);
+        app.update();
+
+        let instanced_a = instanced(&app, group_a[0], &material_a).unwrap();
+        for entity in &group_b {
+            assert_standard(&app, *entity, &material_b);
+        }
+
+        // Moving an instanced entity over takes group A below the threshold and group B above it
+        app.world.entity_mut(group_a[0]).insert(material_b.clone());
+        app.update();
+
+        for entity in &group_a[1..] {
+            assert_standard(&app, *entity, &material_a);
+        }
+
+        let instanced_b = instanced(&app, group_a[0], &material_b).unwrap();
+        assert_ne!(instanced_a, instanced_b);
+        for entity in &group_b {
+            assert_eq!(
+                instanced(&app, *entity, &material_b).as_ref(),
+                Some(&instanced_b)
+            );
+        }
+
+        let auto_instanced_materials = app.world.resource::<AutoInstancedMaterials>();
+        assert_eq!(auto_instanced_materials.len(), 1);
+        assert!(auto_instanced_materials.contains_key(&material_b));
+    }
+
+    #[test]
+    fn removed_materials_regroup() {
+        let mut app = app(3);
+        let mesh = add_mesh(&mut app);
+        let material = add_material(&mut app, Color::RED);
+
+        let entities = spawn(&mut app, &mesh, &material, 3);
+
+        // Without a material, the third entity doesn't count towards the group
+        app.world
+            .entity_mut(entities[2])
+            .remove::<Handle<StandardMaterial>>();
+        app.update();
+
+        for entity in &entities[..2] {
+            assert_standard(&app, *entity, &material);
+        }
+
+        app.world.entity_mut(entities[2]).insert(material.clone());
+        app.update();
+
+        for entity in &entities {
+            assert!(instanced(&app, *entity, &material).is_some());
+        }
+
+        // Removing the material an instanced entity falls back to drops it from the group
+        app.world
+            .entity_mut(entities[2])
+            .remove::<(AutoInstanced, Handle<StandardMaterialInstanced>)>();
+        app.update();
+
+        for entity in &entities[..2] {
+            assert_standard(&app, *entity, &material);
+        }
+
+        let entity = app.world.entity(entities[2]);
+        assert!(!entity.contains::<Handle<StandardMaterial>>());
+        assert!(!entity.contains::<Handle<StandardMaterialInstanced>>());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod auto_instancing;
+pub mod plugin;
+pub mod standard_material;
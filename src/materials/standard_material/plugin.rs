@@ -0,0 +1,180 @@
+use bevy::{
+    asset::load_internal_asset,
+    pbr::StandardMaterial,
+    prelude::{
+        AddAsset, AssetEvent, Assets, EventReader, HandleUntyped, Plugin, Res, ResMut, Shader,
+    },
+    reflect::TypeUuid,
+    utils::HashSet,
+};
+
+use crate::prelude::{InstancedMaterialPlugin, StandardMaterialInstanced};
+
+pub const STANDARD_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 12768476931382573590);
+
+pub struct StandardMaterialInstancedPlugin;
+
+impl Plugin for StandardMaterialInstancedPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        load_internal_asset!(
+            app,
+            STANDARD_SHADER_HANDLE,
+            "standard.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.add_asset::<StandardMaterialInstanced>()
+            .add_plugin(InstancedMaterialPlugin::<StandardMaterialInstanced>::default())
+            .add_system(sync_standard_materials);
+    }
+}
+
+/// Mirrors [`StandardMaterial`] assets into the [`StandardMaterialInstanced`] assets that reference them,
+/// clearing the copy when the source is removed
+pub fn sync_standard_materials(
+    mut standard_events: EventReader<AssetEvent<StandardMaterial>>,
+    mut instanced_events: EventReader<AssetEvent<StandardMaterialInstanced>>,
+    standard_materials: Res<Assets<StandardMaterial>>,
+    mut instanced_materials: ResMut<Assets<StandardMaterialInstanced>>,
+) {
+    let mut changed = HashSet::default();
+    for event in standard_events.iter() {
+        match event {
+            AssetEvent::Created { handle }
+            | AssetEvent::Modified { handle }
+            | AssetEvent::Removed { handle } => {
+                changed.insert(handle.clone_weak());
+            }
+        }
+    }
+
+    // Newly-created wrappers still need their initial copy
+    let created = instanced_events.iter().any(|event| {
+        matches!(
+            event,
+            AssetEvent::Created { .. } | AssetEvent::Modified { .. }
+        )
+    });
+
+    if changed.is_empty() && !created {
+        return;
+    }
+
+    let stale = instanced_materials
+        .iter()
+        .filter(|(_, instanced)| {
+            changed.contains(&instanced.handle) || instanced.material.is_none()
+        })
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+
+    for id in stale {
+        let handle = instanced_materials.get_handle(id);
+
+        let (standard, mirrored) = if let Some(instanced) = instanced_materials.get(&handle) {
+            (
+                standard_materials.get(&instanced.handle).cloned(),
+                instanced.material.is_some(),
+            )
+        } else {
+            continue;
+        };
+
+        // Still waiting on the source to load
+        if standard.is_none() && !mirrored {
+            continue;
+        }
+
+        // A removed source clears the copy, so the wrapper stops drawing.
+        // get_mut emits AssetEvent::Modified, prompting re-extraction
+        if let Some(instanced) = instanced_materials.get_mut(&handle) {
+            instanced.material = standard;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::AssetPlugin,
+        prelude::{App, Color, Handle, MinimalPlugins},
+    };
+
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<StandardMaterial>()
+            .add_asset::<StandardMaterialInstanced>()
+            .add_system(sync_standard_materials);
+
+        app
+    }
+
+    fn mirrored_color(app: &App, instanced: &Handle<StandardMaterialInstanced>) -> Option<Color> {
+        app.world
+            .resource::<Assets<StandardMaterialInstanced>>()
+            .get(instanced)
+            .unwrap()
+            .material()
+            .map(|material| material.base_color)
+    }
+
+    #[test]
+    fn mirrors_follow_their_source() {
+        let mut app = app();
+
+        let standard = app
+            .world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(Color::RED.into());
+        let instanced = app
+            .world
+            .resource_mut::<Assets<StandardMaterialInstanced>>()
+            .add(standard.clone().into());
+
+        app.update();
+        app.update();
+        assert_eq!(mirrored_color(&app, &instanced), Some(Color::RED));
+
+        app.world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .get_mut(&standard)
+            .unwrap()
+            .base_color = Color::BLUE;
+
+        app.update();
+        app.update();
+        assert_eq!(mirrored_color(&app, &instanced), Some(Color::BLUE));
+    }
+
+    #[test]
+    fn removed_sources_clear_their_mirrors() {
+        let mut app = app();
+
+        let standard = app
+            .world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(Color::RED.into());
+        let instanced = app
+            .world
+            .resource_mut::<Assets<StandardMaterialInstanced>>()
+            .add(standard.clone().into());
+
+        app.update();
+        app.update();
+        assert!(mirrored_color(&app, &instanced).is_some());
+
+        app.world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .remove(&standard);
+
+        app.update();
+        app.update();
+        assert_eq!(mirrored_color(&app, &instanced), None);
+    }
+}
@@ -0,0 +1,175 @@
+#import bevy_pbr::mesh_view_bindings
+#import bevy_pbr::pbr_bindings
+#import bevy_pbr::mesh_types
+#import indirect_instancing::instance_struct
+
+#ifndef VERTEX_INSTANCES
+#ifndef SOA_INSTANCES
+#ifdef NO_STORAGE_BUFFERS_SUPPORT
+@group(2)
+@binding(0)
+var<uniform> in_instances: Instances;
+#else
+@group(2)
+@binding(0)
+var<storage> in_instances: Instances;
+#endif
+#endif
+#endif
+
+// Stand-in for bevy_pbr::mesh_bindings, which would collide with the instance buffer at group 2.
+// pbr_functions reads mesh.flags to determine whether shadows should be received.
+var<private> mesh: Mesh;
+
+#import bevy_pbr::utils
+#import bevy_pbr::clustered_forward
+#import bevy_pbr::lighting
+#import bevy_pbr::shadows
+#import bevy_pbr::pbr_functions
+
+struct Vertex {
+    @builtin(instance_index) instance: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+#ifdef VERTEX_UVS
+    @location(2) uv: vec2<f32>,
+#endif
+#ifdef VERTEX_TANGENTS
+    @location(3) tangent: vec4<f32>,
+#endif
+#ifdef VERTEX_COLORS
+    @location(4) color: vec4<f32>,
+#endif
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    #import bevy_pbr::mesh_vertex_output
+};
+
+@vertex
+#ifdef VERTEX_INSTANCES
+fn vertex(in: Vertex, instance_in: InstanceVertex) -> VertexOutput {
+    let instance = instance_from_vertex(instance_in);
+#else
+fn vertex(in: Vertex) -> VertexOutput {
+#ifdef SOA_INSTANCES
+    let instance = load_instance(in.instance);
+#else
+    let instance = in_instances.instances[in.instance];
+#endif
+#endif
+
+    var out: VertexOutput;
+    out.world_position = instance.transform * vec4<f32>(in.position, 1.0);
+    out.clip_position = view.view_proj * out.world_position;
+    out.world_normal = mat3x3<f32>(
+        instance.inverse_transpose_model[0].xyz,
+        instance.inverse_transpose_model[1].xyz,
+        instance.inverse_transpose_model[2].xyz
+    ) * in.normal;
+#ifdef VERTEX_UVS
+    out.uv = in.uv;
+#endif
+#ifdef VERTEX_TANGENTS
+    out.world_tangent = vec4<f32>(
+        mat3x3<f32>(
+            instance.transform[0].xyz,
+            instance.transform[1].xyz,
+            instance.transform[2].xyz
+        ) * in.tangent.xyz,
+        in.tangent.w
+    );
+#endif
+#ifdef VERTEX_COLORS
+    out.color = in.color;
+#endif
+    return out;
+}
+
+struct FragmentInput {
+    @builtin(front_facing) is_front: bool,
+    @builtin(position) frag_coord: vec4<f32>,
+    #import bevy_pbr::mesh_vertex_output
+};
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    mesh.flags = MESH_FLAGS_SHADOW_RECEIVER_BIT;
+
+    var output_color: vec4<f32> = material.base_color;
+#ifdef VERTEX_COLORS
+    output_color = output_color * in.color;
+#endif
+#ifdef VERTEX_UVS
+    if ((material.flags & STANDARD_MATERIAL_FLAGS_BASE_COLOR_TEXTURE_BIT) != 0u) {
+        output_color = output_color * textureSample(base_color_texture, base_color_sampler, in.uv);
+    }
+#endif
+
+    if ((material.flags & STANDARD_MATERIAL_FLAGS_UNLIT_BIT) == 0u) {
+        var pbr_input: PbrInput;
+
+        pbr_input.material.base_color = output_color;
+        pbr_input.material.reflectance = material.reflectance;
+        pbr_input.material.flags = material.flags;
+        pbr_input.material.alpha_cutoff = material.alpha_cutoff;
+
+        var emissive: vec4<f32> = material.emissive;
+#ifdef VERTEX_UVS
+        if ((material.flags & STANDARD_MATERIAL_FLAGS_EMISSIVE_TEXTURE_BIT) != 0u) {
+            emissive = vec4<f32>(emissive.rgb * textureSample(emissive_texture, emissive_sampler, in.uv).rgb, 1.0);
+        }
+#endif
+        pbr_input.material.emissive = emissive;
+
+        var metallic: f32 = material.metallic;
+        var perceptual_roughness: f32 = material.perceptual_roughness;
+#ifdef VERTEX_UVS
+        if ((material.flags & STANDARD_MATERIAL_FLAGS_METALLIC_ROUGHNESS_TEXTURE_BIT) != 0u) {
+            let metallic_roughness = textureSample(metallic_roughness_texture, metallic_roughness_sampler, in.uv);
+            metallic = metallic * metallic_roughness.b;
+            perceptual_roughness = perceptual_roughness * metallic_roughness.g;
+        }
+#endif
+        pbr_input.material.metallic = metallic;
+        pbr_input.material.perceptual_roughness = perceptual_roughness;
+
+        var occlusion: f32 = 1.0;
+#ifdef VERTEX_UVS
+        if ((material.flags & STANDARD_MATERIAL_FLAGS_OCCLUSION_TEXTURE_BIT) != 0u) {
+            occlusion = textureSample(occlusion_texture, occlusion_sampler, in.uv).r;
+        }
+#endif
+        pbr_input.occlusion = occlusion;
+
+        pbr_input.frag_coord = in.frag_coord;
+        pbr_input.world_position = in.world_position;
+        pbr_input.world_normal = prepare_world_normal(
+            in.world_normal,
+            (material.flags & STANDARD_MATERIAL_FLAGS_DOUBLE_SIDED_BIT) != 0u,
+            in.is_front,
+        );
+
+        pbr_input.is_orthographic = view.projection[3].w == 1.0;
+
+        pbr_input.N = apply_normal_mapping(
+            material.flags,
+            pbr_input.world_normal,
+#ifdef VERTEX_TANGENTS
+#ifdef STANDARDMATERIAL_NORMAL_MAP
+            in.world_tangent,
+#endif
+#endif
+#ifdef VERTEX_UVS
+            in.uv,
+#endif
+        );
+        pbr_input.V = calculate_view(in.world_position, pbr_input.is_orthographic);
+        output_color = pbr(pbr_input);
+    } else {
+        output_color = alpha_discard(material, output_color);
+    }
+
+    return output_color;
+}
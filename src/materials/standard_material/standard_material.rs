@@ -0,0 +1,246 @@
+use bevy::{
+    pbr::{AlphaMode, StandardMaterial},
+    prelude::{AssetServer, Handle, Image},
+    reflect::TypeUuid,
+    render::{
+        mesh::MeshVertexBufferLayout,
+        render_asset::RenderAssets,
+        render_resource::{
+            AsBindGroup, AsBindGroupError, BindGroupLayout, Face, PreparedBindGroup,
+            RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+        },
+        renderer::RenderDevice,
+        texture::FallbackImage,
+    },
+};
+
+use crate::{
+    instancing::material::material_instanced::AsBatch,
+    prelude::{InstancedMaterialPipeline, MaterialInstanced, MeshInstance},
+};
+
+use super::plugin::STANDARD_SHADER_HANDLE;
+
+/// Renders an existing [`StandardMaterial`] asset through the instanced pipeline
+///
+/// The source material is mirrored from `Assets<StandardMaterial>` by
+/// [`StandardMaterialInstancedPlugin`](super::plugin::StandardMaterialInstancedPlugin),
+/// so edits to the original asset are picked up automatically.
+#[derive(Debug, Default, Clone, TypeUuid)]
+#[uuid = "f3d6b7c0-a4f2-4138-b79c-e8c06d11b3a9"]
+pub struct StandardMaterialInstanced {
+    pub handle: Handle<StandardMaterial>,
+    pub(crate) material: Option<StandardMaterial>,
+}
+
+impl StandardMaterialInstanced {
+    /// The mirrored source material, if it has been loaded and not since removed
+    pub fn material(&self) -> Option<&StandardMaterial> {
+        self.material.as_ref()
+    }
+}
+
+impl From<Handle<StandardMaterial>> for StandardMaterialInstanced {
+    fn from(handle: Handle<StandardMaterial>) -> Self {
+        StandardMaterialInstanced {
+            handle,
+            material: None,
+        }
+    }
+}
+
+impl AsBindGroup for StandardMaterialInstanced {
+    type Data = StandardMaterialInstancedKey;
+
+    fn as_bind_group(
+        &self,
+        layout: &BindGroupLayout,
+        render_device: &RenderDevice,
+        images: &RenderAssets<Image>,
+        fallback_image: &FallbackImage,
+    ) -> Result<PreparedBindGroup<Self>, AsBindGroupError> {
+        let material = self
+            .material
+            .as_ref()
+            .ok_or(AsBindGroupError::RetryNextUpdate)?;
+
+        let prepared = material.as_bind_group(layout, render_device, images, fallback_image)?;
+
+        Ok(PreparedBindGroup {
+            bindings: prepared.bindings,
+            bind_group: prepared.bind_group,
+            data: self.into(),
+        })
+    }
+
+    fn bind_group_layout(render_device: &RenderDevice) -> BindGroupLayout {
+        StandardMaterial::bind_group_layout(render_device)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct StandardMaterialInstancedKey {
+    pub normal_map: bool,
+    pub cull_mode: Option<Face>,
+}
+
+impl From<&StandardMaterialInstanced> for StandardMaterialInstancedKey {
+    fn from(standard_material: &StandardMaterialInstanced) -> Self {
+        match &standard_material.material {
+            Some(material) => StandardMaterialInstancedKey {
+                normal_map: material.normal_map_texture.is_some(),
+                cull_mode: material.cull_mode,
+            },
+            None => Default::default(),
+        }
+    }
+}
+
+/// Batch key for [`StandardMaterialInstanced`]
+///
+/// Only one material bind group is bound per batch, so in addition to textures
+/// this captures every value that feeds the material uniform.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct StandardMaterialInstancedBatchKey {
+    pub base_color_texture: Option<Handle<Image>>,
+    pub emissive_texture: Option<Handle<Image>>,
+    pub metallic_roughness_texture: Option<Handle<Image>>,
+    pub normal_map_texture: Option<Handle<Image>>,
+    pub occlusion_texture: Option<Handle<Image>>,
+    pub alpha_cutoff: Option<u32>,
+    pub cull_mode: Option<Face>,
+    pub uniform: [u32; 12],
+    pub flags: [bool; 3],
+}
+
+impl PartialOrd for StandardMaterialInstancedBatchKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for StandardMaterialInstancedBatchKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (
+            &self.base_color_texture,
+            &self.emissive_texture,
+            &self.metallic_roughness_texture,
+            &self.normal_map_texture,
+            &self.occlusion_texture,
+            self.alpha_cutoff,
+            self.cull_mode.map(|cull_mode| cull_mode as usize),
+            self.uniform,
+            self.flags,
+        )
+            .cmp(&(
+                &other.base_color_texture,
+                &other.emissive_texture,
+                &other.metallic_roughness_texture,
+                &other.normal_map_texture,
+                &other.occlusion_texture,
+                other.alpha_cutoff,
+                other.cull_mode.map(|cull_mode| cull_mode as usize),
+                other.uniform,
+                other.flags,
+            ))
+    }
+}
+
+impl From<&StandardMaterialInstanced> for StandardMaterialInstancedBatchKey {
+    fn from(standard_material: &StandardMaterialInstanced) -> Self {
+        let material = if let Some(material) = &standard_material.material {
+            material
+        } else {
+            return Default::default();
+        };
+
+        let base_color = material.base_color.as_linear_rgba_f32();
+        let emissive = material.emissive.as_linear_rgba_f32();
+
+        StandardMaterialInstancedBatchKey {
+            base_color_texture: material.base_color_texture.as_ref().map(Handle::clone_weak),
+            emissive_texture: material.emissive_texture.as_ref().map(Handle::clone_weak),
+            metallic_roughness_texture: material
+                .metallic_roughness_texture
+                .as_ref()
+                .map(Handle::clone_weak),
+            normal_map_texture: material.normal_map_texture.as_ref().map(Handle::clone_weak),
+            occlusion_texture: material.occlusion_texture.as_ref().map(Handle::clone_weak),
+            alpha_cutoff: match material.alpha_mode {
+                AlphaMode::Mask(cutoff) => Some(cutoff.to_bits()),
+                _ => None,
+            },
+            cull_mode: material.cull_mode,
+            uniform: [
+                base_color[0].to_bits(),
+                base_color[1].to_bits(),
+                base_color[2].to_bits(),
+                base_color[3].to_bits(),
+                emissive[0].to_bits(),
+                emissive[1].to_bits(),
+                emissive[2].to_bits(),
+                emissive[3].to_bits(),
+                material.perceptual_roughness.to_bits(),
+                material.metallic.to_bits(),
+                material.reflectance.to_bits(),
+                material.depth_bias.to_bits(),
+            ],
+            flags: [
+                material.double_sided,
+                material.unlit,
+                material.flip_normal_map_y,
+            ],
+        }
+    }
+}
+
+impl AsBatch for StandardMaterialInstanced {
+    type BatchKey = StandardMaterialInstancedBatchKey;
+}
+
+impl MaterialInstanced for StandardMaterialInstanced {
+    type Instance = MeshInstance;
+
+    fn vertex_shader(_: &AssetServer) -> ShaderRef {
+        STANDARD_SHADER_HANDLE.typed().into()
+    }
+
+    fn fragment_shader(_: &AssetServer) -> ShaderRef {
+        STANDARD_SHADER_HANDLE.typed().into()
+    }
+
+    fn specialize(
+        _pipeline: &InstancedMaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        key: Self::Data,
+        _layout: &MeshVertexBufferLayout,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        if key.normal_map {
+            descriptor
+                .fragment
+                .as_mut()
+                .unwrap()
+                .shader_defs
+                .push(String::from("STANDARDMATERIAL_NORMAL_MAP"));
+        }
+        descriptor.primitive.cull_mode = key.cull_mode;
+        if let Some(label) = &mut descriptor.label {
+            *label = format!("pbr_{}", *label).into();
+        }
+        Ok(())
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        self.material
+            .as_ref()
+            .map(|material| material.alpha_mode)
+            .unwrap_or_default()
+    }
+
+    fn depth_bias(&self) -> f32 {
+        self.material
+            .as_ref()
+            .map(|material| material.depth_bias)
+            .unwrap_or_default()
+    }
+}
@@ -10,6 +10,8 @@ var in_texture: texture_2d<f32>;
 @binding(1)
 var in_sampler: sampler;
 
+#ifndef VERTEX_INSTANCES
+#ifndef SOA_INSTANCES
 #ifdef NO_STORAGE_BUFFERS_SUPPORT
 @group(2)
 @binding(0)
@@ -19,6 +21,8 @@ var<uniform> in_instances: ColorInstances;
 @binding(0)
 var<storage> in_instances: ColorInstances;
 #endif
+#endif
+#endif
 
 struct VertexInput {
     @builtin(instance_index) instance: u32,
@@ -37,8 +41,21 @@ struct VertexOutput {
 };
 
 @vertex
+#ifdef VERTEX_INSTANCES
+fn vertex(
+    in: VertexInput,
+    base_in: InstanceVertex,
+    instance_in: ColorInstanceVertex,
+) -> VertexOutput {
+    let instance = color_instance_from_vertex(base_in, instance_in);
+#else
 fn vertex(in: VertexInput) -> VertexOutput {
+#ifdef SOA_INSTANCES
+    let instance = load_color_instance(in.instance);
+#else
     let instance = in_instances.instances[in.instance];
+#endif
+#endif
 
     var out: VertexOutput;
     out.world_position = instance.base.transform * vec4<f32>(in.vertex, 1.0);
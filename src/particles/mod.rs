@@ -0,0 +1,121 @@
+pub mod over_life;
+pub mod particle_emitter;
+pub mod particle_emitter_bundle;
+pub mod plugin;
+
+use std::num::NonZeroU64;
+
+use bevy::{
+    ecs::system::lifetimeless::Read,
+    math::{Mat4, Vec3},
+    prelude::{Component, Handle, Image},
+    render::{
+        extract_component::ExtractComponent,
+        render_resource::{AsBindGroup, ShaderRef, ShaderSize, ShaderType},
+    },
+};
+
+use crate::prelude::{ColorMeshInstance, InstanceCompute, PARTICLES_SHADER_HANDLE};
+
+/// Simulation parameters for a [`ParticleEmitter`](crate::prelude::ParticleEmitter),
+/// laid out to match `ParticleUniform` in `particles.wgsl`
+#[derive(Debug, Default, Copy, Clone, PartialEq, ShaderType)]
+pub struct ParticleUniform {
+    /// Emitter's world transform, applied to spawn positions and velocities
+    pub transform: Mat4,
+    pub acceleration: Vec3,
+    /// Seconds since the last dispatch.
+    /// Written by the render world, as dispatches may be skipped or deferred
+    pub delta: f32,
+    pub velocity: Vec3,
+    pub speed: f32,
+    /// Shape parameters, see [`EmitterShape`](crate::prelude::EmitterShape)
+    pub shape_extents: Vec3,
+    pub shape: u32,
+    pub lifetime: f32,
+    pub lifetime_variance: f32,
+    /// Particles to spawn this dispatch, written by the render world
+    pub spawn_count: u32,
+    /// Incremented every frame, and stamped onto the particles a dispatch writes.
+    /// Written by the render world
+    pub frame: u32,
+    /// `frame` of the last dispatch, whose particles are live.
+    /// Written by the render world
+    pub last_frame: u32,
+}
+
+/// Per-frame input to the [`ParticleSimulation`] on the same entity.
+///
+/// Kept apart from the simulation so that its uniform bind group
+/// is only rebuilt when emitter settings change.
+#[derive(Debug, Default, Copy, Clone, Component)]
+pub struct ParticleFrame {
+    /// Seconds since the last frame
+    pub delta: f32,
+    /// Particles due to spawn this frame
+    pub spawn_count: u32,
+    /// Fractional particles carried over from previous frames' emission rate
+    pub spawn_remainder: f32,
+}
+
+impl ExtractComponent for ParticleFrame {
+    type Query = Read<Self>;
+
+    type Filter = ();
+
+    fn extract_component(item: bevy::ecs::query::QueryItem<Self::Query>) -> Self {
+        *item
+    }
+}
+
+/// Per-particle simulation state, carried between dispatches
+#[derive(Debug, Default, Copy, Clone, ShaderType)]
+pub struct ParticleState {
+    pub position: Vec3,
+    pub age: f32,
+    pub velocity: Vec3,
+    pub lifetime: f32,
+    pub frame: u32,
+}
+
+/// [`InstanceCompute`] driving the built-in particle shader.
+///
+/// Maintained from a [`ParticleEmitter`](crate::prelude::ParticleEmitter) on the same entity,
+/// with per-frame values filled in from its [`ParticleFrame`] by the render world.
+/// Each dispatch ages and moves live particles, kills expired ones and spawns new ones,
+/// compacting survivors to the front of the slice so that its tail serves as the free list.
+#[derive(Debug, Default, Clone, Component, AsBindGroup)]
+pub struct ParticleSimulation {
+    #[uniform(0)]
+    pub uniform: ParticleUniform,
+    /// Color over life in row 0 and size over life in row 1,
+    /// see [`bake_over_life`](crate::prelude::bake_over_life)
+    #[texture(1, filterable = false, visibility(compute))]
+    pub over_life: Option<Handle<Image>>,
+}
+
+impl From<&ParticleSimulation> for () {
+    fn from(_: &ParticleSimulation) -> Self {}
+}
+
+impl ExtractComponent for ParticleSimulation {
+    type Query = Read<Self>;
+
+    type Filter = ();
+
+    fn extract_component(item: bevy::ecs::query::QueryItem<Self::Query>) -> Self {
+        item.clone()
+    }
+}
+
+impl InstanceCompute for ParticleSimulation {
+    type Instance = ColorMeshInstance;
+
+    const STATE_SIZE: Option<NonZeroU64> = Some(ParticleState::SHADER_SIZE);
+
+    const LIVE_COUNT: bool = true;
+
+    fn shader() -> ShaderRef {
+        PARTICLES_SHADER_HANDLE.typed().into()
+    }
+}
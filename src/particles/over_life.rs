@@ -0,0 +1,77 @@
+use bevy::{
+    math::Vec4,
+    prelude::{Color, Image},
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+/// Number of texels each over-life curve is baked into
+pub const OVER_LIFE_RESOLUTION: u32 = 64;
+
+/// Sample piecewise-linear `(normalized age, value)` keys at `t`,
+/// holding the first and last values outside their range
+pub fn sample_over_life(keys: &[(f32, Vec4)], t: f32) -> Option<Vec4> {
+    let (first, last) = (keys.first()?, keys.last()?);
+
+    if t <= first.0 {
+        return Some(first.1);
+    }
+
+    Some(
+        keys.windows(2)
+            .find(|window| t <= window[1].0)
+            .map(|window| {
+                let (from, to) = (window[0], window[1]);
+                let span = to.0 - from.0;
+                let fac = if span > 0.0 { (t - from.0) / span } else { 1.0 };
+                from.1.lerp(to.1, fac)
+            })
+            .unwrap_or(last.1),
+    )
+}
+
+/// Bake color and size over life into an [`OVER_LIFE_RESOLUTION`] x 2 texture,
+/// with linear RGBA color in row 0 and size in row 1.
+///
+/// Empty curves default to white and unit size respectively.
+pub fn bake_over_life(size_over_life: &[(f32, f32)], color_over_life: &[(f32, Color)]) -> Image {
+    let keys = |mut keys: Vec<(f32, Vec4)>| {
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        keys
+    };
+
+    let color_keys = keys(
+        color_over_life
+            .iter()
+            .map(|(t, color)| (*t, Vec4::from(color.as_linear_rgba_f32())))
+            .collect(),
+    );
+
+    let size_keys = keys(
+        size_over_life
+            .iter()
+            .map(|(t, size)| (*t, Vec4::splat(*size)))
+            .collect(),
+    );
+
+    let data = [color_keys, size_keys]
+        .iter()
+        .flat_map(|keys| {
+            (0..OVER_LIFE_RESOLUTION).map(move |i| {
+                let t = i as f32 / (OVER_LIFE_RESOLUTION - 1) as f32;
+                sample_over_life(keys, t).unwrap_or(Vec4::ONE)
+            })
+        })
+        .flat_map(|texel| bytemuck::cast::<[f32; 4], [u8; 16]>(texel.to_array()))
+        .collect();
+
+    Image::new(
+        Extent3d {
+            width: OVER_LIFE_RESOLUTION,
+            height: 2,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba32Float,
+    )
+}
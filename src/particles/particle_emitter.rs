@@ -0,0 +1,149 @@
+use bevy::{
+    ecs::reflect::ReflectComponent,
+    math::Vec3,
+    prelude::{
+        default, Assets, Color, Component, DetectChanges, GlobalTransform, Image, Query, Res,
+        ResMut,
+    },
+    reflect::{FromReflect, Reflect},
+    time::Time,
+};
+
+use crate::prelude::{
+    bake_over_life, InstanceSlice, ParticleFrame, ParticleSimulation, ParticleUniform,
+};
+
+/// Region new particles are spawned in, relative to the emitter's transform
+#[derive(Debug, Default, Copy, Clone, PartialEq, Reflect, FromReflect)]
+pub enum EmitterShape {
+    /// Spawn at the origin, moving in a random direction
+    #[default]
+    Point,
+    /// Spawn inside a sphere, moving outward
+    Sphere { radius: f32 },
+    /// Spawn inside an axis-aligned box, moving in a random direction
+    Box { half_extents: Vec3 },
+    /// Spawn on a disc facing +Y, moving within `angle` radians of +Y
+    Cone { radius: f32, angle: f32 },
+}
+
+impl EmitterShape {
+    /// Shape index and parameters as read by `particles.wgsl`
+    pub fn shader_params(&self) -> (u32, Vec3) {
+        match *self {
+            EmitterShape::Point => (0, Vec3::ZERO),
+            EmitterShape::Sphere { radius } => (1, Vec3::new(radius, 0.0, 0.0)),
+            EmitterShape::Box { half_extents } => (2, half_extents),
+            EmitterShape::Cone { radius, angle } => (3, Vec3::new(radius, angle, 0.0)),
+        }
+    }
+}
+
+/// Spawns GPU-simulated particles into the [`InstanceSlice`] on the same entity,
+/// whose `instance_count` is the maximum number of live particles
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub struct ParticleEmitter {
+    /// Particles spawned per second
+    pub rate: f32,
+    /// Particles to spawn at once on the next frame, reset to zero once emitted
+    pub burst: u32,
+    pub shape: EmitterShape,
+    /// Seconds a particle lives for
+    pub lifetime: f32,
+    /// Maximum random deviation from `lifetime`, in seconds
+    pub lifetime_variance: f32,
+    /// Speed along the direction given by `shape`
+    pub speed: f32,
+    /// Velocity added to every new particle, in emitter space
+    pub velocity: Vec3,
+    /// World-space acceleration, i.e. gravity
+    pub acceleration: Vec3,
+    /// `(normalized age, size)` keys, linearly interpolated
+    pub size_over_life: Vec<(f32, f32)>,
+    /// `(normalized age, color)` keys, linearly interpolated
+    pub color_over_life: Vec<(f32, Color)>,
+}
+
+impl Default for ParticleEmitter {
+    fn default() -> Self {
+        Self {
+            rate: 10.0,
+            burst: 0,
+            shape: EmitterShape::default(),
+            lifetime: 1.0,
+            lifetime_variance: 0.0,
+            speed: 1.0,
+            velocity: Vec3::ZERO,
+            acceleration: Vec3::ZERO,
+            size_over_life: vec![],
+            color_over_life: vec![],
+        }
+    }
+}
+
+/// Feed emitter settings and curves into each [`ParticleSimulation`],
+/// and this frame's spawns into its [`ParticleFrame`]
+pub fn update_particle_emitters(
+    time: Res<Time>,
+    mut images: ResMut<Assets<Image>>,
+    mut query_emitter: Query<(
+        &mut ParticleEmitter,
+        &mut ParticleSimulation,
+        &mut ParticleFrame,
+        &InstanceSlice,
+        &GlobalTransform,
+    )>,
+) {
+    for (mut emitter, mut simulation, mut frame, instance_slice, transform) in
+        query_emitter.iter_mut()
+    {
+        if emitter.is_changed() {
+            let over_life = bake_over_life(&emitter.size_over_life, &emitter.color_over_life);
+            match simulation
+                .over_life
+                .as_ref()
+                .and_then(|handle| images.get_mut(handle))
+            {
+                Some(image) => *image = over_life,
+                None => simulation.over_life = Some(images.add(over_life)),
+            }
+        }
+
+        let delta = time.delta_seconds();
+        let spawn = frame.spawn_remainder + emitter.rate.max(0.0) * delta;
+        let spawn_count = spawn.floor();
+
+        *frame = ParticleFrame {
+            delta,
+            spawn_count: (spawn_count as u32)
+                .saturating_add(emitter.burst)
+                .min(instance_slice.instance_count as u32),
+            spawn_remainder: spawn - spawn_count,
+        };
+
+        // Emitting a burst isn't a settings change, so shouldn't rebake curves
+        if emitter.burst > 0 {
+            emitter.bypass_change_detection().burst = 0;
+        }
+
+        let (shape, shape_extents) = emitter.shape.shader_params();
+
+        // Only touch the simulation when settings change, as that rebuilds its bind group
+        let uniform = ParticleUniform {
+            transform: transform.compute_matrix(),
+            acceleration: emitter.acceleration,
+            velocity: emitter.velocity,
+            speed: emitter.speed,
+            shape_extents,
+            shape,
+            lifetime: emitter.lifetime,
+            lifetime_variance: emitter.lifetime_variance,
+            ..default()
+        };
+
+        if simulation.uniform != uniform {
+            simulation.uniform = uniform;
+        }
+    }
+}
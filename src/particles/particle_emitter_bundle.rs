@@ -0,0 +1,33 @@
+use bevy::prelude::{default, Bundle, GlobalTransform, Transform};
+
+use crate::prelude::{
+    ColorMeshInstance, InstanceSliceBundle, InstanceSliceLiveCount, MaterialInstanced,
+    ParticleEmitter, ParticleFrame, ParticleSimulation,
+};
+
+/// Components to create a particle emitter drawn with a [`ColorMeshInstance`] material
+#[derive(Bundle)]
+pub struct ParticleEmitterBundle<M: MaterialInstanced<Instance = ColorMeshInstance>> {
+    #[bundle]
+    pub instance_slice_bundle: InstanceSliceBundle<M>,
+    pub emitter: ParticleEmitter,
+    pub simulation: ParticleSimulation,
+    pub frame: ParticleFrame,
+    pub live_count: InstanceSliceLiveCount,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+impl<M: MaterialInstanced<Instance = ColorMeshInstance>> Default for ParticleEmitterBundle<M> {
+    fn default() -> Self {
+        Self {
+            instance_slice_bundle: default(),
+            emitter: default(),
+            simulation: default(),
+            frame: default(),
+            live_count: default(),
+            transform: default(),
+            global_transform: default(),
+        }
+    }
+}
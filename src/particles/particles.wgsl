@@ -0,0 +1,174 @@
+#import indirect_instancing::instance_struct
+#import indirect_instancing::color_instance_struct
+
+struct ParticleUniform {
+    transform: mat4x4<f32>,
+    acceleration: vec3<f32>,
+    delta: f32,
+    velocity: vec3<f32>,
+    speed: f32,
+    shape_extents: vec3<f32>,
+    shape: u32,
+    lifetime: f32,
+    lifetime_variance: f32,
+    spawn_count: u32,
+    frame: u32,
+    last_frame: u32,
+};
+
+struct ParticleState {
+    position: vec3<f32>,
+    age: f32,
+    velocity: vec3<f32>,
+    lifetime: f32,
+    frame: u32,
+};
+
+@group(0)
+@binding(0)
+var<uniform> in_uniform: ParticleUniform;
+
+@group(0)
+@binding(1)
+var over_life: texture_2d<f32>;
+
+@group(1)
+@binding(0)
+var<storage, read_write> out_instances: ColorInstances;
+
+@group(1)
+@binding(1)
+var<storage, read_write> live_count: atomic<u32>;
+
+@group(2)
+@binding(0)
+var<storage> in_state: array<ParticleState>;
+
+@group(2)
+@binding(1)
+var<storage, read_write> out_state: array<ParticleState>;
+
+let SHAPE_SPHERE: u32 = 1u;
+let SHAPE_BOX: u32 = 2u;
+let SHAPE_CONE: u32 = 3u;
+
+let TAU: f32 = 6.28318530718;
+
+fn hash(n: u32) -> f32 {
+    var x = n * 747796405u + 2891336453u;
+    x = ((x >> ((x >> 28u) + 4u)) ^ x) * 277803737u;
+    x = (x >> 22u) ^ x;
+    return f32(x) / 4294967295.0;
+}
+
+fn random_direction(seed: u32) -> vec3<f32> {
+    let z = hash(seed) * 2.0 - 1.0;
+    let phi = hash(seed + 1u) * TAU;
+    let r = sqrt(max(1.0 - z * z, 0.0));
+    return vec3<f32>(r * cos(phi), r * sin(phi), z);
+}
+
+// Linearly interpolate a row of the baked over-life texture
+fn sample_over_life(row: i32, t: f32) -> vec4<f32> {
+    let size = textureDimensions(over_life);
+    let x = clamp(t, 0.0, 1.0) * f32(size.x - 1);
+    let i = i32(floor(x));
+    let j = min(i + 1, size.x - 1);
+    let y = min(row, size.y - 1);
+    return mix(
+        textureLoad(over_life, vec2<i32>(i, y), 0),
+        textureLoad(over_life, vec2<i32>(j, y), 0),
+        fract(x),
+    );
+}
+
+fn spawn(seed: u32) -> ParticleState {
+    var position = vec3<f32>(0.0);
+    var direction = random_direction(seed);
+
+    let extents = in_uniform.shape_extents;
+    if (in_uniform.shape == SHAPE_SPHERE) {
+        position = direction * extents.x * pow(hash(seed + 2u), 1.0 / 3.0);
+    } else if (in_uniform.shape == SHAPE_BOX) {
+        position = (vec3<f32>(hash(seed + 2u), hash(seed + 3u), hash(seed + 4u)) * 2.0 - 1.0) * extents;
+    } else if (in_uniform.shape == SHAPE_CONE) {
+        let disc_radius = extents.x * sqrt(hash(seed + 2u));
+        let disc_angle = hash(seed + 3u) * TAU;
+        position = vec3<f32>(cos(disc_angle), 0.0, sin(disc_angle)) * disc_radius;
+
+        let cos_angle = mix(cos(extents.y), 1.0, hash(seed + 4u));
+        let sin_angle = sqrt(max(1.0 - cos_angle * cos_angle, 0.0));
+        let phi = hash(seed + 5u) * TAU;
+        direction = vec3<f32>(sin_angle * cos(phi), cos_angle, sin_angle * sin(phi));
+    }
+
+    let velocity = direction * in_uniform.speed + in_uniform.velocity;
+
+    var particle: ParticleState;
+    particle.position = (in_uniform.transform * vec4<f32>(position, 1.0)).xyz;
+    particle.velocity = (in_uniform.transform * vec4<f32>(velocity, 0.0)).xyz;
+    particle.age = 0.0;
+    particle.lifetime = in_uniform.lifetime + (hash(seed + 6u) * 2.0 - 1.0) * in_uniform.lifetime_variance;
+    particle.frame = in_uniform.frame;
+    return particle;
+}
+
+// Append a particle to the front of the slice, dropping it if the slice is full
+fn emit(particle: ParticleState) {
+    let max_instance = arrayLength(&out_instances.instances);
+
+    let index = atomicAdd(&live_count, 1u);
+    if (index >= max_instance) {
+        atomicSub(&live_count, 1u);
+        return;
+    }
+
+    out_state[index] = particle;
+
+    let t = particle.age / particle.lifetime;
+    let size = max(sample_over_life(1, t).x, 0.0001);
+    let p = particle.position;
+
+    out_instances.instances[index].base.transform = mat4x4<f32>(
+        vec4<f32>(size, 0.0, 0.0, 0.0),
+        vec4<f32>(0.0, size, 0.0, 0.0),
+        vec4<f32>(0.0, 0.0, size, 0.0),
+        vec4<f32>(p, 1.0),
+    );
+    out_instances.instances[index].base.inverse_transpose_model = mat4x4<f32>(
+        vec4<f32>(1.0 / size, 0.0, 0.0, -p.x / size),
+        vec4<f32>(0.0, 1.0 / size, 0.0, -p.y / size),
+        vec4<f32>(0.0, 0.0, 1.0 / size, -p.z / size),
+        vec4<f32>(0.0, 0.0, 0.0, 1.0),
+    );
+    out_instances.instances[index].color = sample_over_life(0, t);
+}
+
+@compute
+@workgroup_size(64)
+fn instances(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let max_instance = arrayLength(&out_instances.instances);
+
+    let instance_idx = invocation_id.x;
+    if (instance_idx >= max_instance) {
+        return;
+    }
+
+    // Particles from the previous dispatch were compacted to the front;
+    // anything not written by it is stale and treated as free
+    var particle = in_state[instance_idx];
+    if (particle.frame == in_uniform.last_frame) {
+        particle.age += in_uniform.delta;
+
+        if (particle.age < particle.lifetime) {
+            particle.velocity += in_uniform.acceleration * in_uniform.delta;
+            particle.position += particle.velocity * in_uniform.delta;
+            particle.frame = in_uniform.frame;
+            emit(particle);
+        }
+    }
+
+    if (instance_idx < in_uniform.spawn_count) {
+        emit(spawn((in_uniform.frame * max_instance + instance_idx) * 8u));
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+
+use bevy::{
+    asset::load_internal_asset,
+    prelude::{
+        default, warn, CoreStage, Entity, HandleUntyped, IntoSystemDescriptor, Plugin, Query, Res,
+        ResMut, Resource, Shader, With,
+    },
+    reflect::TypeUuid,
+    render::{
+        extract_component::ExtractComponentPlugin,
+        render_resource::{encase::UniformBuffer, OwnedBindingResource},
+        renderer::{RenderDevice, RenderQueue},
+        RenderApp, RenderStage,
+    },
+    transform::TransformSystem,
+};
+
+use crate::prelude::{
+    queue_compute_instances, update_particle_emitters, ColorInstancePlugin, EmitterShape,
+    InstanceComputeBackend, InstanceComputeBindGroups, InstanceComputePlugin,
+    InstanceComputeSchedule, InstanceSliceLiveCounts, ParticleEmitter, ParticleFrame,
+    ParticleSimulation, ParticleUniform,
+};
+
+pub const PARTICLES_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 7326189534061257130);
+
+/// GPU particle emitters, simulated by [`ParticleSimulation`]
+/// and drawn through any material using [`ColorMeshInstance`](crate::prelude::ColorMeshInstance).
+///
+/// Requires [`IndirectRenderingPlugin`](crate::prelude::IndirectRenderingPlugin).
+/// Particles rely on per-instance state and live counts, so aren't simulated
+/// under [`InstanceComputeBackend::Cpu`].
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        load_internal_asset!(
+            app,
+            PARTICLES_SHADER_HANDLE,
+            "particles.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.register_type::<EmitterShape>()
+            .register_type::<ParticleEmitter>();
+
+        if !app.is_plugin_added::<ColorInstancePlugin>() {
+            app.add_plugin(ColorInstancePlugin);
+        }
+
+        let backend = app
+            .world
+            .get_resource::<InstanceComputeBackend>()
+            .copied()
+            .unwrap_or_default();
+
+        if backend == InstanceComputeBackend::Cpu {
+            warn!("ParticlePlugin is unsupported under InstanceComputeBackend::Cpu");
+            return;
+        }
+
+        app.add_plugin(InstanceComputePlugin::<ParticleSimulation>::default())
+            .add_plugin(ExtractComponentPlugin::<ParticleFrame>::default())
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                update_particle_emitters.after(TransformSystem::TransformPropagate),
+            );
+
+        app.sub_app_mut(RenderApp)
+            .init_resource::<ParticleDispatches>()
+            .add_system_to_stage(RenderStage::Prepare, prepare_particle_simulations)
+            .add_system_to_stage(
+                RenderStage::Queue,
+                queue_particle_dispatches.after(queue_compute_instances::<ParticleSimulation>),
+            );
+    }
+}
+
+/// Render world record of each emitter's dispatches
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ParticleDispatch {
+    /// Incremented every frame, see [`ParticleUniform::frame`]
+    pub frame: u32,
+    /// `frame` of the last dispatch
+    pub last_frame: u32,
+    /// Time elapsed since the last dispatch
+    pub delta: f32,
+    /// Particles due to spawn since the last dispatch
+    pub spawn_count: u32,
+}
+
+/// [`ParticleDispatch`] of each emitter in the render world
+#[derive(Debug, Default, Resource)]
+pub struct ParticleDispatches(pub BTreeMap<Entity, ParticleDispatch>);
+
+/// Accumulate each emitter's frame into its next dispatch,
+/// carrying over time and spawns from frames it wasn't dispatched on
+pub fn prepare_particle_simulations(
+    mut dispatches: ResMut<ParticleDispatches>,
+    query_frame: Query<(Entity, &ParticleFrame), With<ParticleSimulation>>,
+) {
+    dispatches
+        .0
+        .retain(|entity, _| query_frame.contains(*entity));
+
+    for (entity, frame) in query_frame.iter() {
+        let dispatch = dispatches.0.entry(entity).or_default();
+
+        dispatch.frame = dispatch.frame.wrapping_add(1);
+        dispatch.delta += frame.delta;
+        dispatch.spawn_count = dispatch.spawn_count.saturating_add(frame.spawn_count);
+    }
+}
+
+/// Write per-frame values into the uniform of each emitter dispatched this frame,
+/// and zero its live count so the shader can re-append surviving and new particles.
+///
+/// The uniform buffer is updated in place, so its bind group is only rebuilt
+/// when the emitter's settings change.
+pub fn queue_particle_dispatches(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    compute_schedule: Res<InstanceComputeSchedule<ParticleSimulation>>,
+    bind_groups: Res<InstanceComputeBindGroups<ParticleSimulation>>,
+    mut dispatches: ResMut<ParticleDispatches>,
+    mut live_counts: ResMut<InstanceSliceLiveCounts>,
+    query_simulation: Query<(Entity, &ParticleSimulation)>,
+) {
+    for (entity, simulation) in query_simulation.iter() {
+        if !compute_schedule.dispatched.contains(&entity) {
+            continue;
+        }
+
+        let uniform_buffer = bind_groups.uniform.get(&entity).and_then(|bind_group| {
+            bind_group
+                .bindings
+                .iter()
+                .find_map(|binding| match binding {
+                    OwnedBindingResource::Buffer(buffer) => Some(buffer),
+                    _ => None,
+                })
+        });
+
+        let uniform_buffer = match uniform_buffer {
+            Some(uniform_buffer) => uniform_buffer,
+            None => continue,
+        };
+
+        let dispatch = dispatches.0.entry(entity).or_default();
+
+        let uniform = ParticleUniform {
+            delta: dispatch.delta,
+            spawn_count: dispatch.spawn_count,
+            frame: dispatch.frame,
+            last_frame: dispatch.last_frame,
+            ..simulation.uniform
+        };
+
+        let mut buffer = UniformBuffer::new(Vec::<u8>::new());
+        buffer.write(&uniform).unwrap();
+        render_queue.write_buffer(uniform_buffer, 0, buffer.as_ref());
+
+        *dispatch = ParticleDispatch {
+            frame: dispatch.frame,
+            last_frame: dispatch.frame,
+            ..default()
+        };
+
+        render_queue.write_buffer(
+            live_counts.get_or_create(&render_device, entity),
+            0,
+            bytemuck::bytes_of(&0u32),
+        );
+    }
+}
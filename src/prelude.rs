@@ -2,22 +2,31 @@ pub use crate::{
     colored_mesh_instance::{color_instance_bundle::*, mesh_instance_color::*, plugin::*, *},
     instancing::{
         indirect::*,
-        instance_slice::{instance_slice_bundle::*, *},
-        instance_compute::*,
+        instance_slice::{
+            immediate::*, instance_slice_bundle::*, instance_slice_data::*, live_count::*,
+            readback::*, *,
+        },
+        instance_compute::{bind_groups::*, cpu::*, run_mode::*, state::*, view::*, *},
         material::{
             instanced_material_pipeline::*, plugin::*,
             set_instanced_material_bind_group::*, material_instanced::*, systems::*, *,
         },
-        mesh_instance::{mesh_instance_bundle::*, *},
+        mesh_instance::{
+            affine_mesh_instance::*, bulk_instances::*, instance_data::*, mesh_instance_bundle::*,
+            quantized_mesh_instance::*, spline_instances::*, trs_mesh_instance::*, *,
+        },
         plugin::*,
-        render::{instance::*, instanced_mesh_pipeline::*, *},
+        render::{instance::*, instanced_mesh_pipeline::*, persistent_buffer::*, quantize::*, *},
         *,
     },
     materials::{
         basic_material::{plugin::*, *},
         custom_material::{custom_material::*, plugin::*, *},
+        standard_material::{auto_instancing::*, plugin::*, standard_material::*, *},
         texture_material::{plugin::*, texture_material::*, *},
         *,
     },
+    particles::{over_life::*, particle_emitter::*, particle_emitter_bundle::*, plugin::*, *},
+    layouts::{plugin::*, shape::*, *},
     *,
 };
@@ -0,0 +1,196 @@
+//! Checks the code generated by `#[derive(Instance)]`
+
+use bevy::{
+    math::{Mat4, Vec3, Vec4},
+    prelude::{
+        Color, Component, ComputedVisibility, GlobalTransform, Handle, Mesh, Transform, World,
+    },
+    render::render_resource::{encase::StorageBuffer, ShaderImport, ShaderType, VertexFormat},
+};
+
+use bevy_instancing::prelude::{
+    GpuMeshInstance, Instance, InstanceColor, InstanceWgsl, MeshInstance, INSTANCE_VERTEX_LOCATION,
+};
+
+/// Offset into the pulse cycle of an instance, in radians
+#[derive(Debug, Default, Copy, Clone, Component)]
+struct PulsePhase(f32);
+
+impl From<PulsePhase> for f32 {
+    fn from(phase: PulsePhase) -> Self {
+        phase.0
+    }
+}
+
+#[derive(Debug, Default, Clone, Component, Instance)]
+#[instance(import_path = "tests::pulse_instance")]
+struct PulseInstance {
+    #[instance(base)]
+    base: MeshInstance,
+    #[instance(component = InstanceColor)]
+    color: Vec4,
+    #[instance(component = PulsePhase)]
+    phase: f32,
+}
+
+#[derive(Debug, Default, Clone, Component, Instance)]
+#[instance(
+    import_path = "tests::renamed_instance",
+    struct_name = "Renamed",
+    array_name = "RenamedArray",
+    soa_accessor = "load_renamed",
+    vertex_struct = "RenamedVertex",
+    vertex_accessor = "renamed_from_vertex"
+)]
+struct RenamedInstance {
+    #[instance(base)]
+    base: MeshInstance,
+    #[instance(component = PulsePhase)]
+    phase: f32,
+}
+
+#[derive(Debug, Default, Clone, Component, Instance)]
+#[instance(import_path = "tests::base_only_instance")]
+struct BaseOnlyInstance {
+    #[instance(base)]
+    base: MeshInstance,
+}
+
+#[test]
+fn gpu_struct_follows_the_base_layout() {
+    // The base is 144 bytes; the vec4 follows it and the f32 pads the struct to 16 bytes
+    assert_eq!(GpuMeshInstance::min_size().get(), 144);
+    assert_eq!(GpuPulseInstance::min_size().get(), 176);
+
+    let instance = PulseInstance {
+        base: MeshInstance {
+            mesh: Handle::default(),
+            transform: Mat4::from_translation(Vec3::X),
+        },
+        color: Vec4::new(0.25, 0.5, 0.75, 1.0),
+        phase: 2.0,
+    };
+    let prepared = PulseInstance::prepare_instance(&instance, 7);
+    assert_eq!(prepared.base.mesh, 7);
+    assert_eq!(prepared.base.transform, instance.base.transform);
+
+    let mut buffer = StorageBuffer::new(Vec::<u8>::new());
+    buffer.write(&prepared).unwrap();
+    let bytes = buffer.into_inner();
+
+    assert_eq!(bytes.len(), 176);
+    assert_eq!(&bytes[0..4], 7u32.to_le_bytes());
+    assert_eq!(&bytes[144..160], bytemuck::bytes_of(&instance.color));
+    assert_eq!(&bytes[160..164], 2.0f32.to_le_bytes());
+}
+
+#[test]
+fn streams_and_attributes_follow_the_base() {
+    let base_streams = MeshInstance::streams();
+    let streams = PulseInstance::streams();
+
+    assert_eq!(streams.len(), base_streams.len() + 2);
+    assert_eq!(streams[..base_streams.len()], base_streams[..]);
+    assert_eq!(
+        (streams[3].offset, streams[3].size, streams[3].stride),
+        (144, 16, 16)
+    );
+    assert_eq!(
+        (streams[4].offset, streams[4].size, streams[4].stride),
+        (160, 4, 4)
+    );
+
+    let base_attributes = MeshInstance::vertex_attributes(INSTANCE_VERTEX_LOCATION);
+    let attributes = PulseInstance::vertex_attributes(INSTANCE_VERTEX_LOCATION);
+    let location = INSTANCE_VERTEX_LOCATION + base_attributes.len() as u32;
+
+    assert_eq!(attributes.len(), base_attributes.len() + 2);
+    assert_eq!(attributes[..base_attributes.len()], base_attributes[..]);
+    assert_eq!(
+        attributes[base_attributes.len()..]
+            .iter()
+            .map(|attribute| (attribute.shader_location, attribute.format))
+            .collect::<Vec<_>>(),
+        vec![
+            (location, VertexFormat::Float32x4),
+            (location + 1, VertexFormat::Float32),
+        ]
+    );
+}
+
+#[test]
+fn wgsl_names_default_to_the_struct_name() {
+    assert_eq!(PulseInstance::IMPORT_PATH, "tests::pulse_instance");
+    assert_eq!(PulseInstance::STRUCT_NAME, "PulseInstanceData");
+    assert_eq!(PulseInstance::ARRAY_NAME, "PulseInstances");
+    assert_eq!(PulseInstance::SOA_ACCESSOR, "load_pulse_instance");
+    assert_eq!(PulseInstance::VERTEX_ACCESSOR, "pulse_instance_from_vertex");
+    assert_eq!(
+        PulseInstance::vertex_structs(),
+        vec!["InstanceVertex", "PulseInstanceVertex"]
+    );
+}
+
+#[test]
+fn wgsl_names_can_be_overridden() {
+    assert_eq!(RenamedInstance::IMPORT_PATH, "tests::renamed_instance");
+    assert_eq!(RenamedInstance::STRUCT_NAME, "Renamed");
+    assert_eq!(RenamedInstance::ARRAY_NAME, "RenamedArray");
+    assert_eq!(RenamedInstance::SOA_ACCESSOR, "load_renamed");
+    assert_eq!(RenamedInstance::VERTEX_ACCESSOR, "renamed_from_vertex");
+    assert_eq!(
+        RenamedInstance::vertex_structs(),
+        vec!["InstanceVertex", "RenamedVertex"]
+    );
+}
+
+#[test]
+fn base_only_instances_reuse_the_base_vertex_structs() {
+    assert_eq!(
+        BaseOnlyInstance::vertex_structs(),
+        MeshInstance::vertex_structs()
+    );
+    assert_eq!(BaseOnlyInstance::streams(), MeshInstance::streams());
+}
+
+#[test]
+fn shader_imports_the_base() {
+    let (_, shader) = PulseInstance::shader().unwrap();
+
+    assert_eq!(
+        shader.import_path(),
+        Some(&ShaderImport::Custom("tests::pulse_instance".into()))
+    );
+    assert!(shader
+        .imports()
+        .any(|import| *import == ShaderImport::Custom(MeshInstance::IMPORT_PATH.into())));
+
+    // Handles are derived from the import path, so distinct instances don't collide
+    let (renamed, _) = RenamedInstance::shader().unwrap();
+    assert_ne!(PulseInstance::shader().unwrap().0, renamed);
+}
+
+#[test]
+fn extraction_reads_each_component() {
+    let mut world = World::new();
+    let transform = Transform::from_translation(Vec3::new(1.0, 2.0, 3.0));
+    world.spawn((
+        Handle::<Mesh>::default(),
+        GlobalTransform::from(transform),
+        ComputedVisibility::INVISIBLE,
+        InstanceColor(Color::rgba(0.25, 0.5, 0.75, 1.0)),
+        PulsePhase(2.0),
+    ));
+
+    let mut query = world.query::<<PulseInstance as Instance>::Query>();
+    let item = query.single(&world);
+    let instance = PulseInstance::extract_instance(item);
+
+    // Visibility is only computed by bevy's own systems, so the base hides the instance
+    assert_eq!(instance.base.transform, Mat4::ZERO);
+    assert_eq!(
+        instance.color,
+        Vec4::from(InstanceColor(Color::rgba(0.25, 0.5, 0.75, 1.0)))
+    );
+    assert_eq!(instance.phase, 2.0);
+}